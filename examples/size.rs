@@ -0,0 +1,54 @@
+//! Smallest firmware drawing with the driver, measured by
+//! `scripts/size.sh`
+//!
+//! The driver lives in a `static`, so its RAM shows in `.bss`. On the host
+//! this builds as a plain binary, for the bare-metal targets it is the
+//! whole firmware.
+#![cfg_attr(target_os = "none", no_std, no_main)]
+
+#[path = "size/stubs.rs"]
+mod stubs;
+
+use core::mem::MaybeUninit;
+use st7567::ST7567;
+use stubs::{Delay, Gpio, Spi};
+
+static mut DISPLAY: MaybeUninit<ST7567<Gpio, Gpio, Spi>> = MaybeUninit::uninit();
+
+fn run() {
+    // Only touched here, once
+    let display = unsafe {
+        let display = &mut *core::ptr::addr_of_mut!(DISPLAY);
+        display.write(ST7567::new(Spi, Gpio, Gpio))
+    };
+    let _ = display.reset(&mut Delay);
+    let _ = display.init();
+    display.clear();
+    display.fill_region(8, 8, 32, 16, true);
+    display.set_pixel(100, 40, true);
+    let _ = display.show();
+    #[cfg(feature = "shadow")]
+    let _ = display.show_changed();
+}
+
+#[cfg(target_os = "none")]
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    loop {
+        core::hint::spin_loop();
+    }
+}
+
+#[cfg(target_os = "none")]
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+    run();
+    loop {
+        core::hint::spin_loop();
+    }
+}
+
+#[cfg(not(target_os = "none"))]
+fn main() {
+    run();
+}
@@ -0,0 +1,54 @@
+//! Bufferless firmware, writing pages straight to the controller, measured
+//! by `scripts/size.sh`
+//!
+//! See the `size` example, this one draws a line of 8x8 glyphs from a
+//! stack array instead of keeping a frame.
+#![cfg_attr(target_os = "none", no_std, no_main)]
+
+#[path = "size/stubs.rs"]
+mod stubs;
+
+use core::mem::MaybeUninit;
+use st7567::{NoBuffer, NoResetPin, ST7567};
+use stubs::{Delay, Gpio, Spi};
+
+static mut DISPLAY: MaybeUninit<ST7567<Gpio, NoResetPin, Spi, NoBuffer>> = MaybeUninit::uninit();
+
+fn run() {
+    // Only touched here, once
+    let display = unsafe {
+        let display = &mut *core::ptr::addr_of_mut!(DISPLAY);
+        display.write(ST7567::with_buffer_without_reset(Spi, Gpio, NoBuffer))
+    };
+    let _ = display.reset(&mut Delay);
+    let _ = display.init();
+    let mut line = [0u8; 64];
+    for (column, byte) in line.iter_mut().enumerate() {
+        *byte = if column % 8 < 6 { 0x7e } else { 0 };
+    }
+    for page in 0..8 {
+        let _ = display.write_page(page, 32, &line);
+    }
+}
+
+#[cfg(target_os = "none")]
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    loop {
+        core::hint::spin_loop();
+    }
+}
+
+#[cfg(target_os = "none")]
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+    run();
+    loop {
+        core::hint::spin_loop();
+    }
+}
+
+#[cfg(not(target_os = "none"))]
+fn main() {
+    run();
+}
@@ -0,0 +1,50 @@
+/* C bindings for the st7567 crate, built with the `ffi` feature:
+ *
+ *     cargo rustc --release --features ffi --crate-type cdylib
+ *
+ * Functions returning an int return ST7567_OK on success, or a negative
+ * error code. Handles are not thread safe.
+ */
+#ifndef ST7567_H
+#define ST7567_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define ST7567_OK 0
+/* Null handle or pointer, or out of range argument */
+#define ST7567_EINVAL (-1)
+/* Error talking to the SPI device or driving a GPIO line */
+#define ST7567_EIO (-2)
+
+#define ST7567_WIDTH 128
+#define ST7567_HEIGHT 64
+/* One bit per pixel, 8 pages of 128 vertical bytes, LSB at the top */
+#define ST7567_BUFFER_SIZE 1024
+
+typedef struct St7567Handle St7567Handle;
+
+/* e.g. st7567_open("/dev/spidev0.1", "/dev/gpiochip0", 6, 5), NULL on error */
+St7567Handle *st7567_open(const char *spidev, const char *gpiochip,
+                          uint32_t dc_line, uint32_t rst_line);
+void st7567_close(St7567Handle *handle);
+
+/* Reset and initialize the controller */
+int st7567_init(St7567Handle *handle);
+int st7567_set_pixel(St7567Handle *handle, uint32_t x, uint32_t y, bool value);
+/* Copy a whole frame of ST7567_BUFFER_SIZE bytes into the framebuffer */
+int st7567_blit(St7567Handle *handle, const uint8_t *data, size_t len);
+int st7567_show(St7567Handle *handle);
+/* Set the contrast, 0 to 63, ST7567_EINVAL above */
+int st7567_set_contrast(St7567Handle *handle, uint8_t value);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* ST7567_H */
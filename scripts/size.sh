@@ -0,0 +1,36 @@
+#!/bin/sh
+# Flash and RAM used by the driver on a Cortex-M0, per feature set
+#
+# Builds the `size` and `size_bufferless` examples for thumbv6m-none-eabi,
+# optimized for size, and prints what they take: flash is text plus
+# initialized data, RAM is data plus bss. Needs the target and binutils:
+#
+#   rustup target add thumbv6m-none-eabi
+#   scripts/size.sh
+set -eu
+
+target=thumbv6m-none-eabi
+cd "$(dirname "$0")/.."
+
+export CARGO_PROFILE_RELEASE_OPT_LEVEL=s
+export CARGO_PROFILE_RELEASE_LTO=true
+export CARGO_PROFILE_RELEASE_CODEGEN_UNITS=1
+
+measure() {
+    example=$1
+    label=$2
+    features=${3-$2}
+    cargo build --quiet --release --target "$target" --no-default-features \
+        --features "$features" --example "$example"
+    size "target/$target/release/examples/$example" | awk \
+        -v name="$example" -v label="$label" \
+        'NR == 2 { printf "%-16s %-10s %6d %6d\n", name, label, $1 + $2, $2 + $3 }'
+}
+
+printf "%-16s %-10s %6s %6s\n" example features flash ram
+measure size none ""
+measure size shadow
+measure size_bufferless none ""
+# Every no_std default feature, none of them called into
+measure size defaults \
+    "region,bitmap,strip-chart,render,factory,events,auto-show,three-wire,parallel,bitbang,timing"
@@ -0,0 +1,264 @@
+//! Image assets packed at build time
+//!
+//! Icons drawn in an image editor end up as PNG or BMP files, while the
+//! driver wants packed page-layout bytes (see [`bitmap`](crate::bitmap)).
+//! With the `build-assets` feature enabled on the build dependency, a build
+//! script converts every image of a directory into Rust constants:
+//!
+//! ```toml
+//! [build-dependencies]
+//! st7567 = { version = "0.1", default-features = false, features = ["build-assets"] }
+//! ```
+//!
+//! ```no_run
+//! // build.rs
+//! use st7567::assets;
+//! use st7567::render::Conversion;
+//!
+//! fn main() {
+//!     assets::build("assets", "assets.rs", Conversion::Threshold(128)).unwrap();
+//! }
+//! ```
+//!
+//! ```ignore
+//! // src/main.rs
+//! include!(concat!(env!("OUT_DIR"), "/assets.rs"));
+//!
+//! // An 8 pixel high icon is one page
+//! display.write_page(0, 120, &BATTERY)?;
+//! let battery = Bitmap::new(&BATTERY, BATTERY_WIDTH, BATTERY_HEIGHT);
+//! ```
+//!
+//! `assets/battery.png` gives `BATTERY`, `BATTERY_WIDTH` and
+//! `BATTERY_HEIGHT`. Dark pixels are turned on, transparent ones are off,
+//! and [`Conversion`] picks between a threshold and the ordered dither of
+//! the [`render`](crate::render) module. The image decoder is only built for
+//! the build script, firmware depending on the driver does not link it.
+use crate::render::{dither, Conversion};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Error converting an asset directory.
+#[derive(Debug)]
+pub enum AssetError {
+    Io(io::Error),
+    Image(PathBuf, image::ImageError),
+    /// Two files give the same constant name
+    DuplicateName(String),
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::Io(e) => write!(f, "cannot read assets: {}", e),
+            AssetError::Image(path, e) => write!(f, "cannot decode {}: {}", path.display(), e),
+            AssetError::DuplicateName(name) => write!(f, "several assets named {}", name),
+        }
+    }
+}
+
+impl std::error::Error for AssetError {}
+
+impl From<io::Error> for AssetError {
+    fn from(e: io::Error) -> Self {
+        AssetError::Io(e)
+    }
+}
+
+/// Pack `width` x `height` luminance values, row by row, into page layout.
+///
+/// Missing values count as white.
+pub fn pack(luma: &[u8], width: usize, height: usize, conversion: Conversion) -> Vec<u8> {
+    let pages = height.div_ceil(8);
+    let mut packed = vec![0; width.saturating_mul(pages)];
+    for y in 0..height {
+        for x in 0..width {
+            let value = luma
+                .get(y.saturating_mul(width).saturating_add(x))
+                .copied()
+                .unwrap_or(u8::MAX);
+            let on = match conversion {
+                Conversion::Dither => dither(x, y, value),
+                Conversion::Threshold(threshold) => value < threshold,
+            };
+            if let Some(byte) = packed.get_mut((y / 8).saturating_mul(width).saturating_add(x)) {
+                if on {
+                    *byte |= 1 << (y % 8);
+                }
+            }
+        }
+    }
+    packed
+}
+
+/// Decode the image at `path` and pack it, giving its width, height and
+/// bytes.
+pub fn convert_file(
+    path: &Path,
+    conversion: Conversion,
+) -> Result<(usize, usize, Vec<u8>), AssetError> {
+    let image = image::open(path)
+        .map_err(|e| AssetError::Image(path.to_path_buf(), e))?
+        .to_luma_alpha8();
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let luma: Vec<u8> = image
+        .pixels()
+        .map(|pixel| {
+            let [value, alpha] = pixel.0;
+            if alpha < 0x80 {
+                u8::MAX
+            } else {
+                value
+            }
+        })
+        .collect();
+    Ok((width, height, pack(&luma, width, height, conversion)))
+}
+
+/// Constant name for an asset file: its stem in upper snake case.
+fn const_name(path: &Path) -> String {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut name: String = stem
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+/// Rust source declaring the constants of every PNG and BMP file of `dir`,
+/// in file name order. Other files are skipped.
+pub fn generate(dir: &Path, conversion: Conversion) -> Result<String, AssetError> {
+    let mut files = BTreeMap::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+        if matches!(extension.as_deref(), Some("png") | Some("bmp")) {
+            files.insert(path.file_name().unwrap_or_default().to_owned(), path);
+        }
+    }
+    let mut names = Vec::new();
+    let mut source = String::from("// Generated by st7567::assets, do not edit\n");
+    for path in files.values() {
+        let name = const_name(path);
+        if names.contains(&name) {
+            return Err(AssetError::DuplicateName(name));
+        }
+        let (width, height, bytes) = convert_file(path, conversion)?;
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        source.push_str(&format!(
+            "\n/// `{}`, {}x{}\npub const {}: [u8; {}] = {:?};\n",
+            file_name,
+            width,
+            height,
+            name,
+            bytes.len(),
+            bytes
+        ));
+        source.push_str(&format!("pub const {}_WIDTH: usize = {};\n", name, width));
+        source.push_str(&format!("pub const {}_HEIGHT: usize = {};\n", name, height));
+        names.push(name);
+    }
+    Ok(source)
+}
+
+/// Build script entry point: convert the images of `dir` into
+/// `$OUT_DIR/out_file`, and rerun the build script when they change.
+pub fn build(
+    dir: impl AsRef<Path>,
+    out_file: impl AsRef<Path>,
+    conversion: Conversion,
+) -> Result<(), AssetError> {
+    let dir = dir.as_ref();
+    let out_dir = std::env::var_os("OUT_DIR")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "OUT_DIR is not set"))?;
+    let source = generate(dir, conversion)?;
+    fs::write(Path::new(&out_dir).join(out_file), source)?;
+    println!("cargo:rerun-if-changed={}", dir.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma, LumaA, Rgb, RgbImage};
+
+    #[test]
+    fn test_pack() {
+        // A 3x10 image, dark on the first column and the last row
+        let mut luma = vec![0xff; 30];
+        for y in 0..10 {
+            luma[y * 3] = 0;
+        }
+        luma[27..].fill(0x10);
+        let packed = pack(&luma, 3, 10, Conversion::Threshold(0x80));
+        assert_eq!(packed, [0xff, 0x00, 0x00, 0x03, 0x02, 0x02]);
+
+        // Mid gray is dithered to half the pixels, short input is white
+        let packed = pack(&[0x80; 64], 8, 8, Conversion::Dither);
+        let on: u32 = packed.iter().map(|byte| byte.count_ones()).sum();
+        assert_eq!(on, 32);
+        assert_eq!(pack(&[], 2, 3, Conversion::Dither), [0, 0]);
+    }
+
+    #[test]
+    fn test_generate() {
+        let dir = std::env::temp_dir().join(format!("st7567-assets-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut arrow = GrayImage::from_pixel(4, 8, Luma([0xff]));
+        arrow.put_pixel(0, 0, Luma([0]));
+        arrow.put_pixel(3, 7, Luma([0]));
+        arrow.save(dir.join("arrow-up.png")).unwrap();
+        // Transparent pixels are off whatever their color
+        let mut dot = image::GrayAlphaImage::from_pixel(2, 2, LumaA([0, 0]));
+        dot.put_pixel(1, 1, LumaA([0, 0xff]));
+        dot.save(dir.join("2dot.png")).unwrap();
+        RgbImage::from_pixel(1, 1, Rgb([0, 0, 0]))
+            .save(dir.join("block.bmp"))
+            .unwrap();
+        fs::write(dir.join("notes.txt"), "not an image").unwrap();
+
+        let source = generate(&dir, Conversion::Threshold(0x80)).unwrap();
+        assert_eq!(
+            source,
+            "// Generated by st7567::assets, do not edit\n\
+             \n/// `2dot.png`, 2x2\npub const _2DOT: [u8; 2] = [0, 2];\n\
+             pub const _2DOT_WIDTH: usize = 2;\npub const _2DOT_HEIGHT: usize = 2;\n\
+             \n/// `arrow-up.png`, 4x8\npub const ARROW_UP: [u8; 4] = [1, 0, 0, 128];\n\
+             pub const ARROW_UP_WIDTH: usize = 4;\npub const ARROW_UP_HEIGHT: usize = 8;\n\
+             \n/// `block.bmp`, 1x1\npub const BLOCK: [u8; 1] = [1];\n\
+             pub const BLOCK_WIDTH: usize = 1;\npub const BLOCK_HEIGHT: usize = 1;\n"
+        );
+
+        RgbImage::new(1, 1).save(dir.join("block.png")).unwrap();
+        assert!(matches!(
+            generate(&dir, Conversion::Dither),
+            Err(AssetError::DuplicateName(name)) if name == "BLOCK"
+        ));
+        fs::remove_file(dir.join("block.bmp")).unwrap();
+        fs::write(dir.join("block.png"), "garbage").unwrap();
+        assert!(matches!(
+            generate(&dir, Conversion::Dither),
+            Err(AssetError::Image(path, _)) if path.ends_with("block.png")
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(
+            generate(&dir, Conversion::Dither),
+            Err(AssetError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,104 @@
+//! Flushing at a fixed cadence, without calling `show()`
+//!
+//! [`ST7567::auto_show`] runs the application loop: it calls a closure that
+//! draws into the framebuffer, then sends the frame if it changed, and
+//! waits for the next tick:
+//!
+//! ```ignore
+//! display.auto_show(&mut delay, 50, |display| {
+//!     display.clear();
+//!     draw_clock(display, rtc.now());
+//!     ControlFlow::Continue(())
+//! })?;
+//! ```
+//!
+//! The loop holds the display borrowed, so the closure is the only place to
+//! draw while it runs. Only what changed is sent: with the `shadow` feature,
+//! the frame is compared with the shadow copy of the controller RAM and
+//! only the changed columns are sent. Otherwise the pages drawn to since
+//! the last flush are sent, as with [`ST7567::show_dirty`], which costs no
+//! RAM beyond the driver state.
+use crate::transport::Transport;
+use crate::{Error, Pin, PixelBuffer, ST7567};
+use core::ops::ControlFlow;
+use embedded_hal::delay::DelayNs;
+
+impl<DC: Pin, RST: Pin, S: Transport, B: PixelBuffer, const W: usize, const H: usize>
+    ST7567<DC, RST, S, B, W, H>
+{
+    /// Call `tick` then flush what it drew, every `period_ms`, until it
+    /// returns [`ControlFlow::Break`], see the [module](self) docs.
+    ///
+    /// The period is the wait between two ticks: the time spent drawing and
+    /// flushing comes on top of it. The frame drawn by the last tick is
+    /// flushed before returning. Stops at the first error, which is
+    /// returned.
+    pub fn auto_show<D: DelayNs, F: FnMut(&mut Self) -> ControlFlow<()>>(
+        &mut self,
+        delay: &mut D,
+        period_ms: u32,
+        mut tick: F,
+    ) -> Result<(), Error<DC, RST, S>> {
+        loop {
+            let flow = tick(self);
+            #[cfg(feature = "shadow")]
+            {
+                if !self.verify_frame() {
+                    self.show_changed()?;
+                }
+            }
+            #[cfg(not(feature = "shadow"))]
+            {
+                if self.is_dirty() {
+                    self.show_dirty()?;
+                }
+            }
+            if flow.is_break() {
+                return Ok(());
+            }
+            delay.delay_ms(period_ms);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{create_test_st7567, MockDelay};
+
+    #[test]
+    fn test_auto_show() {
+        let mut st7567 = create_test_st7567();
+        let mut delay = MockDelay::new();
+        let mut ticks = 0;
+        let mut sent = Vec::new();
+        let result = st7567.auto_show(&mut delay, 20, |display| {
+            sent.push(display.spi.get_written_data().len());
+            display.spi.clear_written_data();
+            ticks += 1;
+            // Draw on the first and third ticks only
+            if ticks == 1 || ticks == 3 {
+                display.set_pixel(ticks, 0, true);
+            }
+            if ticks == 4 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+        assert!(result.is_ok());
+        assert_eq!(delay.total_ms(), 3 * 20);
+        // Nothing sent after the unchanged second and fourth ticks
+        assert!(sent[1] > 0);
+        assert_eq!(sent[2], 0);
+        assert!(sent[3] > 0);
+        assert!(st7567.spi.get_written_data().is_empty());
+
+        // Errors stop the loop
+        let mut st7567 = create_test_st7567();
+        st7567.spi.set_fail(true);
+        st7567.set_pixel(0, 0, true);
+        let result = st7567.auto_show(&mut delay, 20, |_| ControlFlow::Continue(()));
+        assert!(matches!(result, Err(Error::SpiError(_))));
+    }
+}
@@ -0,0 +1,263 @@
+//! Show an image or a test pattern on a Pimoroni GFX HAT
+//!
+//! ```text
+//! st7567-show image.pbm --contrast 40 --rotate 180
+//! st7567-show --pattern checkerboard
+//! ```
+//!
+//! Images are PBM files, plain (P1) or raw (P4), drawn from the top left
+//! corner and cropped to the screen.
+use st7567::linux::{LinuxDelay, LinuxST7567};
+use st7567::{Rotation, ST7567};
+use std::error::Error;
+use std::process;
+
+const USAGE: &str = "usage: st7567-show [IMAGE.pbm] [--contrast 0-63] [--rotate 0|90|180|270]
+                   [--pattern on|checkerboard|border] [--invert]";
+
+/// Test patterns, in drawing coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pattern {
+    On,
+    Checkerboard,
+    Border,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Options {
+    image: Option<String>,
+    contrast: Option<u8>,
+    rotation: Rotation,
+    pattern: Option<Pattern>,
+    invert: bool,
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--contrast" => {
+                let contrast = value()?;
+                match contrast.parse() {
+                    Ok(contrast) if contrast <= 63 => options.contrast = Some(contrast),
+                    _ => return Err(format!("invalid contrast: {}", contrast)),
+                }
+            }
+            "--rotate" => {
+                options.rotation = match value()?.as_str() {
+                    "0" => Rotation::Rotate0,
+                    "90" => Rotation::Rotate90,
+                    "180" => Rotation::Rotate180,
+                    "270" => Rotation::Rotate270,
+                    other => return Err(format!("invalid rotation: {}", other)),
+                }
+            }
+            "--pattern" => {
+                options.pattern = match value()?.as_str() {
+                    "on" => Some(Pattern::On),
+                    "checkerboard" => Some(Pattern::Checkerboard),
+                    "border" => Some(Pattern::Border),
+                    other => return Err(format!("unknown pattern: {}", other)),
+                }
+            }
+            "--invert" => options.invert = true,
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+            _ if options.image.is_none() => options.image = Some(arg),
+            _ => return Err(format!("unexpected argument: {}", arg)),
+        }
+    }
+    if options.image.is_some() && options.pattern.is_some() {
+        return Err("give either an image or a pattern".to_string());
+    }
+    Ok(options)
+}
+
+/// A decoded PBM image, `true` for black pixels.
+#[derive(Debug, PartialEq, Eq)]
+struct Pbm {
+    width: usize,
+    height: usize,
+    pixels: Vec<bool>,
+}
+
+impl Pbm {
+    fn get(&self, x: usize, y: usize) -> bool {
+        x < self.width && self.pixels.get(y * self.width + x) == Some(&true)
+    }
+}
+
+/// Next whitespace separated header token, skipping comments. Returns the
+/// token and the rest of the file.
+fn token(bytes: &[u8]) -> Option<(&[u8], &[u8])> {
+    let mut rest = bytes;
+    loop {
+        match rest.first()? {
+            b'#' => {
+                let end = rest.iter().position(|&b| b == b'\n')?;
+                rest = &rest[end..];
+            }
+            b if b.is_ascii_whitespace() => rest = &rest[1..],
+            _ => break,
+        }
+    }
+    let end = rest
+        .iter()
+        .position(|b| b.is_ascii_whitespace())
+        .unwrap_or(rest.len());
+    Some(rest.split_at(end))
+}
+
+fn number(bytes: &[u8]) -> Option<(usize, &[u8])> {
+    let (token, rest) = token(bytes)?;
+    let number = std::str::from_utf8(token).ok()?.parse().ok()?;
+    Some((number, rest))
+}
+
+fn parse_pbm(bytes: &[u8]) -> Result<Pbm, String> {
+    let invalid = || "invalid PBM file".to_string();
+    let (magic, rest) = token(bytes).ok_or_else(invalid)?;
+    let (width, rest) = number(rest).ok_or_else(invalid)?;
+    let (height, rest) = number(rest).ok_or_else(invalid)?;
+    let count = width.checked_mul(height).ok_or_else(invalid)?;
+    let pixels: Vec<bool> = match magic {
+        b"P1" => rest
+            .iter()
+            .filter(|b| !b.is_ascii_whitespace())
+            .map(|&b| b == b'1')
+            .take(count)
+            .collect(),
+        b"P4" => {
+            // A single whitespace separates the header from the data
+            let data = rest.get(1..).unwrap_or_default();
+            let stride = width.div_ceil(8);
+            if data.len() < stride.checked_mul(height).ok_or_else(invalid)? {
+                return Err("truncated PBM file".to_string());
+            }
+            (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| {
+                    let byte = data.get(y * stride + x / 8).copied().unwrap_or(0);
+                    byte & (0x80 >> (x % 8)) != 0
+                })
+                .collect()
+        }
+        _ => return Err("not a PBM file (P1 or P4)".to_string()),
+    };
+    if pixels.len() < count {
+        return Err("truncated PBM file".to_string());
+    }
+    Ok(Pbm {
+        width,
+        height,
+        pixels,
+    })
+}
+
+fn draw_pattern(display: &mut LinuxST7567, pattern: Pattern) {
+    let (width, height) = display.dimensions();
+    for y in 0..height {
+        for x in 0..width {
+            let on = match pattern {
+                Pattern::On => true,
+                Pattern::Checkerboard => (x + y) % 2 == 0,
+                Pattern::Border => x == 0 || y == 0 || x == width - 1 || y == height - 1,
+            };
+            display.set_pixel(x, y, on);
+        }
+    }
+}
+
+fn run(options: Options) -> Result<(), Box<dyn Error>> {
+    let image = match &options.image {
+        Some(path) => Some(parse_pbm(&std::fs::read(path)?)?),
+        None => None,
+    };
+
+    let mut display = ST7567::open_gfx_hat()?;
+    display.reset(&mut LinuxDelay)?;
+    display.init()?;
+    display.set_rotation(options.rotation)?;
+    if let Some(contrast) = options.contrast {
+        display.set_contrast(contrast)?;
+    }
+    display.set_inverted(options.invert)?;
+
+    if let Some(image) = image {
+        let (width, height) = display.dimensions();
+        for y in 0..height.min(image.height) {
+            for x in 0..width.min(image.width) {
+                display.set_pixel(x, y, image.get(x, y));
+            }
+        }
+    }
+    if let Some(pattern) = options.pattern {
+        draw_pattern(&mut display, pattern);
+    }
+    display.show()?;
+    Ok(())
+}
+
+fn main() {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+    if let Err(e) = run(options) {
+        eprintln!("st7567-show: {}", e);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Result<Options, String> {
+        parse_args(line.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = args("image.pbm --contrast 40 --rotate 180").unwrap();
+        assert_eq!(options.image.as_deref(), Some("image.pbm"));
+        assert_eq!(options.contrast, Some(40));
+        assert_eq!(options.rotation, Rotation::Rotate180);
+
+        let options = args("--pattern border --invert").unwrap();
+        assert_eq!(options.pattern, Some(Pattern::Border));
+        assert!(options.invert);
+
+        assert!(args("--contrast 64").is_err());
+        assert!(args("--contrast").is_err());
+        assert!(args("--rotate 45").is_err());
+        assert!(args("a.pbm b.pbm").is_err());
+        assert!(args("a.pbm --pattern on").is_err());
+        assert!(args("--verbose").is_err());
+    }
+
+    #[test]
+    fn test_parse_pbm() {
+        let plain = parse_pbm(b"P1\n# a comment\n3 2\n1 0 1\n0 1 0\n").unwrap();
+        assert_eq!((plain.width, plain.height), (3, 2));
+        assert!(plain.get(0, 0) && !plain.get(1, 0) && plain.get(1, 1));
+
+        let raw = parse_pbm(b"P4 10 2\n\x80\x40\x00\xc0").unwrap();
+        assert!(raw.get(0, 0) && raw.get(9, 0));
+        assert!(raw.get(8, 1) && raw.get(9, 1) && !raw.get(0, 1));
+        assert!(!raw.get(10, 0));
+
+        assert!(parse_pbm(b"P2 1 1 1").is_err());
+        assert!(parse_pbm(b"P1 2 2 1 0 1").is_err());
+        assert!(parse_pbm(b"P4 8 2\n\xff").is_err());
+        let huge = format!("P1 {} 2 1", usize::MAX);
+        let error = parse_pbm(huge.as_bytes()).err();
+        assert_eq!(error.as_deref(), Some("invalid PBM file"));
+        let huge = format!("P4 {} 9\n", usize::MAX - 7);
+        let error = parse_pbm(huge.as_bytes()).err();
+        assert_eq!(error.as_deref(), Some("invalid PBM file"));
+    }
+}
@@ -0,0 +1,181 @@
+//! Bit-banged SPI, for boards whose SPI peripheral is taken
+//!
+//! [`BitBangSpi`] clocks bytes out on GPIOs in [`SPI_MODE`](crate::SPI_MODE),
+//! most significant bit first, and is handed to the driver in place of the
+//! SPI device:
+//!
+//! ```ignore
+//! let spi = BitBangSpi::new(sclk, mosi, cs, delay, SPI_SPEED_HZ);
+//! let mut display = ST7567::new(spi, dc, rst);
+//! ```
+//!
+//! The delay times each half clock period; the pin writes come on top of
+//! it, so the actual clock is a bit slower than requested.
+use crate::{Pin, PinState};
+use embedded_hal::delay::DelayNs;
+use embedded_hal::spi::{self, ErrorKind, ErrorType, Operation, SpiDevice};
+
+/// Error of a [`BitBangSpi`] transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitBangError<E> {
+    /// Error driving a pin
+    Pin(E),
+    /// There is no MISO line to read from
+    ReadUnsupported,
+}
+
+impl<E: core::fmt::Debug> spi::Error for BitBangError<E> {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+/// Clocks bytes out on SCLK and MOSI, see the [module](self) docs.
+pub struct BitBangSpi<SCK, MOSI, CS, D> {
+    sck: SCK,
+    mosi: MOSI,
+    cs: CS,
+    delay: D,
+    half_period_ns: u32,
+}
+
+impl<SCK, MOSI, CS, D> BitBangSpi<SCK, MOSI, CS, D>
+where
+    SCK: Pin,
+    MOSI: Pin<Error = SCK::Error>,
+    CS: Pin<Error = SCK::Error>,
+    D: DelayNs,
+{
+    /// Clock at up to `frequency_hz`, at least 1Hz.
+    pub fn new(sck: SCK, mosi: MOSI, cs: CS, delay: D, frequency_hz: u32) -> Self {
+        BitBangSpi {
+            sck,
+            mosi,
+            cs,
+            delay,
+            half_period_ns: 500_000_000u32.saturating_div(frequency_hz.max(1)),
+        }
+    }
+
+    /// Give the pins and the delay back.
+    pub fn release(self) -> (SCK, MOSI, CS, D) {
+        (self.sck, self.mosi, self.cs, self.delay)
+    }
+
+    fn write_byte(&mut self, byte: u8) -> Result<(), SCK::Error> {
+        for bit in (0..8).rev() {
+            self.mosi.set_value(if byte >> bit & 1 != 0 {
+                PinState::High
+            } else {
+                PinState::Low
+            })?;
+            self.delay.delay_ns(self.half_period_ns);
+            self.sck.set_value(PinState::High)?;
+            self.delay.delay_ns(self.half_period_ns);
+            self.sck.set_value(PinState::Low)?;
+        }
+        Ok(())
+    }
+}
+
+impl<SCK, MOSI, CS, D> ErrorType for BitBangSpi<SCK, MOSI, CS, D>
+where
+    SCK: Pin,
+    SCK::Error: core::fmt::Debug,
+{
+    type Error = BitBangError<SCK::Error>;
+}
+
+impl<SCK, MOSI, CS, D> SpiDevice for BitBangSpi<SCK, MOSI, CS, D>
+where
+    SCK: Pin,
+    SCK::Error: core::fmt::Debug,
+    MOSI: Pin<Error = SCK::Error>,
+    CS: Pin<Error = SCK::Error>,
+    D: DelayNs,
+{
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        self.sck
+            .set_value(PinState::Low)
+            .map_err(BitBangError::Pin)?;
+        self.cs
+            .set_value(PinState::Low)
+            .map_err(BitBangError::Pin)?;
+        let mut result = Ok(());
+        for operation in operations.iter() {
+            result = match operation {
+                Operation::Write(bytes) => bytes
+                    .iter()
+                    .try_for_each(|&byte| self.write_byte(byte))
+                    .map_err(BitBangError::Pin),
+                Operation::DelayNs(ns) => {
+                    self.delay.delay_ns(*ns);
+                    Ok(())
+                }
+                _ => Err(BitBangError::ReadUnsupported),
+            };
+            if result.is_err() {
+                break;
+            }
+        }
+        // Release the bus even after an error
+        let deselect = self.cs.set_value(PinState::High).map_err(BitBangError::Pin);
+        result.and(deselect)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{MockDelay, MockError, MockPin};
+    use crate::ST7567;
+    use PinState::{High, Low};
+
+    fn bitbang() -> BitBangSpi<MockPin, MockPin, MockPin, MockDelay> {
+        let (sck, mosi, cs) = (MockPin::new(), MockPin::new(), MockPin::new());
+        BitBangSpi::new(sck, mosi, cs, MockDelay::new(), crate::SPI_SPEED_HZ)
+    }
+
+    /// Bytes clocked out, one MOSI state per bit.
+    fn written_bytes(mosi: &MockPin) -> Vec<u8> {
+        mosi.get_states()
+            .chunks(8)
+            .map(|bits| {
+                bits.iter()
+                    .fold(0, |byte, bit| byte << 1 | (*bit == High) as u8)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_clocking() {
+        let mut spi = bitbang();
+        assert!(spi.transaction(&mut [Operation::Write(&[0xa5])]).is_ok());
+        let (sck, mosi, cs, delay) = spi.release();
+        assert_eq!(written_bytes(&mosi), [0xa5]);
+        // Idle low, then one rising edge per bit
+        let sck = sck.get_states();
+        assert_eq!(sck.len(), 1 + 2 * 8);
+        assert_eq!(sck[..3], [Low, High, Low]);
+        assert_eq!(cs.get_states(), [Low, High]);
+        // 1MHz: 500ns per half period
+        assert_eq!(delay.delays_ns, [500; 16]);
+    }
+
+    #[test]
+    fn test_bitbang_driver() {
+        let mut st7567 = ST7567::new(bitbang(), MockPin::new(), MockPin::new());
+        assert!(st7567.init().is_ok());
+        assert_eq!(written_bytes(&st7567.spi.mosi), crate::sequence::GFX_HAT);
+
+        st7567.spi.mosi.set_fail(true);
+        st7567.spi.cs.clear_states();
+        assert!(matches!(
+            st7567.send_commands(&[crate::Command::Nop]),
+            Err(crate::Error::SpiError(BitBangError::Pin(
+                MockError::PinError
+            )))
+        ));
+        assert_eq!(st7567.spi.cs.get_states(), [Low, High]);
+    }
+}
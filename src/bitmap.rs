@@ -0,0 +1,234 @@
+//! Hit tests on packed, page-layout bitmaps
+//!
+//! Tiny games and cursors need to know whether two shapes touch, or whether
+//! anything is drawn under a rectangle. [`Bitmap`] answers on the packed
+//! bytes, 8 pixels at a time, instead of pixel by pixel. Sprites use the
+//! framebuffer layout: one byte per column holding 8 rows, least significant
+//! bit at the top, pages of `width` bytes one after the other.
+//!
+//! ```
+//! use st7567::bitmap::{bitmaps_overlap, Bitmap};
+//!
+//! let ball = [0x3c, 0x7e, 0x7e, 0x3c];
+//! let ball = Bitmap::new(&ball, 4, 8).unwrap();
+//! let paddle = [0xff];
+//! let paddle = Bitmap::new(&paddle, 1, 8).unwrap();
+//!
+//! assert!(bitmaps_overlap(&ball, (10, 20), &paddle, (13, 25)));
+//! assert!(!bitmaps_overlap(&ball, (10, 20), &paddle, (14, 20)));
+//! ```
+use crate::buffer::row_mask;
+use crate::transport::Transport;
+use crate::{BoundingBox, Pin, PixelBuffer, ST7567};
+use core::convert::TryFrom;
+
+/// A 1 bit per pixel image in page layout, see the [module](self) docs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bitmap<'a> {
+    data: &'a [u8],
+    width: usize,
+    height: usize,
+}
+
+impl<'a> Bitmap<'a> {
+    /// A `width` x `height` bitmap, `None` if `data` is too short for it.
+    ///
+    /// Bits of the last page below `height` are ignored.
+    pub fn new(data: &'a [u8], width: usize, height: usize) -> Option<Self> {
+        if data.len() < width.checked_mul(height.div_ceil(8))? {
+            return None;
+        }
+        Some(Bitmap {
+            data,
+            width,
+            height,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Whether the pixel at `x`, `y` is on, `false` out of the bitmap.
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.strip(x, y) & 1 != 0
+    }
+
+    /// Column `x`, rows `y` to `y + 7`, row `y` in the least significant
+    /// bit. Pixels out of the bitmap read as off.
+    fn strip(&self, x: usize, y: usize) -> u8 {
+        if x >= self.width || y >= self.height {
+            return 0;
+        }
+        let byte = |page: usize| {
+            page.checked_mul(self.width)
+                .and_then(|offset| self.data.get(offset.checked_add(x)?))
+                .copied()
+                .unwrap_or(0)
+        };
+        let page = y / 8;
+        let pair = u16::from_le_bytes([byte(page), byte(page.saturating_add(1))]);
+        let strip = (pair >> (y % 8)) as u8;
+        strip & row_mask(self.height.saturating_sub(y))
+    }
+
+    /// `true` if any pixel of `rect` is on. Parts of `rect` out of the
+    /// bitmap are ignored.
+    pub fn region_any_set(&self, rect: BoundingBox) -> bool {
+        let right = rect.x.saturating_add(rect.width).min(self.width);
+        let bottom = rect.y.saturating_add(rect.height).min(self.height);
+        (rect.y..bottom).step_by(8).any(|y| {
+            let mask = row_mask(bottom.saturating_sub(y));
+            (rect.x..right).any(|x| self.strip(x, y) & mask != 0)
+        })
+    }
+}
+
+/// `true` if a lit pixel of `a`, drawn at `pos_a`, covers a lit pixel of
+/// `b`, drawn at `pos_b`. Positions are the top left corners and may be
+/// negative.
+pub fn bitmaps_overlap(a: &Bitmap, pos_a: (i32, i32), b: &Bitmap, pos_b: (i32, i32)) -> bool {
+    let span = |pos: i32, len: usize| {
+        let len = i64::try_from(len).unwrap_or(i64::MAX);
+        (i64::from(pos), i64::from(pos).saturating_add(len))
+    };
+    let (a_left, a_right) = span(pos_a.0, a.width);
+    let (a_top, a_bottom) = span(pos_a.1, a.height);
+    let (b_left, b_right) = span(pos_b.0, b.width);
+    let (b_top, b_bottom) = span(pos_b.1, b.height);
+    let (left, right) = (a_left.max(b_left), a_right.min(b_right));
+    let (top, bottom) = (a_top.max(b_top), a_bottom.min(b_bottom));
+    (top..bottom).step_by(8).any(|y| {
+        let mask = row_mask(usize::try_from(bottom.saturating_sub(y)).unwrap_or(8));
+        // `x` and `y` are inside both bitmaps, the offsets are positive
+        let offset = |pos: i64, start: i64| pos.abs_diff(start) as usize;
+        (left..right).any(|x| {
+            let a = a.strip(offset(x, a_left), offset(y, a_top));
+            let b = b.strip(offset(x, b_left), offset(y, b_top));
+            a & b & mask != 0
+        })
+    })
+}
+
+impl<DC: Pin, RST: Pin, S: Transport, B: PixelBuffer, const W: usize, const H: usize>
+    ST7567<DC, RST, S, B, W, H>
+{
+    /// The framebuffer as a [`Bitmap`], for hit tests against it.
+    ///
+    /// Coordinates are framebuffer coordinates, they do not follow the
+    /// rotation.
+    pub fn bitmap(&self) -> Bitmap<'_> {
+        Bitmap {
+            data: self.buf.as_bytes(),
+            width: W,
+            height: H,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::create_test_st7567;
+
+    /// 3x10 frame: left and right columns, two pages
+    const FRAME: [u8; 6] = [0xff, 0x00, 0xff, 0x03, 0x00, 0x03];
+
+    #[test]
+    fn test_new() {
+        assert!(Bitmap::new(&FRAME, 3, 16).is_some());
+        assert!(Bitmap::new(&FRAME, 3, 17).is_none());
+        assert!(Bitmap::new(&FRAME, 4, 9).is_none());
+        assert!(Bitmap::new(&FRAME, usize::MAX, 16).is_none());
+    }
+
+    #[test]
+    fn test_never_panics() {
+        let edges = [0, 1, 3, 8, 10, usize::MAX - 1, usize::MAX];
+        let frame = Bitmap::new(&FRAME, 3, 10).unwrap();
+        for &width in edges.iter() {
+            for &height in edges.iter() {
+                let _ = Bitmap::new(&FRAME, width, height);
+                for &x in edges.iter() {
+                    for &y in edges.iter() {
+                        let _ = frame.get(x, y);
+                        let _ = frame.region_any_set(BoundingBox {
+                            x,
+                            y,
+                            width,
+                            height,
+                        });
+                    }
+                }
+            }
+        }
+        let positions = [i32::MIN, -1, 0, 1, i32::MAX];
+        for &x in positions.iter() {
+            for &y in positions.iter() {
+                let _ = bitmaps_overlap(&frame, (x, y), &frame, (0, 0));
+                let _ = bitmaps_overlap(&frame, (0, 0), &frame, (x, y));
+            }
+        }
+        assert!(bitmaps_overlap(
+            &frame,
+            (i32::MAX, 0),
+            &frame,
+            (i32::MAX, 5)
+        ));
+    }
+
+    #[test]
+    fn test_region_any_set() {
+        let frame = Bitmap::new(&FRAME, 3, 10).unwrap();
+        assert!(frame.get(2, 9) && !frame.get(1, 9) && !frame.get(2, 10));
+        let rect = |x, y, width, height| BoundingBox {
+            x,
+            y,
+            width,
+            height,
+        };
+        assert!(frame.region_any_set(rect(0, 0, 3, 10)));
+        assert!(!frame.region_any_set(rect(1, 0, 1, 10)));
+        // Across the page boundary
+        assert!(frame.region_any_set(rect(2, 5, 1, 4)));
+        assert!(!frame.region_any_set(rect(1, 5, 1, 4)));
+        // Bits below the height are ignored
+        let padded = [0x00, 0x02];
+        let padded = Bitmap::new(&padded, 1, 10).unwrap();
+        assert!(padded.region_any_set(rect(0, 9, 1, 1)));
+        let short = Bitmap::new(padded.data, 1, 9).unwrap();
+        assert!(!short.region_any_set(rect(0, 0, 1, 16)));
+        assert!(!frame.region_any_set(rect(5, 0, 3, 10)));
+        assert!(frame.region_any_set(rect(2, 9, usize::MAX, usize::MAX)));
+        assert!(!frame.region_any_set(rect(usize::MAX, 0, usize::MAX, 10)));
+    }
+
+    #[test]
+    fn test_bitmaps_overlap() {
+        let frame = Bitmap::new(&FRAME, 3, 10).unwrap();
+        let dot = [0x01];
+        let dot = Bitmap::new(&dot, 1, 1).unwrap();
+        assert!(bitmaps_overlap(&frame, (0, 0), &dot, (0, 9)));
+        assert!(!bitmaps_overlap(&frame, (0, 0), &dot, (1, 4)));
+        assert!(!bitmaps_overlap(&frame, (0, 0), &dot, (0, 10)));
+        assert!(bitmaps_overlap(&frame, (-2, -3), &dot, (0, 0)));
+        assert!(bitmaps_overlap(&dot, (0, 0), &frame, (-2, -3)));
+        assert!(!bitmaps_overlap(&frame, (-1, -3), &dot, (0, 0)));
+
+        // Sprites against the framebuffer
+        let mut st7567 = create_test_st7567();
+        st7567.set_pixel(40, 30, true);
+        assert!(bitmaps_overlap(&st7567.bitmap(), (0, 0), &dot, (40, 30)));
+        assert!(!bitmaps_overlap(&st7567.bitmap(), (0, 0), &dot, (40, 31)));
+        assert!(st7567.bitmap().region_any_set(BoundingBox {
+            x: 32,
+            y: 28,
+            width: 9,
+            height: 3,
+        }));
+    }
+}
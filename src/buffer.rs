@@ -0,0 +1,482 @@
+//! Framebuffer storage
+//!
+//! The display memory is organized in pages of one byte per column, 8 pages
+//! of 128 bytes on the GFX HAT. Each byte holds a vertical strip of 8 pixels,
+//! least significant bit at the top, so pixel `(x, y)` is bit `y % 8` of byte
+//! `(y / 8) * WIDTH + x`.
+//!
+//! [`PixelBuffer`] abstracts the storage holding those bytes. The driver owns
+//! a plain `[u8; BUFFER_SIZE]` by default, but any implementation can be
+//! given to [`ST7567::with_buffer`](crate::ST7567::with_buffer), for instance
+//! a borrowed slice living in core-coupled RAM or a [`StaticFramebuffer`].
+//! Arrays and [`SizedFramebuffer`]s of the 128x32 and 132x64 sizes hold the
+//! frames of those panels, their pixel accessors use that geometry.
+use crate::{buffer_size, BUFFER_SIZE, HEIGHT, WIDTH};
+use core::ops::Range;
+use core::slice::Chunks;
+
+/// Largest number of pages of a panel.
+const PAGES: usize = HEIGHT as usize / 8;
+
+/// Dimensions of a frame, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Geometry {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+}
+
+impl Geometry {
+    /// The 128x64 panel of the GFX HAT.
+    pub(crate) const DEFAULT: Geometry = Geometry {
+        width: WIDTH as usize,
+        height: HEIGHT as usize,
+    };
+
+    pub(crate) fn pages(self) -> usize {
+        self.height / 8
+    }
+
+    /// Byte offsets of `columns` in `page`.
+    pub(crate) fn page_bytes(self, page: usize, columns: Range<usize>) -> Range<usize> {
+        let start = page.saturating_mul(self.width);
+        start.saturating_add(columns.start)..start.saturating_add(columns.end)
+    }
+
+    fn offset(self, x: usize, y: usize) -> Option<usize> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        (y / 8).checked_mul(self.width)?.checked_add(x)
+    }
+
+    pub(crate) fn get(self, bytes: &[u8], x: usize, y: usize) -> bool {
+        match self.offset(x, y) {
+            Some(offset) => {
+                let byte = bytes.get(offset).copied().unwrap_or(0);
+                byte & (1 << (y % 8)) != 0
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn set(self, bytes: &mut [u8], x: usize, y: usize, value: bool) {
+        let byte = match self.offset(x, y).and_then(|o| bytes.get_mut(o)) {
+            Some(byte) => byte,
+            None => return,
+        };
+        if value {
+            *byte |= 1 << (y % 8);
+        } else {
+            *byte &= !(1 << (y % 8));
+        }
+    }
+
+    /// Set the pixels of `columns` x `rows` to `value`, a byte at a time.
+    pub(crate) fn fill_rect(
+        self,
+        bytes: &mut [u8],
+        columns: Range<usize>,
+        rows: Range<usize>,
+        value: bool,
+    ) {
+        let columns = columns.start..columns.end.min(self.width);
+        let rows = rows.start..rows.end.min(self.height);
+        if columns.is_empty() || rows.is_empty() {
+            return;
+        }
+        for page in rows.start / 8..=rows.end.saturating_sub(1) / 8 {
+            let first_row = page.saturating_mul(8);
+            let top = rows.start.saturating_sub(first_row);
+            let bottom = rows.end.saturating_sub(first_row);
+            let mask = row_mask(bottom) & !row_mask(top);
+            let line = bytes
+                .get_mut(self.page_bytes(page, columns.clone()))
+                .unwrap_or_default();
+            for byte in line {
+                if value {
+                    *byte |= mask;
+                } else {
+                    *byte &= !mask;
+                }
+            }
+        }
+    }
+}
+
+/// Storage for a page-organized, 1 bit per pixel frame.
+///
+/// Only the raw byte accessors need to be implemented. Pixels falling outside
+/// of the screen, or outside of a storage shorter than the frame, are
+/// ignored by `set` and read as off by `get`.
+///
+/// The pixel accessors and [`pages`](Self::pages) address a frame of
+/// [`WIDTH`](Self::WIDTH) x [`HEIGHT`](Self::HEIGHT) pixels, 128x64 unless
+/// the storage says otherwise. Slices do not know the panel they hold a
+/// frame of, use [`ST7567::get_pixel`](crate::ST7567::get_pixel) and
+/// [`ST7567::set_pixel`](crate::ST7567::set_pixel) for other sizes.
+pub trait PixelBuffer {
+    /// Width of the frame, in pixels.
+    const WIDTH: usize = WIDTH as usize;
+
+    /// Height of the frame, in pixels.
+    const HEIGHT: usize = HEIGHT as usize;
+
+    /// Raw bytes, in controller page layout.
+    fn as_bytes(&self) -> &[u8];
+
+    /// Mutable raw bytes, in controller page layout.
+    fn as_bytes_mut(&mut self) -> &mut [u8];
+
+    /// Read a single pixel of the frame.
+    fn get(&self, x: usize, y: usize) -> bool {
+        geometry::<Self>().get(self.as_bytes(), x, y)
+    }
+
+    /// Write a single pixel of the frame.
+    fn set(&mut self, x: usize, y: usize, value: bool) {
+        geometry::<Self>().set(self.as_bytes_mut(), x, y, value)
+    }
+
+    /// Set every pixel to `value`.
+    fn fill(&mut self, value: bool) {
+        let fill = if value { 0xff } else { 0x00 };
+        for byte in self.as_bytes_mut() {
+            *byte = fill;
+        }
+    }
+
+    /// Iterate over the pages, each one being a row of
+    /// [`WIDTH`](Self::WIDTH) bytes.
+    fn pages(&self) -> Chunks<'_, u8> {
+        let frame = Self::WIDTH.saturating_mul(Self::HEIGHT / 8);
+        let bytes = self.as_bytes();
+        bytes.get(..frame).unwrap_or(bytes).chunks(Self::WIDTH)
+    }
+}
+
+/// Frame geometry of a storage.
+fn geometry<B: PixelBuffer + ?Sized>() -> Geometry {
+    Geometry {
+        width: B::WIDTH,
+        height: B::HEIGHT,
+    }
+}
+
+/// An axis-aligned rectangle, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoundingBox {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// The low `rows` bits set, all of them from 8 rows on.
+pub(crate) fn row_mask(rows: usize) -> u8 {
+    match rows {
+        0..=7 => !(0xff << rows),
+        _ => 0xff,
+    }
+}
+
+/// Number of lit pixels and their bounding box, for bytes in page layout of
+/// `width` columns.
+pub(crate) fn lit_area<I: Iterator<Item = u8>>(
+    bytes: I,
+    width: usize,
+) -> (usize, Option<BoundingBox>) {
+    let mut count = 0usize;
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (usize::MAX, usize::MAX, 0, 0);
+    for (offset, byte) in bytes.enumerate() {
+        let (x, top) = match (offset.checked_rem(width), offset.checked_div(width)) {
+            (Some(x), Some(page)) => (x, page.saturating_mul(8)),
+            _ => break,
+        };
+        if byte == 0 {
+            continue;
+        }
+        count = count.saturating_add(byte.count_ones() as usize);
+        min_x = min_x.min(x);
+        max_x = max_x.max(x);
+        min_y = min_y.min(top.saturating_add(byte.trailing_zeros() as usize));
+        max_y = max_y.max(top.saturating_add(byte.ilog2() as usize));
+    }
+    if count == 0 {
+        return (0, None);
+    }
+    let bounding_box = BoundingBox {
+        x: min_x,
+        y: min_y,
+        width: max_x.saturating_sub(min_x).saturating_add(1),
+        height: max_y.saturating_sub(min_y).saturating_add(1),
+    };
+    (count, Some(bounding_box))
+}
+
+/// Statistics about the content of a framebuffer, see
+/// [`ST7567::buffer_stats`](crate::ST7567::buffer_stats).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BufferStats {
+    /// Number of pixels turned on
+    pub lit_pixels: usize,
+    /// Number of pixels turned on in each page, out of `8 * WIDTH`. Pages
+    /// past the panel height read as empty.
+    pub page_lit_pixels: [u16; PAGES],
+    /// Smallest rectangle containing every lit pixel, `None` if blank
+    pub bounding_box: Option<BoundingBox>,
+    /// Width of the panel, in pixels
+    width: usize,
+}
+
+impl BufferStats {
+    pub(crate) fn of(bytes: &[u8], geometry: Geometry) -> Self {
+        let bytes = bytes
+            .get(..geometry.width.saturating_mul(geometry.pages()))
+            .unwrap_or(bytes);
+        let mut page_lit_pixels = [0; PAGES];
+        for (lit, page) in page_lit_pixels.iter_mut().zip(bytes.chunks(geometry.width)) {
+            *lit = page.iter().map(|byte| byte.count_ones() as u16).sum();
+        }
+        let (lit_pixels, bounding_box) = lit_area(bytes.iter().copied(), geometry.width);
+        Self {
+            lit_pixels,
+            page_lit_pixels,
+            bounding_box,
+            width: geometry.width,
+        }
+    }
+
+    /// `true` when no pixel is lit.
+    pub fn is_blank(&self) -> bool {
+        self.lit_pixels == 0
+    }
+
+    /// Fill ratio of a page, in percent. Pages out of range read as empty.
+    pub fn page_fill_percent(&self, page: usize) -> u8 {
+        let lit = self.page_lit_pixels.get(page).copied().unwrap_or(0);
+        let pixels = self.width.saturating_mul(8);
+        (usize::from(lit).saturating_mul(100))
+            .checked_div(pixels)
+            .unwrap_or(0) as u8
+    }
+}
+
+/// Owned storage for the 128x64, 128x32 and 132x64 panels, see
+/// [`buffer_size`](crate::buffer_size). Other sizes can use a slice.
+macro_rules! impl_array_buffer {
+    ($(($width:expr, $height:expr)),*) => {$(
+        impl PixelBuffer for [u8; buffer_size($width, $height)] {
+            const WIDTH: usize = $width;
+            const HEIGHT: usize = $height;
+
+            fn as_bytes(&self) -> &[u8] {
+                self
+            }
+
+            fn as_bytes_mut(&mut self) -> &mut [u8] {
+                self
+            }
+        }
+
+        impl PixelBuffer for SizedFramebuffer<{ buffer_size($width, $height) }> {
+            const WIDTH: usize = $width;
+            const HEIGHT: usize = $height;
+
+            fn as_bytes(&self) -> &[u8] {
+                &self.0
+            }
+
+            fn as_bytes_mut(&mut self) -> &mut [u8] {
+                &mut self.0
+            }
+        }
+    )*};
+}
+
+impl_array_buffer!((128, 64), (128, 32), (132, 64));
+
+impl PixelBuffer for [u8] {
+    fn as_bytes(&self) -> &[u8] {
+        self
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self
+    }
+}
+
+/// Lets the driver borrow its storage, e.g. `&mut [u8]` or
+/// `&'static mut StaticFramebuffer`.
+impl<B: PixelBuffer + ?Sized> PixelBuffer for &mut B {
+    const WIDTH: usize = B::WIDTH;
+    const HEIGHT: usize = B::HEIGHT;
+
+    fn as_bytes(&self) -> &[u8] {
+        (**self).as_bytes()
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        (**self).as_bytes_mut()
+    }
+}
+
+/// A framebuffer of `N` bytes with a `const` constructor.
+///
+/// It can be placed in a `static` (or a section of its own with
+/// `#[link_section]`) and handed to the driver as a `&'static mut`, keeping
+/// the frame out of the stack. `N` is the
+/// [`buffer_size`](crate::buffer_size) of a 128x64, 128x32 or 132x64
+/// panel:
+///
+/// ```ignore
+/// static mut FRAME: SizedFramebuffer<{ st7567::buffer_size(128, 32) }> =
+///     SizedFramebuffer::new();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizedFramebuffer<const N: usize>([u8; N]);
+
+impl<const N: usize> SizedFramebuffer<N> {
+    pub const fn new() -> Self {
+        SizedFramebuffer([0; N])
+    }
+}
+
+impl<const N: usize> Default for SizedFramebuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The [`SizedFramebuffer`] of a 128x64 panel.
+pub type StaticFramebuffer = SizedFramebuffer<BUFFER_SIZE>;
+
+/// No storage at all, for bufferless builds.
+///
+/// A driver created with it keeps no frame: the pixel and buffer methods
+/// have no effect, `show()` has nothing to send, and drawing goes through
+/// [`ST7567::write_page`](crate::ST7567::write_page). This saves the 1KB
+/// frame on targets short of RAM:
+///
+/// ```ignore
+/// let mut display = ST7567::with_buffer(spi, dc, rst, NoBuffer);
+/// display.write_page(0, 0, &glyphs)?;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NoBuffer;
+
+impl PixelBuffer for NoBuffer {
+    fn as_bytes(&self) -> &[u8] {
+        &[]
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        &mut []
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exercise<B: PixelBuffer>(buf: &mut B) {
+        buf.set(3, 9, true);
+        assert!(buf.get(3, 9));
+        assert_eq!(buf.as_bytes()[WIDTH as usize + 3], 0b10);
+        buf.set(3, 9, false);
+        assert!(!buf.get(3, 9));
+
+        // Out of screen pixels are ignored
+        buf.set(WIDTH as usize, 0, true);
+        buf.set(0, HEIGHT as usize, true);
+        assert!(!buf.get(WIDTH as usize, 0));
+        assert!(buf.as_bytes().iter().all(|&b| b == 0));
+
+        buf.fill(true);
+        assert!(buf.get(WIDTH as usize - 1, HEIGHT as usize - 1));
+        assert_eq!(buf.pages().count(), 8);
+        assert!(buf.pages().all(|page| page.len() == WIDTH as usize));
+        buf.fill(false);
+    }
+
+    #[test]
+    fn test_array_buffer() {
+        exercise(&mut [0u8; BUFFER_SIZE]);
+    }
+
+    #[test]
+    fn test_slice_buffer() {
+        let mut storage = [0u8; BUFFER_SIZE];
+        let mut slice: &mut [u8] = &mut storage;
+        exercise(&mut slice);
+    }
+
+    #[test]
+    fn test_static_framebuffer() {
+        static EMPTY: StaticFramebuffer = StaticFramebuffer::new();
+        let mut buf = EMPTY.clone();
+        exercise(&mut buf);
+    }
+
+    #[test]
+    fn test_sized_buffers() {
+        let mut buf = [0u8; buffer_size(128, 32)];
+        buf.set(127, 31, true);
+        buf.set(0, 32, true);
+        assert!(buf.get(127, 31));
+        assert_eq!(buf.as_bytes()[3 * 128 + 127], 0x80);
+        assert_eq!(buf.as_bytes().iter().filter(|&&b| b != 0).count(), 1);
+        assert_eq!(buf.pages().count(), 4);
+
+        let mut buf = SizedFramebuffer::<{ buffer_size(132, 64) }>::new();
+        // Borrowed storage keeps its geometry
+        let mut borrowed = &mut buf;
+        PixelBuffer::set(&mut borrowed, 131, 63, true);
+        assert!(PixelBuffer::get(&borrowed, 131, 63));
+        assert_eq!(buf.as_bytes()[1055], 0x80);
+        assert_eq!(buf.pages().count(), 8);
+        assert!(buf.pages().all(|page| page.len() == 132));
+    }
+
+    #[test]
+    fn test_buffer_stats() {
+        let mut buf = [0u8; BUFFER_SIZE];
+        let stats = BufferStats::of(&buf, Geometry::DEFAULT);
+        assert!(stats.is_blank());
+        assert_eq!(stats.bounding_box, None);
+
+        buf.set(5, 10, true);
+        buf.set(6, 10, true);
+        buf.set(100, 60, true);
+        for x in 0..WIDTH as usize {
+            for y in 0..8 {
+                buf.set(x, y, true);
+            }
+        }
+        let stats = BufferStats::of(&buf, Geometry::DEFAULT);
+        assert_eq!(stats.lit_pixels, 8 * 128 + 3);
+        assert_eq!(stats.page_lit_pixels, [1024, 2, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(stats.page_fill_percent(0), 100);
+        assert_eq!(stats.page_fill_percent(1), 0);
+        assert_eq!(stats.page_fill_percent(42), 0);
+        assert_eq!(
+            stats.bounding_box,
+            Some(BoundingBox {
+                x: 0,
+                y: 0,
+                width: 128,
+                height: 61
+            })
+        );
+    }
+
+    #[test]
+    fn test_short_slice_is_safe() {
+        let mut storage = [0u8; 16];
+        let slice: &mut [u8] = &mut storage;
+        slice.set(3, 0, true);
+        slice.set(3, 63, true);
+        assert!(slice.get(3, 0));
+        assert!(!slice.get(3, 63));
+        assert_eq!(slice.pages().count(), 1);
+    }
+}
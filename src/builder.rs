@@ -0,0 +1,287 @@
+//! Configure the panel before creating the driver
+//!
+//! `init()` sends the bias, scan directions, regulation ratio and contrast
+//! stored in the driver. The defaults suit the Pimoroni GFX HAT; other panels
+//! can set their own through [`Builder`]:
+//!
+//! ```ignore
+//! let mut display = ST7567::builder()
+//!     .bias(Bias::OneNinth)
+//!     .contrast(32)
+//!     .flip_vertical(true)
+//!     .build(spi, dc, rst)?;
+//! display.reset(&mut delay)?;
+//! display.init()?;
+//! ```
+//!
+//! Values are checked when building, as the setters of the driver would:
+//! the contrast according to the [range policy](Builder::range_policy), the
+//! regulation ratio and column offset returning
+//! [`Error::InvalidArgument`] when out of range.
+use crate::consts::ST7567_CONTRAST_MASK;
+use crate::transport::Transport;
+use crate::{
+    Bias, BitOrder, ControllerVariant, Error, Flags, InitSequence, Pin, PixelBuffer, RangePolicy,
+    Rotation, BUFFER_SIZE, ST7567,
+};
+use core::marker::PhantomData;
+
+/// Ties the pin and bus types of a [`Builder`] to the driver it builds.
+type Parts<DC, RST, S> = PhantomData<fn() -> (DC, RST, S)>;
+
+/// The driver built by [`Builder::build_sized`], or why it was not.
+type Built<DC, RST, S, B, const W: usize, const H: usize> =
+    Result<ST7567<DC, RST, S, B, W, H>, Error<DC, RST, S>>;
+
+/// Settings used by `init()`, see the [module](self) docs.
+pub struct Builder<DC: Pin, RST: Pin, S: Transport> {
+    bias: Bias,
+    contrast: u8,
+    regulation_ratio: u8,
+    flip_horizontal: bool,
+    flip_vertical: bool,
+    rotation: Rotation,
+    column_offset: u8,
+    bit_order: BitOrder,
+    init_sequence: InitSequence,
+    range_policy: RangePolicy,
+    max_contrast: u8,
+    parts: Parts<DC, RST, S>,
+}
+
+impl<DC: Pin, RST: Pin, S: Transport> ST7567<DC, RST, S> {
+    /// Start configuring a driver, from the GFX HAT defaults.
+    pub fn builder() -> Builder<DC, RST, S> {
+        Builder {
+            bias: Bias::default(),
+            contrast: 40,
+            regulation_ratio: 3,
+            flip_horizontal: false,
+            flip_vertical: false,
+            rotation: Rotation::default(),
+            column_offset: 0,
+            bit_order: BitOrder::default(),
+            init_sequence: InitSequence::BuiltIn,
+            range_policy: RangePolicy::default(),
+            max_contrast: u8::MAX,
+            parts: PhantomData,
+        }
+    }
+}
+
+impl<DC: Pin, RST: Pin, S: Transport> Builder<DC, RST, S> {
+    /// Start from the profile of another controller of the family, see
+    /// [`ST7567::set_controller_variant`]. Settings given after this call
+    /// override the profile, e.g. the column offset the module needs.
+    pub fn controller_variant(mut self, variant: ControllerVariant) -> Self {
+        let profile = variant.profile();
+        self.bias = profile.bias;
+        self.regulation_ratio = profile.regulation_ratio;
+        self.contrast = profile.contrast;
+        self.column_offset = profile.column_offset;
+        self.init_sequence = profile.init_sequence;
+        self
+    }
+
+    /// LCD bias ratio, see [`ST7567::set_bias`].
+    pub fn bias(mut self, bias: Bias) -> Self {
+        self.bias = bias;
+        self
+    }
+
+    /// Contrast, 0 to 63. Larger values are handled according to the
+    /// [range policy](Self::range_policy), see [`ST7567::set_contrast`].
+    pub fn contrast(mut self, contrast: u8) -> Self {
+        self.contrast = contrast;
+        self
+    }
+
+    /// Regulation resistor ratio, 0 to 7, see
+    /// [`ST7567::set_regulation_ratio`].
+    pub fn regulation_ratio(mut self, ratio: u8) -> Self {
+        self.regulation_ratio = ratio;
+        self
+    }
+
+    /// Mirror the image left to right, compared to the GFX HAT mounting.
+    pub fn flip_horizontal(mut self, flip: bool) -> Self {
+        self.flip_horizontal = flip;
+        self
+    }
+
+    /// Mirror the image top to bottom, compared to the GFX HAT mounting.
+    pub fn flip_vertical(mut self, flip: bool) -> Self {
+        self.flip_vertical = flip;
+        self
+    }
+
+    /// Drawing rotation, see [`ST7567::set_rotation`].
+    pub fn rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// First RAM column of the glass, 0 to 4, see
+    /// [`ST7567::set_column_offset`].
+    pub fn column_offset(mut self, offset: u8) -> Self {
+        self.column_offset = offset;
+        self
+    }
+
+    /// Bit order of the SPI bus, see [`ST7567::set_bit_order`].
+    pub fn bit_order(mut self, bit_order: BitOrder) -> Self {
+        self.bit_order = bit_order;
+        self
+    }
+
+    /// Replace or extend the init sequence, see
+    /// [`ST7567::set_init_sequence`].
+    pub fn init_sequence(mut self, init_sequence: InitSequence) -> Self {
+        self.init_sequence = init_sequence;
+        self
+    }
+
+    /// Handling of out of range values, see [`ST7567::set_range_policy`].
+    pub fn range_policy(mut self, range_policy: RangePolicy) -> Self {
+        self.range_policy = range_policy;
+        self
+    }
+
+    /// Contrast cap, see [`ST7567::set_max_contrast`]. It applies to the
+    /// [`contrast`](Self::contrast) given here too.
+    pub fn max_contrast(mut self, max: u8) -> Self {
+        self.max_contrast = max;
+        self
+    }
+
+    /// Create the driver. Nothing is sent until `init()`.
+    ///
+    /// Returns [`Error::InvalidArgument`] for a contrast rejected by the
+    /// range policy, a regulation ratio above 7 or a column offset above 4.
+    pub fn build(
+        self,
+        spi: S,
+        dc_pin: DC,
+        rst_pin: RST,
+    ) -> Result<ST7567<DC, RST, S>, Error<DC, RST, S>> {
+        self.build_sized(spi, dc_pin, rst_pin, [0; BUFFER_SIZE])
+    }
+
+    /// Create the driver of a `W` x `H` panel, drawing into `buf`, see
+    /// [`ST7567::with_sized_buffer`]. The column offset must also fit in the
+    /// RAM columns left by the panel width.
+    pub fn build_sized<B: PixelBuffer, const W: usize, const H: usize>(
+        self,
+        spi: S,
+        dc_pin: DC,
+        rst_pin: RST,
+        buf: B,
+    ) -> Built<DC, RST, S, B, W, H> {
+        let mut display = ST7567::with_sized_buffer(spi, dc_pin, rst_pin, buf);
+        display.range_policy = self.range_policy;
+        display.contrast = display.fit(self.contrast, ST7567_CONTRAST_MASK)?;
+        if self.regulation_ratio > 7 {
+            return Err(Error::InvalidArgument);
+        }
+        display.regulation_ratio = self.regulation_ratio;
+        display.set_column_offset(self.column_offset)?;
+        display.bias = self.bias;
+        display
+            .flags
+            .set(Flags::SEGMENT_REMAP, self.flip_horizontal);
+        display.flags.set(Flags::COM_REVERSE, !self.flip_vertical);
+        display.rotation = self.rotation;
+        display.set_bit_order(self.bit_order);
+        display.set_init_sequence(self.init_sequence);
+        display.set_max_contrast(self.max_contrast);
+        Ok(display)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consts::*;
+    use crate::tests::{MockPin, MockSpiDevice};
+    use crate::{buffer_size, Bias, Error, RangePolicy, Rotation, ST7567};
+
+    #[test]
+    fn test_builder() {
+        let mut st7567 = ST7567::builder()
+            .bias(Bias::OneNinth)
+            .contrast(32)
+            .regulation_ratio(7)
+            .flip_vertical(true)
+            .build(MockSpiDevice::new(), MockPin::new(), MockPin::new())
+            .unwrap();
+        assert!(st7567.init().is_ok());
+        assert_eq!(
+            st7567.spi.get_written_data(),
+            vec![
+                ST7567_BIAS_1_9,
+                ST7567_SEG_DIR_NORMAL,
+                ST7567_SETCOMNORMAL,
+                ST7567_DISPNORMAL,
+                ST7567_DISPRAM,
+                ST7567_SETSTARTLINE,
+                ST7567_POWERCTRL,
+                ST7567_REG_RATIO | 7,
+                ST7567_DISPON,
+                ST7567_SETCONTRAST,
+                32,
+            ]
+        );
+
+        let st7567 = ST7567::builder()
+            .contrast(50)
+            .max_contrast(45)
+            .build(MockSpiDevice::new(), MockPin::new(), MockPin::new())
+            .unwrap();
+        assert_eq!((st7567.contrast(), st7567.max_contrast()), (45, 45));
+
+        let st7567 = ST7567::builder()
+            .column_offset(4)
+            .rotation(Rotation::Rotate180)
+            .build_sized::<_, 128, 32>(
+                MockSpiDevice::new(),
+                MockPin::new(),
+                MockPin::new(),
+                [0; buffer_size(128, 32)],
+            )
+            .unwrap();
+        assert_eq!(st7567.dimensions(), (128, 32));
+        assert_eq!(st7567.column_offset, 4);
+        assert_eq!(st7567.rotation(), Rotation::Rotate180);
+    }
+
+    #[test]
+    fn test_builder_ranges() {
+        let build = |builder: crate::Builder<MockPin, MockPin, MockSpiDevice>| {
+            builder.build(MockSpiDevice::new(), MockPin::new(), MockPin::new())
+        };
+        // The contrast follows the range policy, whatever the call order
+        let contrast = |policy| {
+            let builder = ST7567::builder().contrast(80).range_policy(policy);
+            build(builder).map(|st7567| st7567.contrast())
+        };
+        assert!(matches!(contrast(RangePolicy::Wrap), Ok(16)));
+        assert!(matches!(contrast(RangePolicy::Saturate), Ok(63)));
+        let rejected = contrast(RangePolicy::Reject);
+        assert!(matches!(rejected, Err(Error::InvalidArgument)));
+
+        // The regulation ratio and column offset are checked like their
+        // setters do
+        let ratio = ST7567::builder().regulation_ratio(8);
+        assert!(matches!(build(ratio), Err(Error::InvalidArgument)));
+        let offset = ST7567::builder().column_offset(5);
+        assert!(matches!(build(offset), Err(Error::InvalidArgument)));
+        let wide = ST7567::builder()
+            .column_offset(1)
+            .build_sized::<_, 132, 64>(
+                MockSpiDevice::new(),
+                MockPin::new(),
+                MockPin::new(),
+                [0; buffer_size(132, 64)],
+            );
+        assert!(matches!(wide, Err(Error::InvalidArgument)));
+    }
+}
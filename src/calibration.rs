@@ -0,0 +1,284 @@
+//! Per-unit panel tuning, stored outside of the firmware
+//!
+//! Glass from different batches needs a different contrast, and modules are
+//! not always mounted the same way. [`PanelCalibration`] packs the tuning of
+//! one unit into a few checksummed bytes, to write to an EEPROM at the end of
+//! the production line and apply at boot:
+//!
+//! ```ignore
+//! let mut blob = [0; calibration::ENCODED_LEN];
+//! eeprom.read(CALIBRATION_ADDRESS, &mut blob)?;
+//! if let Ok(calibration) = PanelCalibration::decode(&blob) {
+//!     display.apply_calibration(&calibration)?;
+//! }
+//! display.init()?;
+//! ```
+use crate::consts::{RAM_COLUMNS, ST7567_CONTRAST_MASK};
+use crate::transport::Transport;
+use crate::{Bias, Command, Error, Pin, PixelBuffer, Rotation, ST7567};
+
+/// Length of an encoded [`PanelCalibration`], in bytes.
+pub const ENCODED_LEN: usize = 7;
+
+/// First byte of an encoded calibration.
+const MAGIC: u8 = 0x67;
+
+/// Layout version, bumped when the encoding changes.
+const VERSION: u8 = 1;
+
+/// Why a blob is rejected by [`PanelCalibration::decode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalibrationError {
+    /// The blob is shorter than [`ENCODED_LEN`]
+    TooShort,
+    /// The blob was not written by [`PanelCalibration::encode`], or by an
+    /// incompatible version of it. Erased EEPROM reads as this.
+    UnknownFormat,
+    /// The checksum does not match, the blob is corrupted
+    Checksum,
+    /// A value is out of the controller range
+    OutOfRange,
+}
+
+/// Tuning of one panel, see the [module](self) docs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PanelCalibration {
+    /// Contrast, 0 to 63
+    pub contrast: u8,
+    /// Regulation resistor ratio, 0 to 7
+    pub regulation_ratio: u8,
+    pub bias: Bias,
+    /// First RAM column of the glass, see [`ST7567::set_column_offset`]
+    pub column_offset: u8,
+    pub rotation: Rotation,
+}
+
+/// CRC-8, polynomial 0x07.
+fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |crc, &byte| {
+        (0..8).fold(crc ^ byte, |crc, _| {
+            if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+impl PanelCalibration {
+    /// Whether every value is in the controller range. The column offset is
+    /// checked by the driver, against the panel width.
+    fn in_range(&self) -> bool {
+        self.contrast <= ST7567_CONTRAST_MASK
+            && self.regulation_ratio <= 7
+            && self.column_offset < RAM_COLUMNS
+    }
+
+    /// Pack into bytes, with a version and a checksum.
+    pub fn encode(&self) -> [u8; ENCODED_LEN] {
+        let bias = match self.bias {
+            Bias::OneSeventh => 0,
+            Bias::OneNinth => 1,
+        };
+        let rotation = match self.rotation {
+            Rotation::Rotate0 => 0,
+            Rotation::Rotate90 => 1,
+            Rotation::Rotate180 => 2,
+            Rotation::Rotate270 => 3,
+        };
+        let mut blob = [
+            MAGIC,
+            VERSION,
+            self.contrast,
+            self.regulation_ratio,
+            bias | rotation << 1,
+            self.column_offset,
+            0,
+        ];
+        let (body, crc) = blob.split_at_mut(ENCODED_LEN - 1);
+        if let Some(crc) = crc.first_mut() {
+            *crc = crc8(body);
+        }
+        blob
+    }
+
+    /// Unpack bytes written by [`encode`](Self::encode). Bytes past
+    /// [`ENCODED_LEN`] are ignored.
+    pub fn decode(bytes: &[u8]) -> Result<Self, CalibrationError> {
+        let (magic, version, contrast, ratio, flags, column_offset, crc) = match bytes {
+            [magic, version, contrast, ratio, flags, column_offset, crc, ..] => (
+                *magic,
+                *version,
+                *contrast,
+                *ratio,
+                *flags,
+                *column_offset,
+                *crc,
+            ),
+            _ => return Err(CalibrationError::TooShort),
+        };
+        if magic != MAGIC || version != VERSION {
+            return Err(CalibrationError::UnknownFormat);
+        }
+        if crc8(bytes.get(..ENCODED_LEN - 1).unwrap_or_default()) != crc {
+            return Err(CalibrationError::Checksum);
+        }
+        let rotation = match (flags >> 1) & 0b11 {
+            0 => Rotation::Rotate0,
+            1 => Rotation::Rotate90,
+            2 => Rotation::Rotate180,
+            _ => Rotation::Rotate270,
+        };
+        let calibration = PanelCalibration {
+            contrast,
+            regulation_ratio: ratio,
+            bias: if flags & 1 != 0 {
+                Bias::OneNinth
+            } else {
+                Bias::OneSeventh
+            },
+            column_offset,
+            rotation,
+        };
+        if flags >> 3 != 0 || !calibration.in_range() {
+            return Err(CalibrationError::OutOfRange);
+        }
+        Ok(calibration)
+    }
+}
+
+impl<DC: Pin, RST: Pin, S: Transport, B: PixelBuffer, const W: usize, const H: usize>
+    ST7567<DC, RST, S, B, W, H>
+{
+    /// The tuning currently in use, to encode and store.
+    pub fn calibration(&self) -> PanelCalibration {
+        PanelCalibration {
+            contrast: self.contrast,
+            regulation_ratio: self.regulation_ratio,
+            bias: self.bias,
+            column_offset: self.column_offset,
+            rotation: self.rotation,
+        }
+    }
+
+    /// Use the contrast, regulation ratio, bias, column offset and rotation
+    /// of `calibration`.
+    ///
+    /// Before `init()`, the values are only stored and sent by `init()`.
+    /// Afterwards they are sent right away. Returns
+    /// [`Error::InvalidArgument`] without changing anything if a value is out
+    /// of range, including a column offset too large for the panel width.
+    pub fn apply_calibration(
+        &mut self,
+        calibration: &PanelCalibration,
+    ) -> Result<(), Error<DC, RST, S>> {
+        if !calibration.in_range() || calibration.column_offset > Self::SPARE_COLUMNS {
+            return Err(Error::InvalidArgument);
+        }
+        self.contrast = calibration.contrast.min(self.max_contrast);
+        self.regulation_ratio = calibration.regulation_ratio;
+        self.bias = calibration.bias;
+        self.column_offset = calibration.column_offset;
+        self.rotation = calibration.rotation;
+        self.forget_controller_ram();
+        if !self.is_initialized() {
+            return Ok(());
+        }
+        self.send_commands(&[
+            Command::SetBias(self.bias),
+            self.segment_direction(),
+            self.com_direction(),
+            Command::SetRegulationRatio(self.regulation_ratio),
+            Command::SetContrast(self.contrast),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::*;
+    use crate::tests::create_test_st7567;
+
+    const TUNED: PanelCalibration = PanelCalibration {
+        contrast: 33,
+        regulation_ratio: 5,
+        bias: Bias::OneNinth,
+        column_offset: 2,
+        rotation: Rotation::Rotate180,
+    };
+
+    #[test]
+    fn test_encode_decode() {
+        let blob = TUNED.encode();
+        assert_eq!(blob[..6], [MAGIC, VERSION, 33, 5, 0b101, 2]);
+        assert_eq!(PanelCalibration::decode(&blob), Ok(TUNED));
+        // Trailing bytes, e.g. the rest of an EEPROM page, are ignored
+        assert_eq!(
+            PanelCalibration::decode(&[&blob[..], &[0xff; 9]].concat()),
+            Ok(TUNED)
+        );
+
+        assert_eq!(
+            PanelCalibration::decode(&blob[..6]),
+            Err(CalibrationError::TooShort)
+        );
+        assert_eq!(
+            PanelCalibration::decode(&[0xff; ENCODED_LEN]),
+            Err(CalibrationError::UnknownFormat)
+        );
+        let mut corrupted = blob;
+        corrupted[2] ^= 0x04;
+        assert_eq!(
+            PanelCalibration::decode(&corrupted),
+            Err(CalibrationError::Checksum)
+        );
+        let too_bright = PanelCalibration {
+            contrast: 64,
+            ..TUNED
+        };
+        assert_eq!(
+            PanelCalibration::decode(&too_bright.encode()),
+            Err(CalibrationError::OutOfRange)
+        );
+    }
+
+    #[test]
+    fn test_apply_calibration() {
+        let mut st7567 = create_test_st7567();
+        assert!(st7567.apply_calibration(&TUNED).is_ok());
+        assert_eq!(st7567.calibration(), TUNED);
+        assert_eq!(
+            st7567.spi.get_written_data(),
+            [
+                ST7567_BIAS_1_9,
+                ST7567_SEG_DIR_REV,
+                ST7567_SETCOMNORMAL,
+                ST7567_REG_RATIO | 5,
+                ST7567_SETCONTRAST,
+                33
+            ]
+        );
+
+        let out_of_range = PanelCalibration {
+            column_offset: 5,
+            ..TUNED
+        };
+        assert!(matches!(
+            st7567.apply_calibration(&out_of_range),
+            Err(Error::InvalidArgument)
+        ));
+        assert_eq!(st7567.calibration(), TUNED);
+
+        // Before init(), the values wait for it
+        assert!(st7567
+            .software_reset(&mut crate::tests::MockDelay::new())
+            .is_ok());
+        st7567.spi.clear_written_data();
+        assert!(st7567.apply_calibration(&TUNED).is_ok());
+        assert!(st7567.spi.get_written_data().is_empty());
+        assert!(st7567.init().is_ok());
+        assert_eq!(st7567.spi.get_written_data()[0], ST7567_BIAS_1_9);
+    }
+}
@@ -0,0 +1,345 @@
+//! Typed controller commands
+//!
+//! Every command the driver sends goes through [`Command`], whose encoding
+//! checks parameters against the field widths of the controller, so an out
+//! of range value is rejected instead of spilling into the opcode bits:
+//!
+//! ```
+//! use st7567::Command;
+//!
+//! assert_eq!(Command::SetStartLine(10).encode().unwrap().as_bytes(), [0x4a]);
+//! assert!(Command::SetStartLine(64).encode().is_none());
+//! ```
+//!
+//! [`ST7567::send_commands`](crate::ST7567::send_commands) sends a list of
+//! them. [`Command::decode`] goes the other way, and is the one description
+//! of the command grammar: the [trace](crate::trace) decoder and the
+//! [`init_sequence!`](crate::init_sequence) checks are built on it.
+use crate::consts::*;
+use crate::Bias;
+
+/// Highest column address of the controller RAM.
+const LAST_COLUMN: u8 = 131;
+
+/// Highest page address, page 8 being the icon row.
+const LAST_PAGE: u8 = 8;
+
+/// A controller command and its parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    DisplayOn,
+    DisplayOff,
+    /// RAM line shown at the top of the screen, `0..=63`
+    SetStartLine(u8),
+    /// Page address for the following data, `0..=8`, page 8 being the
+    /// icon row
+    SetPage(u8),
+    /// Column address for the following data, `0..=131`
+    SetColumn(u8),
+    /// Low nibble of the column address alone, `0..=15`
+    SetColumnLow(u8),
+    /// High nibble of the column address alone, `0..=8`
+    SetColumnHigh(u8),
+    /// Reverse the segment scan, mirroring horizontally
+    SetSegmentReverse(bool),
+    /// Reverse the COM scan, mirroring vertically
+    SetComReverse(bool),
+    SetInverse(bool),
+    /// Turn every pixel on regardless of the RAM content
+    SetAllPixelsOn(bool),
+    SetBias(Bias),
+    /// Booster, regulator and follower enable bits, `0..=7`
+    SetPowerControl(u8),
+    /// Regulation resistor ratio, `0..=7`
+    SetRegulationRatio(u8),
+    /// Electronic volume, `0..=63`
+    SetContrast(u8),
+    /// Booster level, 0 for 4x and 1 for 5x
+    SetBooster(u8),
+    EnterReadModifyWrite,
+    ExitReadModifyWrite,
+    SoftwareReset,
+    Nop,
+}
+
+/// The bytes of an encoded [`Command`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncodedCommand {
+    bytes: [u8; 2],
+    len: usize,
+}
+
+impl EncodedCommand {
+    fn one(byte: u8) -> Self {
+        EncodedCommand {
+            bytes: [byte, 0],
+            len: 1,
+        }
+    }
+
+    fn two(first: u8, second: u8) -> Self {
+        EncodedCommand {
+            bytes: [first, second],
+            len: 2,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.bytes.get(..self.len).unwrap_or_default()
+    }
+}
+
+/// Why [`Command::decode`] failed on the start of a byte stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The first byte is not an opcode of the controller, or there is none
+    UnknownOpcode,
+    /// The opcode takes a parameter byte, missing from the stream
+    MissingParameter,
+    /// The parameter byte is out of range for the opcode
+    ParameterOutOfRange,
+}
+
+impl Command {
+    /// Whether the parameter fits its field of the opcode.
+    const fn in_range(self) -> bool {
+        match self {
+            Command::SetStartLine(line) => line <= ST7567_STARTLINE_MASK,
+            Command::SetPage(page) => page <= LAST_PAGE,
+            Command::SetColumn(column) => column <= LAST_COLUMN,
+            Command::SetColumnLow(nibble) => nibble <= ST7567_COLL_MASK,
+            Command::SetColumnHigh(nibble) => nibble <= LAST_COLUMN >> 4,
+            Command::SetPowerControl(bits) => bits <= 7,
+            Command::SetRegulationRatio(ratio) => ratio <= 7,
+            Command::SetContrast(value) => value <= ST7567_CONTRAST_MASK,
+            Command::SetBooster(level) => level <= ST7567_SETBOOSTER5X,
+            _ => true,
+        }
+    }
+
+    /// Decode the command at the start of `bytes`, returning it with the
+    /// number of bytes it takes.
+    ///
+    /// A column address nibble followed by the other one decodes as a
+    /// single [`SetColumn`](Self::SetColumn) when that column exists. Bytes
+    /// whose parameter bits are out of range, like page 9, are not opcodes.
+    /// Usable in constant expressions.
+    pub const fn decode(bytes: &[u8]) -> Result<(Command, usize), DecodeError> {
+        let (opcode, next) = match bytes {
+            [] => return Err(DecodeError::UnknownOpcode),
+            [opcode] => (*opcode, None),
+            [opcode, next, ..] => (*opcode, Some(*next)),
+        };
+        let nibble = opcode & 0x0f;
+        let command = match opcode {
+            0x00..=0x0f => match next {
+                Some(high @ 0x10..=0x1f) => {
+                    Command::SetColumn((high & ST7567_COLH_MASK) << 4 | nibble)
+                }
+                _ => Command::SetColumnLow(nibble),
+            },
+            0x10..=0x1f => match next {
+                Some(low @ 0x00..=0x0f) => Command::SetColumn(nibble << 4 | low),
+                _ => Command::SetColumnHigh(nibble),
+            },
+            0x20..=0x27 => Command::SetRegulationRatio(opcode & 0x07),
+            0x28..=0x2f => Command::SetPowerControl(opcode & 0x07),
+            0x40..=0x7f => Command::SetStartLine(opcode & ST7567_STARTLINE_MASK),
+            ST7567_SETCONTRAST | ST7567_SETBOOSTER => {
+                let value = match next {
+                    Some(value) => value,
+                    None => return Err(DecodeError::MissingParameter),
+                };
+                let command = if opcode == ST7567_SETCONTRAST {
+                    Command::SetContrast(value)
+                } else {
+                    Command::SetBooster(value)
+                };
+                if !command.in_range() {
+                    return Err(DecodeError::ParameterOutOfRange);
+                }
+                return Ok((command, 2));
+            }
+            ST7567_SEG_DIR_NORMAL | ST7567_SEG_DIR_REV => {
+                Command::SetSegmentReverse(opcode == ST7567_SEG_DIR_REV)
+            }
+            ST7567_BIAS_1_9 => Command::SetBias(Bias::OneNinth),
+            ST7567_BIAS_1_7 => Command::SetBias(Bias::OneSeventh),
+            ST7567_DISPRAM | ST7567_DISPENTIRE => {
+                Command::SetAllPixelsOn(opcode == ST7567_DISPENTIRE)
+            }
+            ST7567_DISPNORMAL | ST7567_DISPINVERSE => {
+                Command::SetInverse(opcode == ST7567_DISPINVERSE)
+            }
+            ST7567_DISPOFF => Command::DisplayOff,
+            ST7567_DISPON => Command::DisplayOn,
+            0xb0..=0xbf => Command::SetPage(nibble),
+            // The low bits of the COM direction are ignored
+            0xc0..=0xcf => Command::SetComReverse(opcode & 0x08 != 0),
+            ST7567_ENTER_RMWMODE => Command::EnterReadModifyWrite,
+            ST7567_EXIT_RMWMODE => Command::ExitReadModifyWrite,
+            ST7567_EXIT_SOFTRST => Command::SoftwareReset,
+            ST7567_NOP => Command::Nop,
+            _ => return Err(DecodeError::UnknownOpcode),
+        };
+        match command {
+            Command::SetColumn(_) if command.in_range() => Ok((command, 2)),
+            // A pair naming a column past the end is two lone nibbles
+            Command::SetColumn(_) if opcode <= ST7567_COLL_MASK => {
+                Ok((Command::SetColumnLow(nibble), 1))
+            }
+            Command::SetColumn(_) if nibble <= LAST_COLUMN >> 4 => {
+                Ok((Command::SetColumnHigh(nibble), 1))
+            }
+            _ if command.in_range() => Ok((command, 1)),
+            _ => Err(DecodeError::UnknownOpcode),
+        }
+    }
+
+    /// The bytes to send, `None` when the parameter is out of range.
+    pub fn encode(self) -> Option<EncodedCommand> {
+        if !self.in_range() {
+            return None;
+        }
+        let pick = |condition: bool, yes: u8, no: u8| if condition { yes } else { no };
+        let encoded = match self {
+            Command::DisplayOn => EncodedCommand::one(ST7567_DISPON),
+            Command::DisplayOff => EncodedCommand::one(ST7567_DISPOFF),
+            Command::SetStartLine(line) => EncodedCommand::one(ST7567_SETSTARTLINE | line),
+            Command::SetPage(page) => EncodedCommand::one(ST7567_SETPAGESTART | page),
+            Command::SetColumn(column) => EncodedCommand::two(
+                ST7567_SETCOLL | (column & ST7567_COLL_MASK),
+                ST7567_SETCOLH | ((column >> 4) & ST7567_COLH_MASK),
+            ),
+            Command::SetColumnLow(nibble) => EncodedCommand::one(ST7567_SETCOLL | nibble),
+            Command::SetColumnHigh(nibble) => EncodedCommand::one(ST7567_SETCOLH | nibble),
+            Command::SetSegmentReverse(reverse) => {
+                EncodedCommand::one(pick(reverse, ST7567_SEG_DIR_REV, ST7567_SEG_DIR_NORMAL))
+            }
+            Command::SetComReverse(reverse) => {
+                EncodedCommand::one(pick(reverse, ST7567_SETCOMREVERSE, ST7567_SETCOMNORMAL))
+            }
+            Command::SetInverse(inverse) => {
+                EncodedCommand::one(pick(inverse, ST7567_DISPINVERSE, ST7567_DISPNORMAL))
+            }
+            Command::SetAllPixelsOn(on) => {
+                EncodedCommand::one(pick(on, ST7567_DISPENTIRE, ST7567_DISPRAM))
+            }
+            Command::SetBias(bias) => EncodedCommand::one(pick(
+                bias == Bias::OneNinth,
+                ST7567_BIAS_1_9,
+                ST7567_BIAS_1_7,
+            )),
+            Command::SetPowerControl(bits) => EncodedCommand::one(ST7567_POWERCTRL_OFF | bits),
+            Command::SetRegulationRatio(ratio) => EncodedCommand::one(ST7567_REG_RATIO | ratio),
+            Command::SetContrast(value) => EncodedCommand::two(ST7567_SETCONTRAST, value),
+            Command::SetBooster(level) => EncodedCommand::two(ST7567_SETBOOSTER, level),
+            Command::EnterReadModifyWrite => EncodedCommand::one(ST7567_ENTER_RMWMODE),
+            Command::ExitReadModifyWrite => EncodedCommand::one(ST7567_EXIT_RMWMODE),
+            Command::SoftwareReset => EncodedCommand::one(ST7567_EXIT_SOFTRST),
+            Command::Nop => EncodedCommand::one(ST7567_NOP),
+        };
+        Some(encoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::create_test_st7567;
+    use crate::Error;
+
+    fn bytes(command: Command) -> Option<Vec<u8>> {
+        command.encode().map(|encoded| encoded.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(bytes(Command::SetStartLine(63)), Some(vec![0x7f]));
+        assert_eq!(bytes(Command::SetStartLine(64)), None);
+        assert_eq!(bytes(Command::SetPage(8)), Some(vec![0xb8]));
+        assert_eq!(bytes(Command::SetPage(9)), None);
+        assert_eq!(bytes(Command::SetColumn(131)), Some(vec![0x03, 0x18]));
+        assert_eq!(bytes(Command::SetColumn(132)), None);
+        assert_eq!(bytes(Command::SetPowerControl(7)), Some(vec![0x2f]));
+        assert_eq!(bytes(Command::SetPowerControl(8)), None);
+        assert_eq!(bytes(Command::SetRegulationRatio(8)), None);
+        assert_eq!(bytes(Command::SetContrast(40)), Some(vec![0x81, 40]));
+        assert_eq!(bytes(Command::SetContrast(64)), None);
+        assert_eq!(bytes(Command::SetBias(Bias::OneNinth)), Some(vec![0xa2]));
+        assert_eq!(bytes(Command::SetComReverse(true)), Some(vec![0xc8]));
+        assert_eq!(bytes(Command::SetColumnHigh(8)), Some(vec![0x18]));
+        assert_eq!(bytes(Command::SetColumnHigh(9)), None);
+        assert_eq!(bytes(Command::SetBooster(1)), Some(vec![0xf8, 0x01]));
+        assert_eq!(bytes(Command::SetBooster(2)), None);
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(Command::decode(&[0xb8]), Ok((Command::SetPage(8), 1)));
+        assert_eq!(Command::decode(&[0xb9]), Err(DecodeError::UnknownOpcode));
+        assert_eq!(
+            Command::decode(&[0x03, 0x18]),
+            Ok((Command::SetColumn(131), 2))
+        );
+        assert_eq!(
+            Command::decode(&[0x18, 0x03]),
+            Ok((Command::SetColumn(131), 2))
+        );
+        assert_eq!(
+            Command::decode(&[0x04, 0x18]),
+            Ok((Command::SetColumnLow(4), 1))
+        );
+        assert_eq!(Command::decode(&[0x19]), Err(DecodeError::UnknownOpcode));
+        assert_eq!(
+            Command::decode(&[0xc3]),
+            Ok((Command::SetComReverse(false), 1))
+        );
+        assert_eq!(Command::decode(&[0x81]), Err(DecodeError::MissingParameter));
+        assert_eq!(
+            Command::decode(&[0x81, 0x40]),
+            Err(DecodeError::ParameterOutOfRange)
+        );
+        assert_eq!(Command::decode(&[]), Err(DecodeError::UnknownOpcode));
+    }
+
+    #[test]
+    fn test_decode_inverts_encode() {
+        // Every byte, and every byte followed by a parameter or nibble
+        for first in 0..=u8::MAX {
+            for second in [
+                None,
+                Some(0x00),
+                Some(0x01),
+                Some(0x18),
+                Some(0x3f),
+                Some(0xff),
+            ] {
+                let stream: Vec<u8> = std::iter::once(first).chain(second).collect();
+                if let Ok((command, len)) = Command::decode(&stream) {
+                    let encoded = bytes(command).expect("decoded commands encode");
+                    assert_eq!(Command::decode(&encoded), Ok((command, len)));
+                    // Same bytes, except for the nibble order of column
+                    // pairs and the ignored COM direction bits
+                    if !matches!(command, Command::SetColumn(_) | Command::SetComReverse(_)) {
+                        assert_eq!(encoded, stream[..len]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_send_commands() {
+        let mut st7567 = create_test_st7567();
+        let result = st7567.send_commands(&[Command::Nop, Command::SetStartLine(64)]);
+        assert!(matches!(result, Err(Error::InvalidArgument)));
+        assert!(st7567.spi.get_written_data().is_empty());
+
+        // Longer lists are split over several transfers
+        let contrast = [Command::SetContrast(0x22); 20];
+        assert!(st7567.send_commands(&contrast).is_ok());
+        assert_eq!(st7567.spi.get_written_data(), [0x81, 0x22].repeat(20));
+        assert_eq!(st7567.dc_pin.get_states().len(), 2);
+    }
+}
@@ -0,0 +1,129 @@
+//! API in the style of the `ssd1306` crate
+//!
+//! Projects moving from an SSD1306 or SH1106 OLED to this LCD can keep most
+//! of their code by going through [`BufferedGraphicsMode`], which mirrors the
+//! method names of `ssd1306`'s buffered graphics mode:
+//!
+//! ```ignore
+//! let mut display = ST7567::new(spi, dc, rst).into_buffered_graphics_mode();
+//! display.init()?;
+//! display.clear_buffer();
+//! display.set_pixel(10, 20, true);
+//! display.flush()?;
+//! ```
+//!
+//! Differences: the LCD needs a [`reset`](crate::ST7567::reset) before
+//! `init()`, reachable through [`BufferedGraphicsMode::inner_mut`], and
+//! `flush()` always sends the whole frame.
+use crate::transport::Transport;
+use crate::{Error, Pin, PixelBuffer, BUFFER_SIZE, ST7567};
+
+/// A driver wrapped with `ssd1306`-style method names.
+pub struct BufferedGraphicsMode<
+    DC: Pin,
+    RST: Pin,
+    S: Transport,
+    B: PixelBuffer = [u8; BUFFER_SIZE],
+    const W: usize = 128,
+    const H: usize = 64,
+> {
+    display: ST7567<DC, RST, S, B, W, H>,
+}
+
+impl<DC: Pin, RST: Pin, S: Transport, B: PixelBuffer, const W: usize, const H: usize>
+    ST7567<DC, RST, S, B, W, H>
+{
+    /// Switch to the `ssd1306`-style API.
+    pub fn into_buffered_graphics_mode(self) -> BufferedGraphicsMode<DC, RST, S, B, W, H> {
+        BufferedGraphicsMode { display: self }
+    }
+}
+
+impl<DC: Pin, RST: Pin, S: Transport, B: PixelBuffer, const W: usize, const H: usize>
+    BufferedGraphicsMode<DC, RST, S, B, W, H>
+{
+    /// Send the initialization sequence, see [`ST7567::init`].
+    pub fn init(&mut self) -> Result<(), Error<DC, RST, S>> {
+        self.display.init()
+    }
+
+    /// Send the framebuffer to the display.
+    pub fn flush(&mut self) -> Result<(), Error<DC, RST, S>> {
+        self.display.show()
+    }
+
+    /// Turn every pixel of the framebuffer off.
+    pub fn clear_buffer(&mut self) {
+        self.display.clear();
+    }
+
+    /// Set a pixel in the framebuffer. Out of screen pixels are ignored.
+    pub fn set_pixel(&mut self, x: u32, y: u32, value: bool) {
+        self.display.set_pixel(x as usize, y as usize, value);
+    }
+
+    /// Width and height of the display, in pixels.
+    pub fn dimensions(&self) -> (u8, u8) {
+        let (width, height) = self.display.dimensions();
+        (width as u8, height as u8)
+    }
+
+    /// Invert the display in hardware.
+    pub fn set_invert(&mut self, invert: bool) -> Result<(), Error<DC, RST, S>> {
+        self.display.set_inverted(invert)
+    }
+
+    /// Turn the display on or off. The controller RAM is kept.
+    pub fn set_display_on(&mut self, on: bool) -> Result<(), Error<DC, RST, S>> {
+        if on {
+            self.display.display_on()
+        } else {
+            self.display.display_off()
+        }
+    }
+
+    /// The wrapped driver.
+    pub fn inner(&self) -> &ST7567<DC, RST, S, B, W, H> {
+        &self.display
+    }
+
+    /// The wrapped driver, for settings without an `ssd1306` counterpart.
+    pub fn inner_mut(&mut self) -> &mut ST7567<DC, RST, S, B, W, H> {
+        &mut self.display
+    }
+
+    /// Go back to the native API.
+    pub fn release(self) -> ST7567<DC, RST, S, B, W, H> {
+        self.display
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consts::*;
+    use crate::tests::create_test_st7567;
+    use crate::PixelBuffer;
+
+    #[test]
+    fn test_buffered_graphics_mode() {
+        let mut display = create_test_st7567().into_buffered_graphics_mode();
+        assert_eq!(display.dimensions(), (128, 64));
+        assert!(display.init().is_ok());
+        display.set_pixel(3, 9, true);
+        assert!(display.inner().buf.get(3, 9));
+        display.clear_buffer();
+        assert!(!display.inner().buf.get(3, 9));
+
+        display.inner_mut().spi.clear_written_data();
+        assert!(display.set_display_on(false).is_ok());
+        assert!(display.set_invert(true).is_ok());
+        assert!(display.flush().is_ok());
+        let st7567 = display.release();
+        let written_data = st7567.spi.get_written_data();
+        assert_eq!(
+            written_data[..3],
+            [ST7567_DISPOFF, ST7567_DISPINVERSE, ST7567_ENTER_RMWMODE]
+        );
+        assert_eq!(written_data.len(), 2 + 1 + 8 * (3 + 128) + 1);
+    }
+}
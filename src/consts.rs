@@ -1,6 +1,9 @@
 pub const SPI_SPEED_HZ: u32 = 1_000_000;
 pub const WIDTH: u8 = 128;
 pub const HEIGHT: u8 = 64;
+/// Columns of the controller RAM, including the 4 not shown by a 128 pixels
+/// wide panel
+pub const RAM_COLUMNS: u8 = 132;
 pub const ST7567_PAGESIZE: u8 = 128;
 
 pub const ST7567_DISPOFF: u8 = 0xae; // 0xae: Display OFF (sleep mode) */
@@ -35,10 +38,12 @@ pub const ST7567_POWERCTRL_VF: u8 = 0x29; // 0x29: Control built-in power circui
 pub const ST7567_POWERCTRL_VR: u8 = 0x2a; // 0x2a: Control built-in power circuit */
 pub const ST7567_POWERCTRL_VB: u8 = 0x2c; // 0x2c: Control built-in power circuit */
 pub const ST7567_POWERCTRL: u8 = 0x2f; // 0x2c: Control built-in power circuit */
+pub const ST7567_POWERCTRL_OFF: u8 = 0x28; // 0x28: Built-in power circuits all off */
 pub const ST7567_REG_RES_RR0: u8 = 0x21; // 0x21: Regulation Resistior ratio */
 pub const ST7567_REG_RES_RR1: u8 = 0x22; // 0x22: Regulation Resistior ratio */
 pub const ST7567_REG_RES_RR2: u8 = 0x24; // 0x24: Regulation Resistior ratio */
 pub const ST7567_SETCONTRAST: u8 = 0x81; // 0x81: Set contrast control */
+pub const ST7567_CONTRAST_MASK: u8 = 0x3f;
 pub const ST7567_SETBOOSTER: u8 = 0xf8; // Set booster level */
 pub const ST7567_SETBOOSTER4X: u8 = 0x00; // Set booster level */
 pub const ST7567_SETBOOSTER5X: u8 = 0x01; // Set booster level */
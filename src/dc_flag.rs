@@ -0,0 +1,21 @@
+//! DC pin for transports without a DC line
+//!
+//! The 3-wire and I2C transports select commands or data in-band: the D/C
+//! bit of a 9-bit word, or the control byte of an I2C write. Each of them
+//! shares a `Cell<bool>` with a [`DcFlag`], handed to the driver in place of
+//! the DC pin, and reads the level the driver last set from it.
+use crate::{Pin, PinState};
+use core::cell::Cell;
+
+/// DC pin of an in-band transport: sets the D/C level of the following
+/// bytes.
+pub struct DcFlag<'a>(pub(crate) &'a Cell<bool>);
+
+impl Pin for DcFlag<'_> {
+    type Error = core::convert::Infallible;
+
+    fn set_value(&mut self, pin_state: PinState) -> Result<(), Self::Error> {
+        self.0.set(pin_state == PinState::High);
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+//! Human readable differences between two frames
+//!
+//! When a golden-frame test fails, `assert_eq!` on two 1KB arrays is not very
+//! helpful. [`FrameDiff`] summarizes where the frames differ and its `Display`
+//! implementation renders an ASCII overlay of the differing area:
+//!
+//! * `#` pixel set in both frames
+//! * `.` pixel clear in both frames
+//! * `+` pixel only set in the actual frame
+//! * `-` pixel only set in the golden frame
+//!
+//! Both frames are 128x64, [`BUFFER_SIZE`] bytes in controller page layout.
+//! Other panel sizes are not supported.
+//!
+//! ```
+//! use st7567::diff::FrameDiff;
+//! use st7567::BUFFER_SIZE;
+//!
+//! let golden = [0u8; BUFFER_SIZE];
+//! let mut actual = [0u8; BUFFER_SIZE];
+//! actual[130] = 0b0000_0001; // pixel (2, 8)
+//!
+//! let diff = FrameDiff::new(&golden, &actual);
+//! assert_eq!(diff.count(), 1);
+//! assert!(diff.to_string().contains("1 pixel(s) differ"));
+//! ```
+use crate::buffer::{lit_area, BoundingBox, Geometry};
+use crate::{BUFFER_SIZE, HEIGHT, WIDTH};
+use core::fmt;
+
+/// Differences between a golden frame and an actual frame.
+pub struct FrameDiff<'a> {
+    golden: &'a [u8; BUFFER_SIZE],
+    actual: &'a [u8; BUFFER_SIZE],
+    count: usize,
+    bounding_box: Option<BoundingBox>,
+}
+
+fn pixel(buf: &[u8; BUFFER_SIZE], x: usize, y: usize) -> bool {
+    Geometry::DEFAULT.get(buf, x, y)
+}
+
+impl<'a> FrameDiff<'a> {
+    pub fn new(golden: &'a [u8; BUFFER_SIZE], actual: &'a [u8; BUFFER_SIZE]) -> Self {
+        let (count, bounding_box) = lit_area(
+            golden.iter().zip(actual.iter()).map(|(g, a)| g ^ a),
+            WIDTH as usize,
+        );
+        Self {
+            golden,
+            actual,
+            count,
+            bounding_box,
+        }
+    }
+
+    /// Number of differing pixels.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// `true` if both frames are identical.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Smallest rectangle containing all differing pixels.
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        self.bounding_box
+    }
+}
+
+impl fmt::Display for FrameDiff<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bb = match self.bounding_box {
+            None => return write!(f, "frames are identical"),
+            Some(bb) => bb,
+        };
+        writeln!(
+            f,
+            "{} pixel(s) differ in {}x{} area at ({}, {}) ('+' extra, '-' missing)",
+            self.count, bb.width, bb.height, bb.x, bb.y
+        )?;
+        // Show a bit of context around the differences, clipped to the screen
+        let x0 = bb.x.saturating_sub(2);
+        let y0 = bb.y.saturating_sub(2);
+        let x1 =
+            bb.x.saturating_add(bb.width)
+                .saturating_add(2)
+                .min(WIDTH as usize);
+        let y1 =
+            bb.y.saturating_add(bb.height)
+                .saturating_add(2)
+                .min(HEIGHT as usize);
+        for y in y0..y1 {
+            write!(f, "{:3} ", y)?;
+            for x in x0..x1 {
+                let c = match (pixel(self.golden, x, y), pixel(self.actual, x, y)) {
+                    (true, true) => '#',
+                    (false, false) => '.',
+                    (false, true) => '+',
+                    (true, false) => '-',
+                };
+                write!(f, "{}", c)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(buf: &mut [u8; BUFFER_SIZE], x: usize, y: usize) {
+        buf[(y / 8) * WIDTH as usize + x] |= 1 << (y % 8);
+    }
+
+    #[test]
+    fn test_identical_frames() {
+        let frame = [0x55u8; BUFFER_SIZE];
+        let diff = FrameDiff::new(&frame, &frame);
+        assert!(diff.is_empty());
+        assert_eq!(diff.bounding_box(), None);
+        assert_eq!(diff.to_string(), "frames are identical");
+    }
+
+    #[test]
+    fn test_bounding_box_and_count() {
+        let golden = [0u8; BUFFER_SIZE];
+        let mut actual = [0u8; BUFFER_SIZE];
+        set(&mut actual, 10, 5);
+        set(&mut actual, 20, 17);
+        set(&mut actual, 127, 63);
+        let diff = FrameDiff::new(&golden, &actual);
+        assert_eq!(diff.count(), 3);
+        assert_eq!(
+            diff.bounding_box(),
+            Some(BoundingBox {
+                x: 10,
+                y: 5,
+                width: 118,
+                height: 59
+            })
+        );
+    }
+
+    #[test]
+    fn test_overlay() {
+        let mut golden = [0u8; BUFFER_SIZE];
+        let mut actual = [0u8; BUFFER_SIZE];
+        set(&mut golden, 1, 1);
+        set(&mut actual, 1, 1);
+        set(&mut golden, 2, 1);
+        set(&mut actual, 0, 0);
+        let report = FrameDiff::new(&golden, &actual).to_string();
+        let expected = "\
+2 pixel(s) differ in 3x2 area at (0, 0) ('+' extra, '-' missing)
+  0 +....
+  1 .#-..
+  2 .....
+  3 .....
+";
+        assert_eq!(report, expected);
+    }
+}
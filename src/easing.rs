@@ -0,0 +1,183 @@
+//! Integer easing curves for animations
+//!
+//! Transitions, marquees, gauge needles or contrast fades all boil down to
+//! interpolating between two values over a number of steps. The curves below
+//! are computed with integer arithmetic only (16 fractional bits internally),
+//! so they can be used on `no_std` targets without an FPU.
+//!
+//! ```
+//! use st7567::easing::Easing;
+//!
+//! // Slide from x = 0 to x = 100 in 10 frames
+//! assert_eq!(Easing::Linear.interpolate(0, 100, 5, 10), 50);
+//! assert_eq!(Easing::OutQuad.interpolate(0, 100, 5, 10), 75);
+//! assert_eq!(Easing::OutQuad.interpolate(0, 100, 10, 10), 100);
+//! ```
+use crate::fixed::{Fixed, FRAC_BITS};
+
+const SCALE_BITS: u32 = 16;
+const ONE: i64 = 1 << SCALE_BITS;
+
+/// An easing curve, mapping a progress in `0..=1` to an eased progress.
+///
+/// Every curve maps 0 to 0 and 1 to 1, and never leaves the `0..=1` range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Easing {
+    #[default]
+    Linear,
+    InQuad,
+    OutQuad,
+    InOutQuad,
+    InCubic,
+    OutCubic,
+    InOutCubic,
+    InBounce,
+    OutBounce,
+}
+
+impl Easing {
+    /// Apply the curve to a Q8.8 progress, clamped to `0..=1`.
+    pub fn apply(self, t: Fixed) -> Fixed {
+        let t = i64::from(t.raw()).clamp(0, 1 << FRAC_BITS) << (SCALE_BITS - FRAC_BITS);
+        let eased = self.ease(t) >> (SCALE_BITS - FRAC_BITS);
+        Fixed::from_raw(eased as i16)
+    }
+
+    /// Eased value between `from` and `to` after `step` out of `steps`.
+    ///
+    /// `step` is clamped to `steps`; zero `steps` jumps straight to `to`.
+    pub fn interpolate(self, from: i32, to: i32, step: u32, steps: u32) -> i32 {
+        if steps == 0 || step >= steps {
+            return to;
+        }
+        let t = (i64::from(step) << SCALE_BITS)
+            .checked_div(i64::from(steps))
+            .unwrap_or(ONE);
+        let delta = i64::from(to).saturating_sub(i64::from(from));
+        let offset = delta.saturating_mul(self.ease(t)) >> SCALE_BITS;
+        i64::from(from).saturating_add(offset) as i32
+    }
+
+    /// Core curve, working on 16 fractional bits, `t` in `0..=ONE`.
+    // Values stay in `0..=2 * ONE`, products fit in 51 bits
+    #[allow(clippy::arithmetic_side_effects)]
+    fn ease(self, t: i64) -> i64 {
+        match self {
+            Easing::Linear => t,
+            Easing::InQuad => mul(t, t),
+            Easing::OutQuad => ONE - Easing::InQuad.ease(ONE - t),
+            Easing::InOutQuad => {
+                if t < ONE / 2 {
+                    2 * mul(t, t)
+                } else {
+                    let u = 2 * (ONE - t);
+                    ONE - mul(u, u) / 2
+                }
+            }
+            Easing::InCubic => mul(mul(t, t), t),
+            Easing::OutCubic => ONE - Easing::InCubic.ease(ONE - t),
+            Easing::InOutCubic => {
+                if t < ONE / 2 {
+                    4 * mul(mul(t, t), t)
+                } else {
+                    let u = 2 * (ONE - t);
+                    ONE - mul(mul(u, u), u) / 2
+                }
+            }
+            Easing::InBounce => ONE - Easing::OutBounce.ease(ONE - t),
+            Easing::OutBounce => out_bounce(t),
+        }
+    }
+}
+
+fn mul(a: i64, b: i64) -> i64 {
+    a.saturating_mul(b) >> SCALE_BITS
+}
+
+/// Classic "bounce" curve: a parabola followed by three smaller rebounds.
+///
+/// Uses the usual constants (n = 7.5625, d = 2.75) expressed as exact ratios.
+#[allow(clippy::arithmetic_side_effects)]
+fn out_bounce(t: i64) -> i64 {
+    let bounce = |u: i64, base: i64| (121 * mul(u, u)) / 16 + base;
+    // The rounding of the last rebound would otherwise miss the end point
+    if t >= ONE {
+        ONE
+    } else if t < ONE * 4 / 11 {
+        bounce(t, 0)
+    } else if t < ONE * 8 / 11 {
+        bounce(t - ONE * 6 / 11, ONE * 3 / 4)
+    } else if t < ONE * 10 / 11 {
+        bounce(t - ONE * 9 / 11, ONE * 15 / 16)
+    } else {
+        bounce(t - ONE * 21 / 22, ONE * 63 / 64)
+    }
+    .min(ONE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Easing; 9] = [
+        Easing::Linear,
+        Easing::InQuad,
+        Easing::OutQuad,
+        Easing::InOutQuad,
+        Easing::InCubic,
+        Easing::OutCubic,
+        Easing::InOutCubic,
+        Easing::InBounce,
+        Easing::OutBounce,
+    ];
+
+    #[test]
+    fn test_end_points_and_range() {
+        for easing in ALL.iter() {
+            assert_eq!(easing.apply(Fixed::ZERO), Fixed::ZERO, "{:?}", easing);
+            assert_eq!(easing.apply(Fixed::ONE), Fixed::ONE, "{:?}", easing);
+            for raw in 0..=256 {
+                let v = easing.apply(Fixed::from_raw(raw));
+                assert!(v >= Fixed::ZERO && v <= Fixed::ONE, "{:?}", easing);
+            }
+        }
+    }
+
+    #[test]
+    fn test_out_of_range_progress_is_clamped() {
+        assert_eq!(Easing::InCubic.apply(Fixed::from_int(-3)), Fixed::ZERO);
+        assert_eq!(Easing::InCubic.apply(Fixed::from_int(3)), Fixed::ONE);
+        assert_eq!(Easing::OutQuad.interpolate(10, 20, 50, 10), 20);
+        assert_eq!(Easing::OutQuad.interpolate(10, 20, 0, 0), 20);
+    }
+
+    #[test]
+    fn test_curve_shapes() {
+        assert_eq!(Easing::InQuad.apply(Fixed::HALF), Fixed::from_ratio(1, 4));
+        assert_eq!(Easing::OutQuad.apply(Fixed::HALF), Fixed::from_ratio(3, 4));
+        assert_eq!(Easing::InOutQuad.apply(Fixed::HALF), Fixed::HALF);
+        assert_eq!(Easing::InCubic.apply(Fixed::HALF), Fixed::from_ratio(1, 8));
+        assert_eq!(Easing::OutCubic.apply(Fixed::HALF), Fixed::from_ratio(7, 8));
+        // Bounce touches the floor at the end of its first parabola
+        assert!(Easing::OutBounce.interpolate(0, 1000, 4, 11) >= 999);
+        assert!(Easing::OutBounce.interpolate(0, 1000, 6, 11) < 800);
+        // Decreasing ranges work too
+        assert_eq!(Easing::Linear.interpolate(63, 0, 1, 3), 42);
+    }
+
+    #[test]
+    fn test_extreme_inputs_do_not_overflow() {
+        for easing in ALL.iter() {
+            // Debug builds would panic on any intermediate overflow
+            for &step in [0, 1, u32::MAX / 2, u32::MAX - 1].iter() {
+                easing.interpolate(i32::MIN, i32::MAX, step, u32::MAX);
+                easing.interpolate(i32::MAX, i32::MIN, step, u32::MAX);
+            }
+            easing.apply(Fixed::MIN);
+            easing.apply(Fixed::MAX);
+        }
+        let middle = Easing::Linear.interpolate(i32::MIN, i32::MAX, u32::MAX / 2, u32::MAX);
+        // Progress is only resolved to about 1/65536th of the range
+        assert!(middle.abs() < 1 << 17);
+    }
+}
@@ -0,0 +1,73 @@
+//! RP2040 and RP235x quickstart, using `embassy-rp`
+//!
+//! [`st7567`] checks the bus configuration against what the controller
+//! supports, creates a blocking, transmit-only SPI driver with it, wraps it
+//! with its chip select pin into an [`ExclusiveDevice`], and creates the
+//! driver. The firmware selects the chip with the matching `embassy-rp`
+//! feature:
+//!
+//! ```ignore
+//! let p = embassy_rp::init(Default::default());
+//! let mut display = st7567::embassy::st7567(
+//!     p.SPI0,
+//!     p.PIN_18,
+//!     p.PIN_19,
+//!     Config::default(),
+//!     p.PIN_17,
+//!     p.PIN_20,
+//!     p.PIN_21,
+//! )?;
+//! display.reset(&mut Delay)?;
+//! display.init()?;
+//! ```
+use crate::{SpiConfig, SpiConfigError, ST7567};
+use embassy_rp::gpio::{Level, Output, Pin};
+use embassy_rp::spi::{Blocking, ClkPin, Config, Instance, MosiPin, Phase, Polarity, Spi};
+use embassy_rp::Peri;
+use embedded_hal::spi;
+use embedded_hal_bus::spi::{ExclusiveDevice, NoDelay};
+
+/// The SPI device type built by [`st7567`].
+pub type EmbassySpi<'d, T> = ExclusiveDevice<Spi<'d, T, Blocking>, Output<'d>, NoDelay>;
+
+/// The driver type built by [`st7567`].
+pub type EmbassyST7567<'d, T> = ST7567<Output<'d>, Output<'d>, EmbassySpi<'d, T>>;
+
+/// Create an SPI driver on `spi` with `config` and a driver using it, with
+/// `cs`, `dc` and `rst` as outputs.
+///
+/// Fails if `config` does not suit the controller, see
+/// [`SpiConfig::check`].
+pub fn st7567<'d, T: Instance>(
+    spi: Peri<'d, T>,
+    clk: Peri<'d, impl ClkPin<T> + 'd>,
+    mosi: Peri<'d, impl MosiPin<T> + 'd>,
+    config: Config,
+    cs: Peri<'d, impl Pin>,
+    dc: Peri<'d, impl Pin>,
+    rst: Peri<'d, impl Pin>,
+) -> Result<EmbassyST7567<'d, T>, SpiConfigError> {
+    let mode = spi::Mode {
+        polarity: match config.polarity {
+            Polarity::IdleLow => spi::Polarity::IdleLow,
+            Polarity::IdleHigh => spi::Polarity::IdleHigh,
+        },
+        phase: match config.phase {
+            Phase::CaptureOnFirstTransition => spi::Phase::CaptureOnFirstTransition,
+            Phase::CaptureOnSecondTransition => spi::Phase::CaptureOnSecondTransition,
+        },
+    };
+    SpiConfig {
+        mode,
+        speed_hz: config.frequency,
+    }
+    .check()?;
+    let bus = Spi::new_blocking_txonly(spi, clk, mosi, config);
+    let cs = Output::new(cs, Level::High);
+    let device = ExclusiveDevice::new_no_delay(bus, cs).unwrap_or_else(|e| match e {});
+    Ok(ST7567::new(
+        device,
+        Output::new(dc, Level::High),
+        Output::new(rst, Level::High),
+    ))
+}
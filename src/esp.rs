@@ -0,0 +1,67 @@
+//! ESP32 quickstart, using `esp-hal`
+//!
+//! [`st7567`] checks the bus configuration against what the controller
+//! supports, applies it to an SPI bus, wraps the bus with its chip select
+//! pin into an [`ExclusiveDevice`], and creates the driver. The firmware
+//! selects the chip with the matching `esp-hal` feature:
+//!
+//! ```ignore
+//! let spi = Spi::new(peripherals.SPI2, Config::default())?
+//!     .with_sck(peripherals.GPIO6)
+//!     .with_mosi(peripherals.GPIO7);
+//! let config = Config::default().with_frequency(Rate::from_mhz(4));
+//! let mut display = st7567::esp::st7567(
+//!     spi,
+//!     config,
+//!     peripherals.GPIO10,
+//!     peripherals.GPIO2,
+//!     peripherals.GPIO3,
+//! )?;
+//! display.reset(&mut Delay::new())?;
+//! display.init()?;
+//! ```
+use crate::{SetupError, SpiConfig, ST7567};
+use embedded_hal::spi::{MODE_0, MODE_1, MODE_2, MODE_3};
+use embedded_hal_bus::spi::{ExclusiveDevice, NoDelay};
+use esp_hal::gpio::{Level, Output, OutputConfig, OutputPin};
+use esp_hal::spi::master::{Config, ConfigError, Spi};
+use esp_hal::spi::Mode;
+use esp_hal::Blocking;
+
+/// The SPI device type built by [`st7567`].
+pub type EspSpi<'d> = ExclusiveDevice<Spi<'d, Blocking>, Output<'d>, NoDelay>;
+
+/// The driver type built by [`st7567`].
+pub type EspST7567<'d> = ST7567<Output<'d>, Output<'d>, EspSpi<'d>>;
+
+/// Apply `config` to `spi` and create a driver using it, with `cs`, `dc`
+/// and `rst` as push-pull outputs.
+///
+/// Fails with [`SetupError::Config`] if `config` does not suit the
+/// controller, see [`SpiConfig::check`], and with [`SetupError::Hal`] if
+/// the chip cannot generate its frequency.
+pub fn st7567<'d>(
+    mut spi: Spi<'d, Blocking>,
+    config: Config,
+    cs: impl OutputPin + 'd,
+    dc: impl OutputPin + 'd,
+    rst: impl OutputPin + 'd,
+) -> Result<EspST7567<'d>, SetupError<ConfigError>> {
+    let mode = match config.mode() {
+        Mode::_0 => MODE_0,
+        Mode::_1 => MODE_1,
+        Mode::_2 => MODE_2,
+        Mode::_3 => MODE_3,
+    };
+    SpiConfig {
+        mode,
+        speed_hz: config.frequency().as_hz(),
+    }
+    .check()?;
+    spi.apply_config(&config).map_err(SetupError::Hal)?;
+    let cs = Output::new(cs, Level::High, OutputConfig::default());
+    let dc = Output::new(dc, Level::High, OutputConfig::default());
+    let rst = Output::new(rst, Level::High, OutputConfig::default());
+    let device = ExclusiveDevice::new_no_delay(spi, cs).unwrap_or_else(|e| match e {});
+    Ok(ST7567::new(device, dc, rst))
+}
@@ -0,0 +1,249 @@
+//! Notifications of driver lifecycle transitions
+//!
+//! Applications often have to follow the state of the panel: turn the
+//! backlight off while the controller sleeps, log when the link broke and
+//! when it came back. [`Observed`] wraps the driver and calls a hook with an
+//! [`Event`] for each transition, instead of having the application poll:
+//!
+//! ```ignore
+//! let mut display = Observed::new(display, |event| match event {
+//!     Event::Sleep => backlight.set_low().ok(),
+//!     Event::Wake => backlight.set_high().ok(),
+//!     _ => log::info!("display: {:?}", event),
+//! });
+//! display.display_mut().set_pixel(0, 0, true);
+//! display.show()?;
+//! display.power_off(&mut delay)?;
+//! ```
+//!
+//! Only calls made through the wrapper are observed, drawing goes through
+//! [`display_mut`](Observed::display_mut).
+use crate::power::PowerError;
+use crate::transport::Transport;
+use crate::{Error, Pin, PixelBuffer, ST7567};
+use embedded_hal::delay::DelayNs;
+
+/// A lifecycle transition of the driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// The controller was put to sleep, its supply may be cut
+    Sleep,
+    /// The controller was reset and initialized after a sleep
+    Wake,
+    /// The controller was reset and initialized to recover from errors
+    Reinitialized,
+    /// A call failed after the previous one succeeded
+    Failed,
+    /// A call succeeded after the previous one failed
+    Recovered,
+}
+
+/// A driver reporting its transitions to a hook, see the [module](self)
+/// docs.
+pub struct Observed<
+    F,
+    DC,
+    RST,
+    S,
+    B = [u8; crate::BUFFER_SIZE],
+    const W: usize = 128,
+    const H: usize = 64,
+> where
+    DC: Pin,
+    RST: Pin,
+    S: Transport,
+    B: PixelBuffer,
+{
+    display: ST7567<DC, RST, S, B, W, H>,
+    hook: F,
+    failing: bool,
+}
+
+impl<F, DC, RST, S, B, const W: usize, const H: usize> Observed<F, DC, RST, S, B, W, H>
+where
+    F: FnMut(Event),
+    DC: Pin,
+    RST: Pin,
+    S: Transport,
+    B: PixelBuffer,
+{
+    /// Report the transitions of `display` to `hook`.
+    pub fn new(display: ST7567<DC, RST, S, B, W, H>, hook: F) -> Self {
+        Observed {
+            display,
+            hook,
+            failing: false,
+        }
+    }
+
+    /// Give the driver and the hook back.
+    pub fn release(self) -> (ST7567<DC, RST, S, B, W, H>, F) {
+        (self.display, self.hook)
+    }
+
+    pub fn display(&self) -> &ST7567<DC, RST, S, B, W, H> {
+        &self.display
+    }
+
+    /// The driver, for drawing and settings. Calls made on it directly are
+    /// not reported.
+    pub fn display_mut(&mut self) -> &mut ST7567<DC, RST, S, B, W, H> {
+        &mut self.display
+    }
+
+    /// Report `event` if `result` is a success, and the transitions between
+    /// failures and successes.
+    fn track<T, E>(&mut self, result: Result<T, E>, event: Option<Event>) -> Result<T, E> {
+        let failing = result.is_err();
+        if failing != self.failing {
+            self.failing = failing;
+            (self.hook)(if failing {
+                Event::Failed
+            } else {
+                Event::Recovered
+            });
+        }
+        if let (Ok(_), Some(event)) = (&result, event) {
+            (self.hook)(event);
+        }
+        result
+    }
+
+    /// [`ST7567::show`]
+    pub fn show(&mut self) -> Result<(), Error<DC, RST, S>> {
+        let result = self.display.show();
+        self.track(result, None)
+    }
+
+    /// [`ST7567::show_or_reinit`], reporting [`Event::Reinitialized`] when
+    /// the controller was initialized again.
+    pub fn show_or_reinit<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<DC, RST, S>> {
+        let result = self.display.show_or_reinit_tracked(delay);
+        let event = match result {
+            Ok(true) => Some(Event::Reinitialized),
+            _ => None,
+        };
+        self.track(result, event).map(|_| ())
+    }
+
+    /// [`ST7567::reinit`], reporting [`Event::Reinitialized`].
+    pub fn reinit<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<DC, RST, S>> {
+        let result = self.display.reinit(delay);
+        self.track(result, Some(Event::Reinitialized))
+    }
+
+    /// [`ST7567::recover`]
+    pub fn recover(&mut self) -> Result<(), Error<DC, RST, S>> {
+        let result = self.display.recover();
+        self.track(result, None)
+    }
+
+    /// [`ST7567::power_off`], reporting [`Event::Sleep`].
+    pub fn power_off<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<DC, RST, S>> {
+        let result = self.display.power_off(delay);
+        self.track(result, Some(Event::Sleep))
+    }
+
+    /// Reset and initialize the controller after
+    /// [`power_off`](Self::power_off), reporting [`Event::Wake`].
+    pub fn wake<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<DC, RST, S>> {
+        let result = self.display.reset(delay).and_then(|()| self.display.init());
+        self.track(result, Some(Event::Wake))
+    }
+
+    /// [`ST7567::power_down`], reporting [`Event::Sleep`].
+    pub fn power_down<P: Pin, D: DelayNs>(
+        &mut self,
+        rail: &mut P,
+        delay: &mut D,
+    ) -> Result<(), PowerError<P, DC, RST, S>> {
+        let result = self.display.power_down(rail, delay);
+        self.track(result, Some(Event::Sleep))
+    }
+
+    /// [`ST7567::power_up`], reporting [`Event::Wake`].
+    pub fn power_up<P: Pin, D: DelayNs>(
+        &mut self,
+        rail: &mut P,
+        delay: &mut D,
+    ) -> Result<(), PowerError<P, DC, RST, S>> {
+        let result = self.display.power_up(rail, delay);
+        self.track(result, Some(Event::Wake))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{create_test_st7567, MockDelay, MockPin};
+
+    #[test]
+    fn test_sleep_and_wake() {
+        let mut events = Vec::new();
+        let mut display = Observed::new(create_test_st7567(), |event| events.push(event));
+        let mut delay = MockDelay::new();
+        assert!(display.power_off(&mut delay).is_ok());
+        assert!(display.wake(&mut delay).is_ok());
+        assert!(display.display().is_initialized());
+
+        let mut rail = MockPin::new();
+        assert!(display.power_down(&mut rail, &mut delay).is_ok());
+        assert!(display.power_up(&mut rail, &mut delay).is_ok());
+        assert!(display.show().is_ok());
+        drop(display);
+        assert_eq!(
+            events,
+            [Event::Sleep, Event::Wake, Event::Sleep, Event::Wake]
+        );
+    }
+
+    #[test]
+    fn test_error_transitions() {
+        let mut events = Vec::new();
+        let mut display = Observed::new(create_test_st7567(), |event| events.push(event));
+        let mut delay = MockDelay::new();
+        display.display_mut().set_auto_reinit(2);
+
+        display.display_mut().spi.set_fail(true);
+        assert!(display.show().is_err());
+        assert!(display.show_or_reinit(&mut delay).is_err());
+        // Second failure in a row: reinit, failing too
+        assert!(display.show_or_reinit(&mut delay).is_err());
+        display.display_mut().spi.set_fail(false);
+        assert!(display.recover().is_ok());
+        assert!(display.reinit(&mut delay).is_ok());
+        assert!(display.show().is_ok());
+
+        // A failed wake is not reported as a wake
+        display
+            .display_mut()
+            .rst_pin
+            .as_ref()
+            .unwrap()
+            .set_fail(true);
+        assert!(display.wake(&mut delay).is_err());
+        drop(display);
+        assert_eq!(
+            events,
+            [
+                Event::Failed,
+                Event::Recovered,
+                Event::Reinitialized,
+                Event::Failed
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reinit_on_failures() {
+        let mut events = Vec::new();
+        let mut display = Observed::new(create_test_st7567(), |event| events.push(event));
+        let mut delay = MockDelay::new();
+        display.display_mut().set_auto_reinit(1);
+        // Not initialized: show() fails, reinit() succeeds
+        assert!(display.display_mut().power_off(&mut delay).is_ok());
+        assert!(display.show_or_reinit(&mut delay).is_ok());
+        drop(display);
+        assert_eq!(events, [Event::Reinitialized]);
+    }
+}
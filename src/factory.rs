@@ -0,0 +1,433 @@
+//! End-of-line test of the panel
+//!
+//! [`ST7567::factory_test`] shows a series of patterns, one
+//! [`FactoryStep`] at a time, and polls the operator for a [`Verdict`]
+//! every 10ms. A step without verdict ends after a timeout, so the test
+//! also runs unattended. The result is a [`FactoryReport`]:
+//!
+//! ```ignore
+//! let report = display.factory_test(&mut delay, 5_000, |_step| {
+//!     if pass_button.is_low().unwrap_or(false) {
+//!         Some(Verdict::Pass)
+//!     } else if fail_button.is_low().unwrap_or(false) {
+//!         Some(Verdict::Fail)
+//!     } else {
+//!         None
+//!     }
+//! })?;
+//! log_result(report.passed());
+//! ```
+//!
+//! Backlight and touch LEDs are driven by other chips. Boards having them
+//! implement [`FactoryHooks`] and run
+//! [`factory_test_with_hooks`](ST7567::factory_test_with_hooks), which adds
+//! a step cycling the backlight colors and one lighting each touch LED in
+//! turn. Without hooks, these steps are reported as
+//! [skipped](Outcome::Skipped).
+use crate::transport::Transport;
+use crate::{Command, Error, Pin, PixelBuffer, ST7567};
+use embedded_hal::delay::DelayNs;
+
+/// Time between two polls of the operator.
+const POLL_MS: u32 = 10;
+
+/// Polls between two changes of the `Inversion`, `Backlight` and
+/// `TouchLeds` steps, i.e. 500ms.
+const CYCLE_POLLS: u32 = 50;
+
+/// A pattern of the factory test, in the order they are shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FactoryStep {
+    /// Every pixel on, regardless of the RAM, checking every segment and
+    /// common line is driven
+    AllPixelsOn,
+    /// One pixel checkerboard, showing shorted neighbouring lines
+    Checkerboard,
+    /// Outermost rows and columns, checking the column offset and
+    /// orientation
+    Border,
+    /// Left half on while the contrast cycles from 0 to 63, checking the
+    /// LCD voltage range
+    ContrastSweep,
+    /// Left half on, inverted every 500ms
+    Inversion,
+    /// Blank screen while the backlight cycles through the
+    /// [`BacklightColor`]s every 500ms, if the board has one
+    Backlight,
+    /// Blank screen while the touch LEDs light one after the other every
+    /// 500ms, if the board has some
+    TouchLeds,
+}
+
+impl FactoryStep {
+    pub const ALL: [FactoryStep; 7] = [
+        FactoryStep::AllPixelsOn,
+        FactoryStep::Checkerboard,
+        FactoryStep::Border,
+        FactoryStep::ContrastSweep,
+        FactoryStep::Inversion,
+        FactoryStep::Backlight,
+        FactoryStep::TouchLeds,
+    ];
+}
+
+/// A color shown by the `Backlight` step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BacklightColor {
+    Red,
+    Green,
+    Blue,
+    White,
+}
+
+impl BacklightColor {
+    pub const ALL: [BacklightColor; 4] = [
+        BacklightColor::Red,
+        BacklightColor::Green,
+        BacklightColor::Blue,
+        BacklightColor::White,
+    ];
+}
+
+/// Backlight and touch LEDs of the board, for the last steps of the
+/// factory test.
+///
+/// Every method defaults to the hardware being absent, boards implement the
+/// ones they need.
+pub trait FactoryHooks {
+    /// Whether the board has a backlight the `Backlight` step can check.
+    fn has_backlight(&self) -> bool {
+        false
+    }
+
+    /// Show `color`, or restore the normal backlight on `None` at the end of
+    /// the step.
+    fn set_backlight(&mut self, _color: Option<BacklightColor>) {}
+
+    /// Number of touch LEDs the `TouchLeds` step lights.
+    fn touch_leds(&self) -> usize {
+        0
+    }
+
+    /// Light LED `led` alone, or turn them all off on `None` at the end of
+    /// the step.
+    fn set_touch_led(&mut self, _led: Option<usize>) {}
+}
+
+/// Hooks of a board without backlight nor touch LEDs, used by
+/// [`ST7567::factory_test`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NoHooks;
+
+impl FactoryHooks for NoHooks {}
+
+/// Operator judgement of a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Pass,
+    Fail,
+}
+
+/// Result of a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Pass,
+    Fail,
+    /// No verdict before the timeout
+    Timeout,
+    /// The board does not have the hardware of the step
+    Skipped,
+}
+
+impl From<Verdict> for Outcome {
+    fn from(verdict: Verdict) -> Self {
+        match verdict {
+            Verdict::Pass => Outcome::Pass,
+            Verdict::Fail => Outcome::Fail,
+        }
+    }
+}
+
+/// Result of [`ST7567::factory_test`], one outcome per step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FactoryReport {
+    pub outcomes: [(FactoryStep, Outcome); 7],
+}
+
+impl FactoryReport {
+    /// `true` when the operator passed every step the board has hardware
+    /// for.
+    pub fn passed(&self) -> bool {
+        self.outcomes
+            .iter()
+            .all(|(_, outcome)| matches!(outcome, Outcome::Pass | Outcome::Skipped))
+    }
+}
+
+impl<DC: Pin, RST: Pin, S: Transport, B: PixelBuffer, const W: usize, const H: usize>
+    ST7567<DC, RST, S, B, W, H>
+{
+    /// Run the factory test, see the [module](self) docs.
+    ///
+    /// `operator` is polled every 10ms with the current step, each step
+    /// lasting at most `timeout_ms`. The patterns are drawn in framebuffer
+    /// coordinates, ignoring the rotation. The framebuffer is cleared and
+    /// shown at the end, contrast and inversion are restored.
+    ///
+    /// The `Backlight` and `TouchLeds` steps are skipped, see
+    /// [`factory_test_with_hooks`](Self::factory_test_with_hooks).
+    ///
+    /// Requires `init()`. After an error, the display is left in the state of
+    /// the failed step: call `init()` again.
+    pub fn factory_test<D: DelayNs, F: FnMut(FactoryStep) -> Option<Verdict>>(
+        &mut self,
+        delay: &mut D,
+        timeout_ms: u32,
+        operator: F,
+    ) -> Result<FactoryReport, Error<DC, RST, S>> {
+        self.factory_test_with_hooks(delay, timeout_ms, &mut NoHooks, operator)
+    }
+
+    /// Run the factory test on a board with a backlight or touch LEDs,
+    /// driven through `hooks`.
+    ///
+    /// Same as [`factory_test`](Self::factory_test), with the `Backlight`
+    /// and `TouchLeds` steps run when `hooks` reports the hardware.
+    pub fn factory_test_with_hooks<
+        D: DelayNs,
+        K: FactoryHooks,
+        F: FnMut(FactoryStep) -> Option<Verdict>,
+    >(
+        &mut self,
+        delay: &mut D,
+        timeout_ms: u32,
+        hooks: &mut K,
+        mut operator: F,
+    ) -> Result<FactoryReport, Error<DC, RST, S>> {
+        let mut outcomes = FactoryStep::ALL.map(|step| (step, Outcome::Timeout));
+        for (step, outcome) in outcomes.iter_mut() {
+            *outcome = self.factory_step(*step, delay, timeout_ms, hooks, &mut operator)?;
+        }
+        self.clear();
+        self.show()?;
+        Ok(FactoryReport { outcomes })
+    }
+
+    fn factory_step<D: DelayNs, K: FactoryHooks, F: FnMut(FactoryStep) -> Option<Verdict>>(
+        &mut self,
+        step: FactoryStep,
+        delay: &mut D,
+        timeout_ms: u32,
+        hooks: &mut K,
+        operator: &mut F,
+    ) -> Result<Outcome, Error<DC, RST, S>> {
+        let leds = hooks.touch_leds();
+        match step {
+            FactoryStep::Backlight if !hooks.has_backlight() => return Ok(Outcome::Skipped),
+            FactoryStep::TouchLeds if leds == 0 => return Ok(Outcome::Skipped),
+            _ => {}
+        }
+        self.draw_factory_pattern(step);
+        match step {
+            FactoryStep::AllPixelsOn => self.set_all_pixels_on(true)?,
+            _ => self.show()?,
+        }
+
+        let mut polls = 0u32;
+        let outcome = loop {
+            if let Some(verdict) = operator(step) {
+                break verdict.into();
+            }
+            if polls.saturating_mul(POLL_MS) >= timeout_ms {
+                break Outcome::Timeout;
+            }
+            match step {
+                FactoryStep::ContrastSweep => {
+                    let contrast = (polls % 64) as u8;
+                    self.send_commands(&[Command::SetContrast(contrast)])?;
+                }
+                FactoryStep::Inversion if polls % CYCLE_POLLS == 0 => {
+                    let inverted = (polls / CYCLE_POLLS) % 2 == 0;
+                    self.send_commands(&[Command::SetInverse(inverted != self.is_inverted())])?;
+                }
+                FactoryStep::Backlight if polls % CYCLE_POLLS == 0 => {
+                    let cycle = (polls / CYCLE_POLLS) as usize;
+                    let color = BacklightColor::ALL.get(cycle % 4);
+                    hooks.set_backlight(color.copied());
+                }
+                FactoryStep::TouchLeds if polls % CYCLE_POLLS == 0 => {
+                    let cycle = (polls / CYCLE_POLLS) as usize;
+                    hooks.set_touch_led(cycle.checked_rem(leds));
+                }
+                _ => {}
+            }
+            delay.delay_ms(POLL_MS);
+            polls = polls.saturating_add(1);
+        };
+
+        match step {
+            FactoryStep::AllPixelsOn => self.set_all_pixels_on(false)?,
+            FactoryStep::ContrastSweep => {
+                self.send_commands(&[Command::SetContrast(self.contrast)])?
+            }
+            FactoryStep::Inversion => {
+                self.send_commands(&[Command::SetInverse(self.is_inverted())])?
+            }
+            FactoryStep::Backlight => hooks.set_backlight(None),
+            FactoryStep::TouchLeds => hooks.set_touch_led(None),
+            _ => {}
+        }
+        Ok(outcome)
+    }
+
+    fn draw_factory_pattern(&mut self, step: FactoryStep) {
+        self.dirty_pages = u8::MAX;
+        let bytes = self.buf.as_bytes_mut();
+        let frame = bytes.len().min(crate::buffer_size(W, H));
+        for (offset, byte) in bytes.iter_mut().take(frame).enumerate() {
+            let x = offset.checked_rem(W).unwrap_or(0);
+            *byte = match step {
+                FactoryStep::AllPixelsOn
+                | FactoryStep::Border
+                | FactoryStep::Backlight
+                | FactoryStep::TouchLeds => 0x00,
+                FactoryStep::Checkerboard if x % 2 == 0 => 0x55,
+                FactoryStep::Checkerboard => 0xaa,
+                FactoryStep::ContrastSweep | FactoryStep::Inversion if x < W / 2 => 0xff,
+                FactoryStep::ContrastSweep | FactoryStep::Inversion => 0x00,
+            };
+        }
+        if step == FactoryStep::Border {
+            for x in 0..W {
+                Self::GEOMETRY.set(bytes, x, 0, true);
+                Self::GEOMETRY.set(bytes, x, H.saturating_sub(1), true);
+            }
+            for y in 0..H {
+                Self::GEOMETRY.set(bytes, 0, y, true);
+                Self::GEOMETRY.set(bytes, W.saturating_sub(1), y, true);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::*;
+    use crate::tests::{create_test_st7567, MockDelay};
+
+    #[test]
+    fn test_factory_test_verdicts() {
+        let mut st7567 = create_test_st7567();
+        let mut seen = Vec::new();
+        let report = st7567.factory_test(&mut MockDelay::new(), 1_000, |step| {
+            seen.push(step);
+            match step {
+                FactoryStep::Border => Some(Verdict::Fail),
+                _ => Some(Verdict::Pass),
+            }
+        });
+        let report = report.unwrap();
+        // Without hooks, the operator is not asked about the last steps
+        assert_eq!(seen, FactoryStep::ALL[..5]);
+        assert!(!report.passed());
+        assert_eq!(report.outcomes[2], (FactoryStep::Border, Outcome::Fail));
+        assert_eq!(report.outcomes[4], (FactoryStep::Inversion, Outcome::Pass));
+        assert_eq!(
+            report.outcomes[6],
+            (FactoryStep::TouchLeds, Outcome::Skipped)
+        );
+        assert!(st7567.buffer_stats().is_blank());
+
+        // Skipped steps do not fail the test
+        let report = st7567.factory_test(&mut MockDelay::new(), 1_000, |_| Some(Verdict::Pass));
+        assert!(report.unwrap().passed());
+    }
+
+    /// A board with a backlight and 6 touch LEDs, recording the hook calls.
+    #[derive(Default)]
+    struct Board {
+        backlight: Vec<Option<BacklightColor>>,
+        leds: Vec<Option<usize>>,
+    }
+
+    impl FactoryHooks for Board {
+        fn has_backlight(&self) -> bool {
+            true
+        }
+
+        fn set_backlight(&mut self, color: Option<BacklightColor>) {
+            self.backlight.push(color);
+        }
+
+        fn touch_leds(&self) -> usize {
+            6
+        }
+
+        fn set_touch_led(&mut self, led: Option<usize>) {
+            self.leds.push(led);
+        }
+    }
+
+    #[test]
+    fn test_factory_test_hooks() {
+        let mut st7567 = create_test_st7567();
+        let mut board = Board::default();
+        let mut led_polls = 0;
+        let report =
+            st7567.factory_test_with_hooks(&mut MockDelay::new(), 1_200, &mut board, |step| {
+                match step {
+                    FactoryStep::Backlight => None,
+                    // The operator sees the second LED stay dark
+                    FactoryStep::TouchLeds => {
+                        led_polls += 1;
+                        (led_polls > 60).then_some(Verdict::Fail)
+                    }
+                    _ => Some(Verdict::Pass),
+                }
+            });
+        let report = report.unwrap();
+        assert!(!report.passed());
+        assert_eq!(
+            report.outcomes[5],
+            (FactoryStep::Backlight, Outcome::Timeout)
+        );
+        assert_eq!(report.outcomes[6], (FactoryStep::TouchLeds, Outcome::Fail));
+
+        // A color every 500ms, then the normal backlight back
+        let red = Some(BacklightColor::Red);
+        let green = Some(BacklightColor::Green);
+        let blue = Some(BacklightColor::Blue);
+        assert_eq!(board.backlight, [red, green, blue, None]);
+        assert_eq!(board.leds, [Some(0), Some(1), None]);
+        assert!(st7567.buffer_stats().is_blank());
+    }
+
+    #[test]
+    fn test_factory_test_timeout() {
+        let mut st7567 = create_test_st7567();
+        assert!(st7567.set_inverted(true).is_ok());
+        st7567.spi.clear_written_data();
+        let report = st7567.factory_test(&mut MockDelay::new(), 20, |_| None);
+        let report = report.unwrap();
+        let (panel, board) = report.outcomes.split_at(5);
+        assert!(panel
+            .iter()
+            .all(|(_, outcome)| *outcome == Outcome::Timeout));
+        assert!(board
+            .iter()
+            .all(|(_, outcome)| *outcome == Outcome::Skipped));
+
+        let written_data = st7567.spi.get_written_data();
+        assert_eq!(written_data[0], ST7567_DISPENTIRE);
+        assert_eq!(written_data[1], ST7567_DISPRAM);
+        // The contrast sweep restores the contrast, the inversion step the
+        // inversion
+        let sweep = [0x81, 0, 0x81, 1, 0x81, 40];
+        assert!(written_data.windows(6).any(|w| w == sweep));
+        let inversion = [ST7567_DISPNORMAL, ST7567_DISPINVERSE];
+        assert!(written_data.windows(2).any(|w| w == inversion));
+        assert!(st7567.is_inverted());
+        assert_eq!(st7567.contrast(), 40);
+    }
+}
@@ -0,0 +1,207 @@
+//! C ABI over a Linux driver instance
+//!
+//! Lets C applications, or Python ones through `ctypes`, drive the panel
+//! with this crate. Build a shared library with:
+//!
+//! ```text
+//! cargo rustc --release --features ffi --crate-type cdylib
+//! ```
+//!
+//! The matching declarations are in `include/st7567.h`. Every function
+//! returning an `int` returns [`ST7567_OK`] on success and a negative error
+//! code otherwise. Handles are not thread safe.
+#![allow(unsafe_code)]
+
+use crate::consts::ST7567_CONTRAST_MASK;
+use crate::linux::{LinuxDelay, LinuxST7567};
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int};
+
+pub const ST7567_OK: c_int = 0;
+/// Null handle or pointer, or out of range argument
+pub const ST7567_EINVAL: c_int = -1;
+/// Error talking to the SPI device or driving a GPIO line
+pub const ST7567_EIO: c_int = -2;
+
+/// Opaque driver handle.
+pub struct St7567Handle(LinuxST7567);
+
+fn status<T, E>(result: Result<T, E>) -> c_int {
+    match result {
+        Ok(_) => ST7567_OK,
+        Err(_) => ST7567_EIO,
+    }
+}
+
+/// Open the display on a spidev device, with DC and RST on lines of a GPIO
+/// chip. Returns null on failure.
+///
+/// # Safety
+///
+/// `spidev` and `gpiochip` must be null or valid NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn st7567_open(
+    spidev: *const c_char,
+    gpiochip: *const c_char,
+    dc_line: u32,
+    rst_line: u32,
+) -> *mut St7567Handle {
+    if spidev.is_null() || gpiochip.is_null() {
+        return std::ptr::null_mut();
+    }
+    let (spidev, gpiochip) = match (
+        CStr::from_ptr(spidev).to_str(),
+        CStr::from_ptr(gpiochip).to_str(),
+    ) {
+        (Ok(spidev), Ok(gpiochip)) => (spidev, gpiochip),
+        _ => return std::ptr::null_mut(),
+    };
+    match LinuxST7567::open(spidev, gpiochip, dc_line, rst_line) {
+        Ok(display) => Box::into_raw(Box::new(St7567Handle(display))),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// Release a handle returned by [`st7567_open`]. Null is ignored.
+///
+/// # Safety
+///
+/// `handle` must be null or a handle from [`st7567_open`], not used
+/// afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn st7567_close(handle: *mut St7567Handle) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Reset and initialize the controller.
+///
+/// # Safety
+///
+/// `handle` must be null or a live handle from [`st7567_open`].
+#[no_mangle]
+pub unsafe extern "C" fn st7567_init(handle: *mut St7567Handle) -> c_int {
+    let display = match handle.as_mut() {
+        Some(St7567Handle(display)) => display,
+        None => return ST7567_EINVAL,
+    };
+    if display.reset(&mut LinuxDelay).is_err() {
+        return ST7567_EIO;
+    }
+    status(display.init())
+}
+
+/// Set a pixel in the framebuffer. Out of screen pixels are ignored.
+///
+/// # Safety
+///
+/// `handle` must be null or a live handle from [`st7567_open`].
+#[no_mangle]
+pub unsafe extern "C" fn st7567_set_pixel(
+    handle: *mut St7567Handle,
+    x: u32,
+    y: u32,
+    value: bool,
+) -> c_int {
+    match handle.as_mut() {
+        Some(St7567Handle(display)) => {
+            display.set_pixel(x as usize, y as usize, value);
+            ST7567_OK
+        }
+        None => ST7567_EINVAL,
+    }
+}
+
+/// Copy a whole frame, in controller page layout (see `buffer.rs`), into the
+/// framebuffer. `len` must be [`BUFFER_SIZE`](crate::BUFFER_SIZE).
+///
+/// # Safety
+///
+/// `handle` must be null or a live handle from [`st7567_open`], `data` must
+/// be null or point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn st7567_blit(
+    handle: *mut St7567Handle,
+    data: *const u8,
+    len: usize,
+) -> c_int {
+    let display = match handle.as_mut() {
+        Some(St7567Handle(display)) => display,
+        None => return ST7567_EINVAL,
+    };
+    if data.is_null() || len != crate::BUFFER_SIZE {
+        return ST7567_EINVAL;
+    }
+    let frame = std::slice::from_raw_parts(data, len);
+    display.buffer_mut().copy_from_slice(frame);
+    ST7567_OK
+}
+
+/// Send the framebuffer to the display.
+///
+/// # Safety
+///
+/// `handle` must be null or a live handle from [`st7567_open`].
+#[no_mangle]
+pub unsafe extern "C" fn st7567_show(handle: *mut St7567Handle) -> c_int {
+    match handle.as_mut() {
+        Some(St7567Handle(display)) => status(display.show()),
+        None => ST7567_EINVAL,
+    }
+}
+
+/// Set the contrast, from 0 to 63. Larger values return [`ST7567_EINVAL`]
+/// and leave the contrast unchanged.
+///
+/// # Safety
+///
+/// `handle` must be null or a live handle from [`st7567_open`].
+#[no_mangle]
+pub unsafe extern "C" fn st7567_set_contrast(handle: *mut St7567Handle, value: u8) -> c_int {
+    match handle.as_mut() {
+        Some(_) if value > ST7567_CONTRAST_MASK => ST7567_EINVAL,
+        Some(St7567Handle(display)) => status(display.set_contrast(value)),
+        None => ST7567_EINVAL,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_null_handle() {
+        unsafe {
+            let frame = [0u8; crate::BUFFER_SIZE];
+            assert_eq!(st7567_init(std::ptr::null_mut()), ST7567_EINVAL);
+            assert_eq!(
+                st7567_set_pixel(std::ptr::null_mut(), 0, 0, true),
+                ST7567_EINVAL
+            );
+            assert_eq!(
+                st7567_blit(std::ptr::null_mut(), frame.as_ptr(), frame.len()),
+                ST7567_EINVAL
+            );
+            assert_eq!(st7567_show(std::ptr::null_mut()), ST7567_EINVAL);
+            assert_eq!(st7567_set_contrast(std::ptr::null_mut(), 10), ST7567_EINVAL);
+            st7567_close(std::ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn test_open_failure() {
+        let missing = b"/nonexistent/spidev\0";
+        let chip = b"/nonexistent/gpiochip\0";
+        unsafe {
+            let handle = st7567_open(
+                missing.as_ptr() as *const c_char,
+                chip.as_ptr() as *const c_char,
+                6,
+                5,
+            );
+            assert!(handle.is_null());
+            assert!(st7567_open(std::ptr::null(), std::ptr::null(), 6, 5).is_null());
+        }
+    }
+}
@@ -0,0 +1,258 @@
+//! Q8.8 fixed-point helpers for smooth, float-free animation
+//!
+//! Moving a sprite by less than one pixel per frame requires keeping track of
+//! the fractional part of its position. [`Fixed`] stores a signed Q8.8 number
+//! (8 integer bits, 8 fractional bits) which is enough for velocities and
+//! offsets, while [`SubPixel`] accumulates a Q8.8 velocity into a wider
+//! position so it can travel across (and beyond) the whole screen.
+//!
+//! Everything here is integer-only, so it is cheap on cores without an FPU
+//! such as the Cortex-M0.
+use core::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+/// Number of fractional bits of a [`Fixed`] value.
+pub const FRAC_BITS: u32 = 8;
+const ONE_RAW: i16 = 1 << FRAC_BITS;
+
+/// A signed Q8.8 fixed-point number.
+///
+/// The representable range is `-128.0..=127.996`, with a resolution of 1/256.
+/// Arithmetic saturates instead of overflowing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Fixed(i16);
+
+impl Fixed {
+    pub const ZERO: Fixed = Fixed(0);
+    pub const ONE: Fixed = Fixed(ONE_RAW);
+    pub const HALF: Fixed = Fixed(ONE_RAW / 2);
+    pub const MIN: Fixed = Fixed(i16::MIN);
+    pub const MAX: Fixed = Fixed(i16::MAX);
+
+    /// Build a value from its raw Q8.8 representation.
+    pub const fn from_raw(raw: i16) -> Self {
+        Fixed(raw)
+    }
+
+    /// Raw Q8.8 representation.
+    pub const fn raw(self) -> i16 {
+        self.0
+    }
+
+    /// Build a value from an integer.
+    pub const fn from_int(value: i8) -> Self {
+        Fixed((value as i16) << FRAC_BITS)
+    }
+
+    /// Build `numerator / denominator`, saturating on overflow.
+    ///
+    /// A zero denominator yields [`Fixed::MAX`] or [`Fixed::MIN`] depending on
+    /// the sign of the numerator (and [`Fixed::ZERO`] for `0 / 0`).
+    pub fn from_ratio(numerator: i32, denominator: i32) -> Self {
+        if denominator == 0 {
+            return match numerator {
+                n if n > 0 => Fixed::MAX,
+                n if n < 0 => Fixed::MIN,
+                _ => Fixed::ZERO,
+            };
+        }
+        let raw = (i64::from(numerator) << FRAC_BITS)
+            .checked_div(i64::from(denominator))
+            .unwrap_or(0);
+        Fixed(saturate(raw))
+    }
+
+    /// Largest integer less than or equal to this value.
+    pub const fn floor(self) -> i16 {
+        self.0 >> FRAC_BITS
+    }
+
+    /// Nearest integer, rounding halves up.
+    pub const fn round(self) -> i16 {
+        ((self.0 as i32).saturating_add(ONE_RAW as i32 / 2) >> FRAC_BITS) as i16
+    }
+
+    /// Fractional part, as a number of 1/256th.
+    pub const fn frac(self) -> u8 {
+        (self.0 & (ONE_RAW - 1)) as u8
+    }
+
+    pub fn saturating_add(self, rhs: Fixed) -> Fixed {
+        Fixed(self.0.saturating_add(rhs.0))
+    }
+
+    pub fn saturating_sub(self, rhs: Fixed) -> Fixed {
+        Fixed(self.0.saturating_sub(rhs.0))
+    }
+
+    pub fn saturating_mul(self, rhs: Fixed) -> Fixed {
+        Fixed(saturate(
+            i64::from(self.0).saturating_mul(i64::from(rhs.0)) >> FRAC_BITS,
+        ))
+    }
+}
+
+fn saturate(raw: i64) -> i16 {
+    if raw > i64::from(i16::MAX) {
+        i16::MAX
+    } else if raw < i64::from(i16::MIN) {
+        i16::MIN
+    } else {
+        raw as i16
+    }
+}
+
+impl From<i8> for Fixed {
+    fn from(value: i8) -> Self {
+        Fixed::from_int(value)
+    }
+}
+
+impl Add for Fixed {
+    type Output = Fixed;
+
+    fn add(self, rhs: Fixed) -> Fixed {
+        self.saturating_add(rhs)
+    }
+}
+
+impl AddAssign for Fixed {
+    fn add_assign(&mut self, rhs: Fixed) {
+        *self = self.saturating_add(rhs);
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Fixed;
+
+    fn sub(self, rhs: Fixed) -> Fixed {
+        self.saturating_sub(rhs)
+    }
+}
+
+impl SubAssign for Fixed {
+    fn sub_assign(&mut self, rhs: Fixed) {
+        *self = self.saturating_sub(rhs);
+    }
+}
+
+impl Mul for Fixed {
+    type Output = Fixed;
+
+    fn mul(self, rhs: Fixed) -> Fixed {
+        self.saturating_mul(rhs)
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Fixed;
+
+    fn neg(self) -> Fixed {
+        Fixed(self.0.saturating_neg())
+    }
+}
+
+/// A position moving at a constant Q8.8 velocity, with sub-pixel precision.
+///
+/// The position is kept in 1/256th of pixel units in an `i32`, so it is not
+/// limited to the Q8.8 range and can follow a marquee scrolling far off-screen.
+///
+/// ```
+/// use st7567::fixed::{Fixed, SubPixel};
+///
+/// // Move by a quarter of a pixel per frame
+/// let mut x = SubPixel::new(0, Fixed::from_ratio(1, 4));
+/// assert_eq!(x.step(), 0);
+/// assert_eq!(x.step(), 0);
+/// assert_eq!(x.step(), 0);
+/// assert_eq!(x.step(), 1);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SubPixel {
+    raw: i32,
+    velocity: Fixed,
+}
+
+impl SubPixel {
+    pub fn new(position: i32, velocity: Fixed) -> Self {
+        Self {
+            raw: position.saturating_mul(i32::from(ONE_RAW)),
+            velocity,
+        }
+    }
+
+    /// Current position, in whole pixels (rounded towards negative infinity).
+    pub fn position(&self) -> i32 {
+        self.raw >> FRAC_BITS
+    }
+
+    /// Jump to a whole-pixel position, discarding the accumulated fraction.
+    pub fn set_position(&mut self, position: i32) {
+        self.raw = position.saturating_mul(i32::from(ONE_RAW));
+    }
+
+    pub fn velocity(&self) -> Fixed {
+        self.velocity
+    }
+
+    pub fn set_velocity(&mut self, velocity: Fixed) {
+        self.velocity = velocity;
+    }
+
+    /// Advance by one velocity step and return the new whole-pixel position.
+    pub fn step(&mut self) -> i32 {
+        self.raw = self.raw.saturating_add(i32::from(self.velocity.raw()));
+        self.position()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(Fixed::from_int(3).raw(), 3 * 256);
+        assert_eq!(Fixed::from_int(-2).floor(), -2);
+        assert_eq!(Fixed::from_ratio(3, 2).floor(), 1);
+        assert_eq!(Fixed::from_ratio(3, 2).round(), 2);
+        assert_eq!(Fixed::from_ratio(3, 2).frac(), 128);
+        assert_eq!(Fixed::from_ratio(-1, 2).floor(), -1);
+        assert_eq!(Fixed::from_ratio(1, 0), Fixed::MAX);
+        assert_eq!(Fixed::from_ratio(-1, 0), Fixed::MIN);
+        assert_eq!(Fixed::from_ratio(1000, 1), Fixed::MAX);
+    }
+
+    #[test]
+    fn test_arithmetic_saturates() {
+        let a = Fixed::from_ratio(5, 2);
+        let b = Fixed::from_int(2);
+        assert_eq!(a + b, Fixed::from_ratio(9, 2));
+        assert_eq!(a - b, Fixed::HALF);
+        assert_eq!(a * b, Fixed::from_int(5));
+        assert_eq!(-a, Fixed::from_ratio(-5, 2));
+        assert_eq!(Fixed::MAX + Fixed::ONE, Fixed::MAX);
+        assert_eq!(Fixed::MIN - Fixed::ONE, Fixed::MIN);
+        assert_eq!(Fixed::from_int(100) * Fixed::from_int(100), Fixed::MAX);
+        assert_eq!(-Fixed::MIN, Fixed::MAX);
+    }
+
+    #[test]
+    fn test_sub_pixel_accumulation() {
+        let mut pos = SubPixel::new(10, Fixed::from_ratio(-1, 3));
+        let positions: [i32; 6] = [
+            pos.step(),
+            pos.step(),
+            pos.step(),
+            pos.step(),
+            pos.step(),
+            pos.step(),
+        ];
+        assert_eq!(positions, [9, 9, 9, 8, 8, 8]);
+
+        // Far beyond the Q8.8 range
+        let mut pos = SubPixel::new(1000, Fixed::from_int(100));
+        assert_eq!(pos.step(), 1100);
+        pos.set_position(-5);
+        assert_eq!(pos.position(), -5);
+    }
+}
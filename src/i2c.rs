@@ -0,0 +1,182 @@
+//! I2C transport, for modules with an I2C interface or bridge
+//!
+//! Over I2C, every write starts with a control byte telling commands from
+//! display data. [`I2cBus`] adds it in front of the bytes of each transfer,
+//! and a [`DcFlag`] takes the place of the DC pin to select it. The delay
+//! waits out the `DelayNs` operations of transactions:
+//!
+//! ```ignore
+//! let dc = Cell::new(false);
+//! let (bus, dc_flag) = I2cBus::new(i2c, 0x3f, delay, &dc);
+//! let mut display = ST7567::new_without_reset(bus, dc_flag);
+//! ```
+use crate::dc_flag::DcFlag;
+use core::cell::Cell;
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::{self, I2c};
+use embedded_hal::spi::{self, ErrorKind, ErrorType, Operation, SpiDevice};
+
+/// Control byte of a command transfer: last control byte, A0 low.
+const CONTROL_COMMAND: u8 = 0x00;
+
+/// Control byte of a data transfer: last control byte, A0 high.
+const CONTROL_DATA: u8 = 0x40;
+
+/// Error of an [`I2cBus`] transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum I2cError<E> {
+    /// Error of the I2C bus, including a missing acknowledge
+    I2c(E),
+    /// The controller cannot be read from through this transport
+    ReadUnsupported,
+}
+
+impl<E: i2c::Error> spi::Error for I2cError<E> {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            I2cError::I2c(e) => match e.kind() {
+                i2c::ErrorKind::Overrun => ErrorKind::Overrun,
+                _ => ErrorKind::Other,
+            },
+            I2cError::ReadUnsupported => ErrorKind::Other,
+        }
+    }
+}
+
+/// Writes commands and data to an I2C controller, see the [module](self)
+/// docs.
+pub struct I2cBus<'a, I, D> {
+    i2c: I,
+    address: u8,
+    delay: D,
+    dc: &'a Cell<bool>,
+}
+
+impl<'a, I: I2c, D: DelayNs> I2cBus<'a, I, D> {
+    /// Talk to the controller at 7-bit `address`, returning the DC pin to
+    /// give the driver along with the bus. `dc` holds the D/C state between
+    /// the two.
+    pub fn new(i2c: I, address: u8, delay: D, dc: &'a Cell<bool>) -> (Self, DcFlag<'a>) {
+        let bus = I2cBus {
+            i2c,
+            address,
+            delay,
+            dc,
+        };
+        (bus, DcFlag(dc))
+    }
+
+    /// Give the I2C bus and the delay back.
+    pub fn release(self) -> (I, D) {
+        (self.i2c, self.delay)
+    }
+}
+
+impl<I: I2c, D> ErrorType for I2cBus<'_, I, D> {
+    type Error = I2cError<I::Error>;
+}
+
+impl<I: I2c, D: DelayNs> SpiDevice for I2cBus<'_, I, D> {
+    /// Each write is an I2C transfer of its own.
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        let control = if self.dc.get() {
+            CONTROL_DATA
+        } else {
+            CONTROL_COMMAND
+        };
+        for operation in operations {
+            match operation {
+                Operation::Write(bytes) => self
+                    .i2c
+                    .transaction(
+                        self.address,
+                        &mut [
+                            i2c::Operation::Write(&[control]),
+                            i2c::Operation::Write(bytes),
+                        ],
+                    )
+                    .map_err(I2cError::I2c)?,
+                Operation::DelayNs(ns) => self.delay.delay_ns(*ns),
+                _ => return Err(I2cError::ReadUnsupported),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{MockDelay, MockError};
+    use crate::ST7567;
+
+    impl i2c::Error for MockError {
+        fn kind(&self) -> i2c::ErrorKind {
+            i2c::ErrorKind::Other
+        }
+    }
+
+    /// Records every transfer, with its address.
+    #[derive(Default)]
+    struct MockI2c {
+        transfers: Vec<(u8, Vec<u8>)>,
+    }
+
+    impl i2c::ErrorType for MockI2c {
+        type Error = MockError;
+    }
+
+    impl I2c for MockI2c {
+        fn transaction(
+            &mut self,
+            address: u8,
+            operations: &mut [i2c::Operation<'_>],
+        ) -> Result<(), MockError> {
+            let mut bytes = Vec::new();
+            for operation in operations {
+                match operation {
+                    i2c::Operation::Write(data) => bytes.extend_from_slice(data),
+                    i2c::Operation::Read(_) => return Err(MockError::SpiError),
+                }
+            }
+            self.transfers.push((address, bytes));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_control_bytes() {
+        let dc = Cell::new(false);
+        let (bus, dc_flag) = I2cBus::new(MockI2c::default(), 0x3f, MockDelay::new(), &dc);
+        let mut st7567 = ST7567::new_without_reset(bus, dc_flag);
+        assert!(st7567.init().is_ok());
+        let transfers = &st7567.spi.i2c.transfers;
+        assert!(transfers.iter().all(|(address, _)| *address == 0x3f));
+        let (_, init) = &transfers[0];
+        assert_eq!(init[0], CONTROL_COMMAND);
+        assert_eq!(init[1..], crate::sequence::GFX_HAT[..init.len() - 1]);
+
+        st7567.spi.i2c.transfers.clear();
+        assert!(st7567.show().is_ok());
+        let data: Vec<&Vec<u8>> = st7567
+            .spi
+            .i2c
+            .transfers
+            .iter()
+            .map(|(_, bytes)| bytes)
+            .filter(|bytes| bytes[0] == CONTROL_DATA)
+            .collect();
+        assert_eq!(data.len(), 8);
+        assert!(data.iter().all(|bytes| bytes.len() == 1 + 128));
+
+        let mut read = [0];
+        assert_eq!(
+            st7567.spi.transaction(&mut [Operation::Read(&mut read)]),
+            Err(I2cError::ReadUnsupported)
+        );
+
+        let mut operations = [Operation::DelayNs(300)];
+        assert!(st7567.spi.transaction(&mut operations).is_ok());
+        assert_eq!(st7567.spi.delay.delays_ns, [300]);
+    }
+}
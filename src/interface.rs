@@ -0,0 +1,116 @@
+//! Transport through the `display-interface` traits
+//!
+//! [`Interface`] lets the driver talk through any [`WriteOnlyDataCommand`]
+//! implementation: parallel GPIO buses, I2C bridges, or interface adapters
+//! shared with other display drivers. The interface drives the DC line
+//! itself, so it is a [`Transport`] of its own, writing commands and display
+//! data with the matching calls, and the driver gets a [`NoDcPin`]:
+//!
+//! ```ignore
+//! let interface = PGPIO8BitInterface::new(bus, dc_gpio, wr_gpio);
+//! let mut display = ST7567::with_interface(interface, rst);
+//! ```
+use crate::transport::Transport;
+use crate::{Pin, PinState, ST7567};
+use display_interface::{DataFormat, DisplayError, WriteOnlyDataCommand};
+
+/// A display interface used as the transport of the driver, see the
+/// [module](self) docs.
+pub struct Interface<I> {
+    interface: I,
+}
+
+impl<I: WriteOnlyDataCommand> Interface<I> {
+    pub fn new(interface: I) -> Self {
+        Interface { interface }
+    }
+
+    /// Give the interface back.
+    pub fn release(self) -> I {
+        self.interface
+    }
+}
+
+impl<I: WriteOnlyDataCommand> Transport for Interface<I> {
+    type Error = DisplayError;
+
+    fn write_commands(&mut self, bytes: &[u8]) -> Result<(), DisplayError> {
+        self.interface.send_commands(DataFormat::U8(bytes))
+    }
+
+    fn write_data(&mut self, bytes: &[u8]) -> Result<(), DisplayError> {
+        self.interface.send_data(DataFormat::U8(bytes))
+    }
+}
+
+/// Placeholder DC pin type for an [`Interface`], which drives the DC line
+/// itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NoDcPin;
+
+impl Pin for NoDcPin {
+    type Error = core::convert::Infallible;
+
+    fn set_value(&mut self, _pin_state: PinState) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl<RST: Pin, I: WriteOnlyDataCommand> ST7567<NoDcPin, RST, Interface<I>> {
+    /// Create a driver writing through a display interface.
+    pub fn with_interface(interface: I, rst_pin: RST) -> Self {
+        ST7567::new(Interface::new(interface), NoDcPin, rst_pin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::MockPin;
+
+    /// Records commands and data separately.
+    #[derive(Default)]
+    struct MockInterface {
+        commands: Vec<u8>,
+        data: Vec<u8>,
+        fail: bool,
+    }
+
+    impl WriteOnlyDataCommand for MockInterface {
+        fn send_commands(&mut self, cmd: DataFormat<'_>) -> Result<(), DisplayError> {
+            match cmd {
+                DataFormat::U8(bytes) if !self.fail => self.commands.extend_from_slice(bytes),
+                _ => return Err(DisplayError::BusWriteError),
+            }
+            Ok(())
+        }
+
+        fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+            match buf {
+                DataFormat::U8(bytes) if !self.fail => self.data.extend_from_slice(bytes),
+                _ => return Err(DisplayError::BusWriteError),
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_interface() {
+        let mut st7567 = ST7567::with_interface(MockInterface::default(), MockPin::new());
+        assert!(st7567.init().is_ok());
+        assert_eq!(st7567.spi.interface.commands, crate::sequence::GFX_HAT);
+        assert!(st7567.spi.interface.data.is_empty());
+
+        st7567.set_pixel(0, 0, true);
+        assert!(st7567.show().is_ok());
+        let interface = &st7567.spi.interface;
+        assert_eq!(interface.data.len(), crate::BUFFER_SIZE);
+        assert_eq!(interface.data.iter().filter(|&&byte| byte != 0).count(), 1);
+
+        st7567.spi.interface.fail = true;
+        assert!(matches!(
+            st7567.show(),
+            Err(crate::Error::SpiError(DisplayError::BusWriteError))
+        ));
+    }
+}
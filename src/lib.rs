@@ -3,16 +3,114 @@
 //! This LCD display is found on the Pimoroni GFX HAT for the
 //! Raspberry PI
 //!
-use embedded_hal::spi::SpiDevice;
+//! The driver never panics on user input: out of range coordinates are
+//! ignored, no code path indexes into a slice without checking bounds and
+//! arithmetic saturates or is checked instead of overflowing.
+//!
+//! The crate is `no_std`. The default `std` feature adds std-only
+//! conveniences, such as `std::error::Error` for [`Error`]. Timing is
+//! provided by an [`embedded_hal::delay::DelayNs`] implementation passed to
+//! the methods that need to wait.
+//!
+//! The subsystems built over the driver each have a default feature:
+//! `region`, `bitmap`, `strip-chart`, `render`, `factory`, `events`,
+//! `auto-show`, and the `three-wire`, `parallel` and `bitbang` transports.
+//! With `default-features = false`, only the driver and its small helpers
+//! are compiled.
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
+// The C and Python bindings are the only places allowed to use `unsafe`
+#![cfg_attr(not(any(feature = "ffi", feature = "python")), forbid(unsafe_code))]
+#![cfg_attr(any(feature = "ffi", feature = "python"), deny(unsafe_code))]
+#![cfg_attr(
+    not(test),
+    deny(
+        clippy::indexing_slicing,
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::arithmetic_side_effects
+    )
+)]
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::OutputPin;
+#[cfg(feature = "build-assets")]
+pub mod assets;
+#[cfg(feature = "auto-show")]
+pub mod auto_show;
+#[cfg(feature = "bitbang")]
+pub mod bitbang;
+#[cfg(feature = "bitmap")]
+pub mod bitmap;
+pub mod buffer;
+pub mod builder;
+pub mod calibration;
+pub mod command;
+pub mod compat;
+#[allow(dead_code)]
 mod consts;
+pub mod dc_flag;
+pub mod diff;
+pub mod easing;
+#[cfg(all(feature = "embassy-rp", target_os = "none"))]
+pub mod embassy;
+#[cfg(all(feature = "esp-hal", target_os = "none"))]
+pub mod esp;
+#[cfg(feature = "events")]
+pub mod events;
+#[cfg(feature = "factory")]
+pub mod factory;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod fixed;
+#[cfg(feature = "i2c")]
+pub mod i2c;
+#[cfg(feature = "display-interface")]
+pub mod interface;
+#[cfg(feature = "linux")]
+pub mod linux;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+pub mod power;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod raw;
+#[cfg(feature = "region")]
+pub mod region;
+#[cfg(feature = "render")]
+pub mod render;
+pub mod retry;
+#[cfg(feature = "rp2040")]
+pub mod rp2040;
+pub mod sequence;
+#[cfg(feature = "strip-chart")]
+pub mod strip_chart;
+#[cfg(feature = "three-wire")]
+pub mod three_wire;
+#[cfg(feature = "timing")]
+pub mod timing;
+pub mod trace;
+pub mod transport;
+pub mod variant;
 
 use crate::consts::*;
 
+use crate::buffer::Geometry;
+pub use crate::buffer::{
+    BoundingBox, BufferStats, NoBuffer, PixelBuffer, SizedFramebuffer, StaticFramebuffer,
+};
+pub use crate::builder::Builder;
+pub use crate::calibration::PanelCalibration;
+pub use crate::command::Command;
 pub use crate::consts::{HEIGHT, SPI_SPEED_HZ, WIDTH};
+#[cfg(feature = "render")]
+pub use crate::render::LineRenderer;
+pub use crate::sequence::InitSequence;
+pub use crate::transport::Transport;
+pub use crate::variant::ControllerVariant;
 use crate::PinState::{High, Low};
-use std::fmt;
-use std::fmt::{Debug, Formatter};
-use std::time::Duration;
+use core::fmt;
+use core::fmt::{Debug, Formatter};
+use core::ops::Range;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum PinState {
@@ -20,56 +118,229 @@ pub enum PinState {
     Low,
 }
 
+/// Order in which `show()` sends pages to the controller.
+///
+/// The order is only visible as the direction of the update sweep. When the
+/// panel is mounted upside down, `BottomToTop` keeps the sweep aligned with
+/// the reading order, which reduces perceived tearing of scrolling text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PageOrder {
+    #[default]
+    TopToBottom,
+    BottomToTop,
+}
+
+/// What [`ST7567::show_with_deadline`] managed to send.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeadlineFlush {
+    /// Pages sent (or skipped as blank) by this call
+    pub pages: usize,
+    /// Every remaining page of the frame was sent
+    pub complete: bool,
+}
+
+/// Orientation of the drawing coordinates, see [`ST7567::set_rotation`].
+///
+/// Rotations are clockwise. With `Rotate90` and `Rotate270`, the drawing
+/// area is 64 pixels wide and 128 pixels tall.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rotation {
+    #[default]
+    Rotate0,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+
+impl Rotation {
+    /// Width and height of the drawing area of a 128x64 panel, in pixels.
+    pub fn dimensions(self) -> (usize, usize) {
+        self.dimensions_of(Geometry::DEFAULT)
+    }
+
+    pub(crate) fn dimensions_of(self, geometry: Geometry) -> (usize, usize) {
+        match self {
+            Rotation::Rotate0 | Rotation::Rotate180 => (geometry.width, geometry.height),
+            Rotation::Rotate90 | Rotation::Rotate270 => (geometry.height, geometry.width),
+        }
+    }
+
+    /// Framebuffer coordinates of a pixel of the drawing area, `None` when
+    /// out of it.
+    ///
+    /// 180° is done by the controller scan directions, so it maps like 0°.
+    pub(crate) fn to_buffer(
+        self,
+        geometry: Geometry,
+        x: usize,
+        y: usize,
+    ) -> Option<(usize, usize)> {
+        let (width, height) = self.dimensions_of(geometry);
+        if x >= width || y >= height {
+            return None;
+        }
+        match self {
+            Rotation::Rotate0 | Rotation::Rotate180 => Some((x, y)),
+            Rotation::Rotate90 => Some((geometry.width.checked_sub(y)?.checked_sub(1)?, x)),
+            Rotation::Rotate270 => Some((y, geometry.height.checked_sub(x)?.checked_sub(1)?)),
+        }
+    }
+}
+
+/// LCD bias ratio, see [`ST7567::set_bias`].
+///
+/// The right value depends on the panel glass: a wrong bias shows as washed
+/// out or smeared pixels whatever the contrast setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Bias {
+    /// 1/9 bias
+    OneNinth,
+    /// 1/7 bias, used by the Pimoroni GFX HAT
+    #[default]
+    OneSeventh,
+}
+
+/// What happens to out of range contrast, start line and page values, see
+/// [`ST7567::set_range_policy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RangePolicy {
+    /// Drop the high bits: start line 67 is line 3, contrast 70 is 6.
+    #[default]
+    Wrap,
+    /// Use the closest valid value: start line 67 is line 63.
+    Saturate,
+    /// Return [`Error::InvalidArgument`] and send nothing.
+    Reject,
+}
+
+impl RangePolicy {
+    /// `value` made to fit in `mask`, `None` when rejected.
+    fn apply(self, value: u8, mask: u8) -> Option<u8> {
+        match self {
+            RangePolicy::Wrap => Some(value & mask),
+            RangePolicy::Saturate => Some(value.min(mask)),
+            RangePolicy::Reject if value > mask => None,
+            RangePolicy::Reject => Some(value),
+        }
+    }
+}
+
+/// Bit order of the SPI transport, see [`ST7567::set_bit_order`].
+///
+/// The ST7567 expects 8-bit words, most significant bit first. Word size is
+/// enforced by the `SpiDevice<u8>` bound, but embedded-hal has no way to
+/// query the bit order a bus was configured with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BitOrder {
+    #[default]
+    MsbFirst,
+    /// The bus shifts the least significant bit out first. The driver then
+    /// mirrors every byte before sending it, so the controller still
+    /// receives MSB-first words.
+    LsbFirst,
+}
+
 /// A control pin, typically used to model DC & RST pin of the ST7567 display
+///
+/// It is implemented for every [`OutputPin`], so HAL pins can be passed to
+/// [`ST7567::new`] directly. Implement it by hand for pins that are not
+/// exposed through embedded-hal.
 pub trait Pin {
     type Error;
 
     fn set_value(&mut self, pin_state: PinState) -> Result<(), Self::Error>;
 }
 
-pub enum Error<P, S>
+/// Placeholder RST pin type for boards where the reset line is not wired to a
+/// GPIO, see [`ST7567::new_without_reset`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NoResetPin;
+
+impl Pin for NoResetPin {
+    type Error = core::convert::Infallible;
+
+    fn set_value(&mut self, _pin_state: PinState) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Any embedded-hal output pin can be used directly as a DC or RST pin.
+impl<T: OutputPin> Pin for T {
+    type Error = T::Error;
+
+    fn set_value(&mut self, pin_state: PinState) -> Result<(), Self::Error> {
+        match pin_state {
+            High => self.set_high(),
+            Low => self.set_low(),
+        }
+    }
+}
+
+pub enum Error<DC, RST, S>
 where
-    P: Pin,
-    S: SpiDevice,
+    DC: Pin,
+    RST: Pin,
+    S: Transport,
 {
+    /// Error of the transport, an SPI bus or another interface
     SpiError(S::Error),
-    PinError(P::Error),
+    /// Error driving the DC pin
+    PinError(DC::Error),
+    /// Error driving the RST pin
+    ResetPinError(RST::Error),
+    /// A setting was given an out of range value, nothing was sent
+    InvalidArgument,
+    /// `show()` was called before `init()`, or after a reset or power off
+    NotInitialized,
 }
 
-impl<P, S> Debug for Error<P, S>
+impl<DC, RST, S> Debug for Error<DC, RST, S>
 where
-    P: Pin,
-    S: SpiDevice,
+    DC: Pin,
+    RST: Pin,
+    S: Transport,
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
         match &self {
             Error::SpiError(_) => write!(f, "SpiError"),
             Error::PinError(_) => write!(f, "PinError"),
+            Error::ResetPinError(_) => write!(f, "ResetPinError"),
+            Error::InvalidArgument => write!(f, "InvalidArgument"),
+            Error::NotInitialized => write!(f, "NotInitialized"),
         }
     }
 }
 
-impl<P, S> std::error::Error for Error<P, S>
+#[cfg(feature = "std")]
+impl<DC, RST, S> std::error::Error for Error<DC, RST, S>
 where
-    P: Pin,
-    S: SpiDevice,
+    DC: Pin,
+    RST: Pin,
+    S: Transport,
 {
 }
 
-impl<P, S> fmt::Display for Error<P, S>
+impl<DC, RST, S> fmt::Display for Error<DC, RST, S>
 where
-    P: Pin,
-    S: SpiDevice,
+    DC: Pin,
+    RST: Pin,
+    S: Transport,
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
         match &self {
             Error::SpiError(_) => write!(f, "SpiError"),
             Error::PinError(_) => write!(f, "PinError"),
+            Error::ResetPinError(_) => write!(f, "ResetPinError"),
+            Error::InvalidArgument => write!(f, "InvalidArgument"),
+            Error::NotInitialized => write!(f, "NotInitialized"),
         }
     }
 }
 /// Utility function to deal with Error mess
-fn set_pin<P: Pin, S: SpiDevice>(pin: &mut P, pin_state: PinState) -> Result<(), Error<P, S>> {
+fn set_dc_pin<DC: Pin, RST: Pin, S: Transport>(
+    pin: &mut DC,
+    pin_state: PinState,
+) -> Result<(), Error<DC, RST, S>> {
     match pin.set_value(pin_state) {
         Ok(_) => Ok(()),
         Err(e) => Err(Error::PinError(e)),
@@ -77,262 +348,1805 @@ fn set_pin<P: Pin, S: SpiDevice>(pin: &mut P, pin_state: PinState) -> Result<(),
 }
 
 /// Utility function to deal with Error mess
-fn spi_write<P: Pin, S: SpiDevice>(spi: &mut S, data: &[u8]) -> Result<(), Error<P, S>> {
-    match spi.write(data) {
+fn set_rst_pin<DC: Pin, RST: Pin, S: Transport>(
+    pin: &mut RST,
+    pin_state: PinState,
+) -> Result<(), Error<DC, RST, S>> {
+    match pin.set_value(pin_state) {
         Ok(_) => Ok(()),
-        Err(e) => Err(Error::SpiError(e)),
+        Err(e) => Err(Error::ResetPinError(e)),
     }
 }
 
-const BUFFER_SIZE: usize = 1024;
-
-/// Controls the ST7567 LCD Display.
-///
-pub struct ST7567<P: Pin, S: SpiDevice> {
-    dc_pin: P,
-    rst_pin: P,
-    spi: S,
-    buf: [u8; BUFFER_SIZE],
+/// Utility function to deal with Error mess, `pin_state` being the level of
+/// the DC pin: low for commands, high for display data.
+fn spi_write<DC: Pin, RST: Pin, S: Transport>(
+    spi: &mut S,
+    pin_state: PinState,
+    data: &[u8],
+    bit_order: BitOrder,
+) -> Result<(), Error<DC, RST, S>> {
+    if bit_order == BitOrder::LsbFirst {
+        return spi_write_mirrored(spi, pin_state, data);
+    }
+    let result = match pin_state {
+        Low => spi.write_commands(data),
+        High => spi.write_data(data),
+    };
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(Error::SpiError(e)),
+    }
 }
 
-impl<P: Pin, S: SpiDevice> ST7567<P, S> {
-    pub fn new(spi: S, dc_pin: P, rst_pin: P) -> Self {
-        Self {
-            spi,
-            dc_pin,
-            rst_pin,
-            buf: [0; BUFFER_SIZE],
+/// Send `data` with the bits of every byte reversed, for LSB-first buses.
+///
+/// Goes through a small stack chunk so the framebuffer is never modified.
+fn spi_write_mirrored<DC: Pin, RST: Pin, S: Transport>(
+    spi: &mut S,
+    pin_state: PinState,
+    data: &[u8],
+) -> Result<(), Error<DC, RST, S>> {
+    let mut chunk = [0u8; 32];
+    for bytes in data.chunks(chunk.len()) {
+        for (mirrored, byte) in chunk.iter_mut().zip(bytes) {
+            *mirrored = byte.reverse_bits();
         }
+        let mirrored = chunk.get(..bytes.len()).unwrap_or_default();
+        spi_write(spi, pin_state.clone(), mirrored, BitOrder::MsbFirst)?;
     }
+    Ok(())
+}
 
-    fn command(&mut self, data: &[u8]) -> Result<(), Error<P, S>> {
-        set_pin(&mut self.dc_pin, Low)?;
-        spi_write(&mut self.spi, data)
-    }
+/// SPI mode expected by the controller, which samples data on the rising
+/// edge of the clock. Configure the bus with it and [`SPI_SPEED_HZ`].
+pub const SPI_MODE: embedded_hal::spi::Mode = embedded_hal::spi::MODE_0;
+
+/// Fastest SPI clock of the controller, from its 50ns minimum clock
+/// period.
+pub const SPI_MAX_SPEED_HZ: u32 = 20_000_000;
+
+/// SPI bus settings, checked by the quickstart helpers before they are
+/// applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpiConfig {
+    pub mode: embedded_hal::spi::Mode,
+    pub speed_hz: u32,
+}
 
-    fn data(&mut self, data: &[u8]) -> Result<(), Error<P, S>> {
-        set_pin(&mut self.dc_pin, High)?;
-        spi_write(&mut self.spi, data)
+impl Default for SpiConfig {
+    /// [`SPI_MODE`] at [`SPI_SPEED_HZ`].
+    fn default() -> Self {
+        SpiConfig {
+            mode: SPI_MODE,
+            speed_hz: SPI_SPEED_HZ,
+        }
     }
+}
 
-    pub fn reset(&mut self) -> Result<(), Error<P, S>> {
-        set_pin(&mut self.rst_pin, Low)?;
-        std::thread::sleep(Duration::from_millis(10));
-        set_pin(&mut self.rst_pin, High)?;
-        std::thread::sleep(Duration::from_millis(100));
+impl SpiConfig {
+    /// Check that the settings suit the controller.
+    ///
+    /// Besides [`SPI_MODE`], mode 3 samples on the rising edge as well and
+    /// is accepted.
+    pub fn check(&self) -> Result<(), SpiConfigError> {
+        if self.mode != SPI_MODE && self.mode != embedded_hal::spi::MODE_3 {
+            return Err(SpiConfigError::Mode(self.mode));
+        }
+        if self.speed_hz == 0 || self.speed_hz > SPI_MAX_SPEED_HZ {
+            return Err(SpiConfigError::Speed(self.speed_hz));
+        }
         Ok(())
     }
+}
 
-    pub fn set_contrast(&mut self, value: u8) -> Result<(), Error<P, S>> {
-        self.command(&[ST7567_SETCONTRAST, value])
-    }
+/// Why [`SpiConfig::check`] rejected bus settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpiConfigError {
+    /// The mode does not sample on the rising edge of the clock
+    Mode(embedded_hal::spi::Mode),
+    /// The clock is stopped or faster than [`SPI_MAX_SPEED_HZ`]
+    Speed(u32),
+}
 
-    pub fn init(&mut self) -> Result<(), Error<P, S>> {
-        self.command(&[
-            ST7567_BIAS_1_7, // Bais 1/7 (0xA2 = Bias 1/9)
-            ST7567_SEG_DIR_NORMAL,
-            ST7567_SETCOMREVERSE,    // Reverse COM - vertical flip
-            ST7567_DISPNORMAL,       // Inverse display (0xA6 normal)
-            ST7567_SETSTARTLINE | 0, // Start at line 0
-            ST7567_POWERCTRL,
-            ST7567_REG_RATIO | 3,
-            ST7567_DISPON,
-            ST7567_SETCONTRAST, // Set contrast
-            40,                 // Contrast value])
-        ])
+/// Error of the quickstart helpers for HALs.
+#[cfg(any(feature = "rp2040", feature = "esp-hal"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetupError<E> {
+    /// The requested bus configuration does not suit the controller
+    Config(SpiConfigError),
+    /// The HAL refused the configuration or a pin
+    Hal(E),
+}
+
+#[cfg(any(feature = "rp2040", feature = "esp-hal"))]
+impl<E> From<SpiConfigError> for SetupError<E> {
+    fn from(e: SpiConfigError) -> Self {
+        SetupError::Config(e)
     }
+}
 
-    /// Clear the display buffer
-    pub fn clear(&mut self) {
-        self.buf = [0; BUFFER_SIZE];
+/// Regulation ratio and contrast for 0%, 10%, ... 100% brightness, see
+/// [`ST7567::set_brightness`].
+///
+/// Derived from the datasheet model of the LCD driving voltage,
+/// `V0 = (1 + Rb/Ra) * (1 - (63 - EV) / 162) * 2.1V`: the rows step V0
+/// evenly from about 5.2V to 12.6V, using the lowest ratio able to reach
+/// each voltage so the contrast keeps some room around it.
+pub const BRIGHTNESS_TABLE: [(u8, u8); 11] = [
+    (0, 36),
+    (0, 55),
+    (1, 49),
+    (2, 45),
+    (2, 59),
+    (3, 54),
+    (4, 50),
+    (4, 61),
+    (5, 57),
+    (6, 54),
+    (6, 63),
+];
+
+/// Time between two contrast steps of `fade_contrast()`.
+const FADE_STEP_MS: u32 = 10;
+
+/// Booster, regulator and follower all enabled, see
+/// [`Command::SetPowerControl`].
+const POWER_ALL: u8 = 0b111;
+
+/// Size in bytes of the display buffer: one bit per pixel, one byte per
+/// column in each 8-pixel tall page.
+pub const BUFFER_SIZE: usize = buffer_size(WIDTH as usize, HEIGHT as usize);
+
+/// Size in bytes of the display buffer of a `width` x `height` panel, see
+/// [`ST7567::with_sized_buffer`].
+pub const fn buffer_size(width: usize, height: usize) -> usize {
+    width.saturating_mul(height) / 8
+}
+
+/// Checks that frames of `N` bytes fit a `W` x `H` panel, see
+/// [`ST7567::set_sized_buffer`].
+struct FrameSize<const N: usize, const W: usize, const H: usize>;
+
+impl<const N: usize, const W: usize, const H: usize> FrameSize<N, W, H> {
+    const MATCHES: () = assert!(
+        N == buffer_size(W, H),
+        "frame size must be buffer_size(W, H)"
+    );
+}
+
+/// Boolean driver state, one bit each to keep the driver small.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Flags(u8);
+
+impl Flags {
+    /// The controller may still be in Read-Modify-Write mode, i.e. a flush
+    /// was interrupted before `ST7567_EXIT_RMWMODE` got through
+    pub(crate) const RMW_PENDING: u8 = 1 << 0;
+    /// Set by `init()`, cleared by anything resetting the controller
+    pub(crate) const INITIALIZED: u8 = 1 << 1;
+    /// `init_commands` replace the built-in init sequence
+    pub(crate) const REPLACE_INIT: u8 = 1 << 2;
+    /// `show()` skips pages known to be blank in the controller RAM
+    pub(crate) const SKIP_BLANK_PAGES: u8 = 1 << 3;
+    /// The display is inverted in hardware
+    pub(crate) const INVERTED: u8 = 1 << 4;
+    /// Horizontal mirroring sent by `init()`
+    pub(crate) const SEGMENT_REMAP: u8 = 1 << 5;
+    /// Vertical mirroring sent by `init()`
+    pub(crate) const COM_REVERSE: u8 = 1 << 6;
+    /// The bus shifts bytes out LSB-first, see `BitOrder`
+    pub(crate) const LSB_FIRST: u8 = 1 << 7;
+
+    pub(crate) fn get(self, flag: u8) -> bool {
+        self.0 & flag != 0
     }
-    /// Set a single pixel in the  display buffer.
-    ///
-    /// Ignore out of bound values for x & y
-    pub fn set_pixel(&mut self, x: usize, y: usize, value: bool) {
-        if x >= WIDTH as usize || y >= HEIGHT as usize {
-            return;
-        }
-        let offset = ((y / 8) * WIDTH as usize) + x;
-        let bit = y as u8 % 8;
+
+    pub(crate) fn set(&mut self, flag: u8, value: bool) {
         if value {
-            // ON
-            self.buf[offset] = self.buf[offset] | 1 << bit;
+            self.0 |= flag;
         } else {
-            // OFF
-            self.buf[offset] = self.buf[offset] & !(1 << bit);
+            self.0 &= !flag;
         }
     }
 
-    /// Update the ST7567 display with the buffer contents.
-    pub fn show(&mut self) -> Result<(), Error<P, S>> {
-        self.command(&[ST7567_ENTER_RMWMODE])?;
-        for page in 0..8 {
-            let offset: usize = page * ST7567_PAGESIZE as usize;
-            self.command(&[
-                ST7567_SETPAGESTART | page as u8,
-                ST7567_SETCOLL,
-                ST7567_SETCOLH,
-            ])?;
-            let start_offset = offset as usize;
-            let end_offset = start_offset + ST7567_PAGESIZE as usize;
-            let mut data = [0u8; ST7567_PAGESIZE as usize];
-            data.clone_from_slice(&self.buf[start_offset..end_offset]);
-            self.data(&data)?;
+    pub(crate) fn bit_order(self) -> BitOrder {
+        if self.get(Flags::LSB_FIRST) {
+            BitOrder::LsbFirst
+        } else {
+            BitOrder::MsbFirst
         }
-        self.command(&[ST7567_EXIT_RMWMODE])
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::cell::RefCell;
+/// Two page numbers sharing a byte, to keep the driver small.
+#[derive(Debug, Clone, Copy, Default)]
+struct PagePositions(u8);
 
-    #[derive(Debug, Clone, PartialEq)]
-    pub enum MockError {
-        SpiError,
-        PinError,
+impl PagePositions {
+    /// Position, in page order, of the first page `show_with_deadline()`
+    /// did not send
+    fn resume(self) -> u8 {
+        self.0 & 0x0f
     }
 
-    impl std::error::Error for MockError {}
+    fn set_resume(&mut self, page: u8) {
+        self.0 = self.0 & 0xf0 | page & 0x0f;
+    }
 
-    impl fmt::Display for MockError {
-        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-            match self {
-                MockError::SpiError => write!(f, "Mock SPI Error"),
-                MockError::PinError => write!(f, "Mock Pin Error"),
-            }
-        }
+    /// First RAM page of the frame, moved by `scroll_to_screen()`
+    fn start(self) -> u8 {
+        self.0 >> 4
     }
 
-    impl embedded_hal::spi::Error for MockError {
-        fn kind(&self) -> embedded_hal::spi::ErrorKind {
-            embedded_hal::spi::ErrorKind::Other
-        }
+    fn set_start(&mut self, page: u8) {
+        self.0 = page << 4 | self.0 & 0x0f;
     }
+}
 
-    /// Mock Pin implementation for testing
-    #[derive(Debug)]
-    pub struct MockPin {
-        pub states: RefCell<Vec<PinState>>,
-        pub should_fail: RefCell<bool>,
+/// Controls the ST7567 LCD Display.
+///
+/// The DC and RST pins may be of different types. The framebuffer storage
+/// `B` defaults to an owned 1KB array, see [`PixelBuffer`] for alternatives.
+///
+/// `W` and `H` are the panel dimensions in pixels, 128x64 by default. The
+/// controller drives up to 132 columns and 64 rows, in pages of 8 rows, so
+/// 128x32 or 132x64 panels are supported too, see
+/// [`with_sized_buffer`](Self::with_sized_buffer).
+pub struct ST7567<
+    DC: Pin,
+    RST: Pin,
+    S: Transport,
+    B: PixelBuffer = [u8; BUFFER_SIZE],
+    const W: usize = 128,
+    const H: usize = 64,
+> {
+    dc_pin: DC,
+    /// `None` when the reset line is not under our control
+    rst_pin: Option<RST>,
+    spi: S,
+    buf: B,
+    flags: Flags,
+    page_order: PageOrder,
+    /// Bias sent by `init()`
+    bias: Bias,
+    /// Regulation resistor ratio sent by `init()`
+    regulation_ratio: u8,
+    /// Contrast sent by `init()`
+    contrast: u8,
+    /// Commands of the custom init sequence, empty for the built-in one
+    init_commands: &'static [u8],
+    rotation: Rotation,
+    /// First RAM column wired to the glass, with normal segment direction
+    column_offset: u8,
+    range_policy: RangePolicy,
+    /// Highest contrast sent to the controller, `u8::MAX` for no limit
+    max_contrast: u8,
+    /// One bit per page known to be blank in the controller RAM
+    blank_pages: u8,
+    /// One bit per page of the buffer changed since it was last sent in full
+    dirty_pages: u8,
+    /// Consecutive failures of `show_or_reinit()` triggering `reinit()`,
+    /// 0 for never
+    auto_reinit: u8,
+    /// Consecutive failures of `show_or_reinit()` so far
+    failed_shows: u8,
+    pages: PagePositions,
+    /// What the controller RAM is believed to contain. Frames larger than
+    /// 1KB (132x64) do not fit, their last page is always sent in full.
+    #[cfg(feature = "shadow")]
+    shadow: [u8; BUFFER_SIZE],
+    /// One bit per page whose shadow copy can be trusted
+    #[cfg(feature = "shadow")]
+    shadow_valid: u8,
+}
+
+impl<DC: Pin, RST: Pin, S: Transport> ST7567<DC, RST, S> {
+    pub fn new(spi: S, dc_pin: DC, rst_pin: RST) -> Self {
+        Self::with_buffer(spi, dc_pin, rst_pin, [0; BUFFER_SIZE])
     }
+}
 
-    impl MockPin {
-        pub fn new() -> Self {
-            Self {
-                states: RefCell::new(Vec::new()),
-                should_fail: RefCell::new(false),
-            }
-        }
+impl<DC: Pin, S: Transport> ST7567<DC, NoResetPin, S> {
+    /// Create a driver for a board whose RST line is not connected to a GPIO.
+    ///
+    /// `reset()` then falls back to the software reset command.
+    pub fn new_without_reset(spi: S, dc_pin: DC) -> Self {
+        Self::with_buffer_without_reset(spi, dc_pin, [0; BUFFER_SIZE])
+    }
+}
 
-        pub fn set_fail(&self, fail: bool) {
-            *self.should_fail.borrow_mut() = fail;
-        }
+impl<DC: Pin, S: Transport, B: PixelBuffer> ST7567<DC, NoResetPin, S, B> {
+    /// Create a driver without RST pin, drawing into caller-provided storage.
+    pub fn with_buffer_without_reset(spi: S, dc_pin: DC, buf: B) -> Self {
+        Self::from_parts(spi, dc_pin, None, buf)
+    }
+}
 
-        pub fn get_states(&self) -> Vec<PinState> {
-            self.states.borrow().clone()
-        }
+impl<DC: Pin, RST: Pin, S: Transport, B: PixelBuffer> ST7567<DC, RST, S, B> {
+    /// Create a driver drawing into caller-provided storage.
+    pub fn with_buffer(spi: S, dc_pin: DC, rst_pin: RST, buf: B) -> Self {
+        Self::from_parts(spi, dc_pin, Some(rst_pin), buf)
+    }
 
-        pub fn clear_states(&self) {
-            self.states.borrow_mut().clear();
+    /// Replace the whole display buffer with `frame`, e.g. a splash screen
+    /// prepared at build time. `frame` is in the layout described at
+    /// [`buffer`](Self::buffer).
+    ///
+    /// Only for 128x64 panels, other sizes use
+    /// [`set_sized_buffer`](Self::set_sized_buffer).
+    pub fn set_buffer(&mut self, frame: &[u8; BUFFER_SIZE]) {
+        if let Some(buffer) = self.buf.as_bytes_mut().get_mut(..BUFFER_SIZE) {
+            buffer.copy_from_slice(frame);
         }
+        self.dirty_pages = u8::MAX;
     }
+}
 
-    impl Pin for MockPin {
-        type Error = MockError;
+impl<DC: Pin, RST: Pin, S: Transport, B: PixelBuffer, const W: usize, const H: usize>
+    ST7567<DC, RST, S, B, W, H>
+{
+    const GEOMETRY: Geometry = Geometry {
+        width: W,
+        height: H,
+    };
 
-        fn set_value(&mut self, pin_state: PinState) -> Result<(), Self::Error> {
-            if *self.should_fail.borrow() {
-                return Err(MockError::PinError);
-            }
-            self.states.borrow_mut().push(pin_state);
-            Ok(())
+    /// Checked when a driver is created, the controller has 132 columns and
+    /// 8 pages.
+    const VALID_GEOMETRY: () = assert!(
+        W > 0 && W <= RAM_COLUMNS as usize && H > 0 && H <= 64 && H % 8 == 0,
+        "panel must be at most 132x64, with a height multiple of 8"
+    );
+
+    /// One bit per page of the panel.
+    const ALL_PAGES: u8 = 0xff >> (8 - H / 8);
+
+    /// RAM columns beyond the visible ones.
+    const SPARE_COLUMNS: u8 = (RAM_COLUMNS as usize - W) as u8;
+
+    /// Create a driver for a `W` x `H` panel, drawing into caller-provided
+    /// storage of [`buffer_size(W, H)`](buffer_size) bytes.
+    ///
+    /// ```ignore
+    /// let display: ST7567<_, _, _, _, 128, 32> =
+    ///     ST7567::with_sized_buffer(spi, dc, rst, [0; st7567::buffer_size(128, 32)]);
+    /// ```
+    ///
+    /// Dimensions the controller cannot drive fail to compile.
+    pub fn with_sized_buffer(spi: S, dc_pin: DC, rst_pin: RST, buf: B) -> Self {
+        Self::from_parts(spi, dc_pin, Some(rst_pin), buf)
+    }
+
+    fn from_parts(spi: S, dc_pin: DC, rst_pin: Option<RST>, buf: B) -> Self {
+        let () = Self::VALID_GEOMETRY;
+        Self {
+            spi,
+            dc_pin,
+            rst_pin,
+            buf,
+            // The GFX HAT panel is mounted with reversed COM lines
+            flags: Flags(Flags::COM_REVERSE),
+            page_order: PageOrder::default(),
+            bias: Bias::default(),
+            regulation_ratio: 3,
+            contrast: 40,
+            init_commands: &[],
+            rotation: Rotation::default(),
+            column_offset: 0,
+            range_policy: RangePolicy::default(),
+            max_contrast: u8::MAX,
+            blank_pages: 0,
+            dirty_pages: u8::MAX,
+            auto_reinit: 0,
+            failed_shows: 0,
+            pages: PagePositions::default(),
+            #[cfg(feature = "shadow")]
+            shadow: [0; BUFFER_SIZE],
+            #[cfg(feature = "shadow")]
+            shadow_valid: 0,
         }
     }
 
-    /// Mock SPI Device implementation for testing
-    #[derive(Debug)]
-    pub struct MockSpiDevice {
-        pub written_data: RefCell<Vec<u8>>,
-        pub should_fail: RefCell<bool>,
+    fn command(&mut self, data: &[u8]) -> Result<(), Error<DC, RST, S>> {
+        set_dc_pin(&mut self.dc_pin, Low)?;
+        spi_write(&mut self.spi, Low, data, self.flags.bit_order())
     }
 
-    impl MockSpiDevice {
-        pub fn new() -> Self {
-            Self {
-                written_data: RefCell::new(Vec::new()),
-                should_fail: RefCell::new(false),
+    /// Send raw command bytes, for registers the driver does not wrap.
+    ///
+    /// Nothing is checked: a wrong byte can leave the controller in any
+    /// state. What `show()` knows about the controller RAM is forgotten, the
+    /// next flush sends every page.
+    #[cfg(feature = "unsafe_raw")]
+    pub fn send_command(&mut self, bytes: &[u8]) -> Result<(), Error<DC, RST, S>> {
+        self.forget_controller_ram();
+        self.command(bytes)
+    }
+
+    /// Send raw display data, written at the current page and column.
+    ///
+    /// Like [`send_command`](Self::send_command), the next flush sends every
+    /// page.
+    #[cfg(feature = "unsafe_raw")]
+    pub fn send_data(&mut self, bytes: &[u8]) -> Result<(), Error<DC, RST, S>> {
+        self.forget_controller_ram();
+        self.raw_data(bytes)
+    }
+
+    /// Send controller commands, in as few transfers as possible.
+    ///
+    /// Every parameter is checked first: if one is out of range,
+    /// [`Error::InvalidArgument`] is returned and nothing is sent.
+    pub fn send_commands(&mut self, commands: &[Command]) -> Result<(), Error<DC, RST, S>> {
+        if commands.iter().any(|command| command.encode().is_none()) {
+            return Err(Error::InvalidArgument);
+        }
+        let mut bytes = [0u8; 32];
+        let mut len = 0usize;
+        for encoded in commands.iter().filter_map(|command| command.encode()) {
+            let encoded = encoded.as_bytes();
+            let end = len.saturating_add(encoded.len());
+            let (start, end) = if end > bytes.len() {
+                self.command(bytes.get(..len).unwrap_or_default())?;
+                (0, encoded.len())
+            } else {
+                (len, end)
+            };
+            if let Some(slot) = bytes.get_mut(start..end) {
+                slot.copy_from_slice(encoded);
             }
+            len = end;
         }
-
-        pub fn set_fail(&self, fail: bool) {
-            *self.should_fail.borrow_mut() = fail;
+        if len == 0 {
+            return Ok(());
         }
+        self.command(bytes.get(..len).unwrap_or_default())
+    }
 
-        pub fn get_written_data(&self) -> Vec<u8> {
-            self.written_data.borrow().clone()
-        }
+    /// Send a slice of the display buffer as data.
+    ///
+    /// Borrowing straight from the buffer avoids a stack copy of the bytes.
+    fn data(&mut self, range: Range<usize>) -> Result<(), Error<DC, RST, S>> {
+        set_dc_pin(&mut self.dc_pin, High)?;
+        spi_write(
+            &mut self.spi,
+            High,
+            self.buf.as_bytes().get(range).unwrap_or_default(),
+            self.flags.bit_order(),
+        )
+    }
 
-        pub fn clear_written_data(&self) {
-            self.written_data.borrow_mut().clear();
-        }
+    /// Send bytes that are not part of the display buffer as data.
+    fn raw_data(&mut self, data: &[u8]) -> Result<(), Error<DC, RST, S>> {
+        set_dc_pin(&mut self.dc_pin, High)?;
+        spi_write(&mut self.spi, High, data, self.flags.bit_order())
     }
 
-    impl embedded_hal::spi::ErrorType for MockSpiDevice {
-        type Error = MockError;
+    /// Pulse the reset line, using `delay` to time the pulse and the wait
+    /// for the controller to come out of reset.
+    ///
+    /// Without RST pin, [`software_reset`](Self::software_reset) is used
+    /// instead.
+    pub fn reset<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<DC, RST, S>> {
+        self.flags.set(Flags::INITIALIZED, false);
+        self.flags.set(Flags::INVERTED, false);
+        self.forget_controller_ram();
+        let rst_pin = match self.rst_pin.as_mut() {
+            Some(rst_pin) => rst_pin,
+            None => return self.software_reset(delay),
+        };
+        set_rst_pin(rst_pin, Low)?;
+        delay.delay_ms(10);
+        set_rst_pin(rst_pin, High)?;
+        delay.delay_ms(100);
+        Ok(())
     }
 
-    impl embedded_hal::spi::SpiDevice for MockSpiDevice {
-        fn transaction(
-            &mut self,
-            operations: &mut [embedded_hal::spi::Operation<'_, u8>],
-        ) -> Result<(), Self::Error> {
-            if *self.should_fail.borrow() {
-                return Err(MockError::SpiError);
-            }
+    /// Reset the controller registers with the `ST7567_EXIT_SOFTRST`
+    /// command, then wait for it to settle.
+    ///
+    /// Useful when the RST line is not wired. Display RAM content is kept.
+    pub fn software_reset<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<DC, RST, S>> {
+        self.flags.set(Flags::INITIALIZED, false);
+        self.flags.set(Flags::INVERTED, false);
+        self.forget_controller_ram();
+        self.send_commands(&[Command::SoftwareReset])?;
+        // Same conservative settle time as after a hardware reset
+        delay.delay_ms(100);
+        Ok(())
+    }
 
-            for operation in operations {
-                match operation {
-                    embedded_hal::spi::Operation::Write(data) => {
-                        self.written_data.borrow_mut().extend_from_slice(data);
-                    }
-                    _ => {} // We only care about write operations for this driver
-                }
-            }
-            Ok(())
-        }
+    /// Set the LCD contrast, 0 to 63. The value is also used by the
+    /// following `init()` calls.
+    ///
+    /// Larger values are handled according to the
+    /// [range policy](Self::set_range_policy), then capped by
+    /// [`set_max_contrast`](Self::set_max_contrast).
+    pub fn set_contrast(&mut self, value: u8) -> Result<(), Error<DC, RST, S>> {
+        let value = self
+            .fit(value, ST7567_CONTRAST_MASK)?
+            .min(self.max_contrast);
+        self.send_commands(&[Command::SetContrast(value)])?;
+        self.contrast = value;
+        Ok(())
     }
 
-    // Helper to create a test ST7567 instance
-    fn create_test_st7567() -> ST7567<MockPin, MockSpiDevice> {
-        let spi = MockSpiDevice::new();
-        let dc_pin = MockPin::new();
-        let rst_pin = MockPin::new();
-        ST7567::new(spi, dc_pin, rst_pin)
+    /// The contrast last set, by [`set_contrast`](Self::set_contrast) or
+    /// [`set_brightness`](Self::set_brightness).
+    pub fn contrast(&self) -> u8 {
+        self.contrast
     }
 
-    #[test]
-    fn test_new() {
-        let st7567 = create_test_st7567();
-        // Buffer should be initialized to all zeros
-        assert_eq!(st7567.buf, [0; BUFFER_SIZE]);
+    /// Cap the contrast of every following [`set_contrast`](Self::set_contrast),
+    /// [`fade_contrast`](Self::fade_contrast),
+    /// [`set_brightness`](Self::set_brightness) and calibration, e.g. to bound
+    /// the power draw or keep a settings menu from making the panel
+    /// unreadable. `u8::MAX` removes the cap (the default).
+    ///
+    /// Nothing is sent: a larger contrast already set stays on screen until
+    /// the next of these calls, but is lowered for the following `init()`.
+    pub fn set_max_contrast(&mut self, max: u8) {
+        self.max_contrast = max;
+        self.contrast = self.contrast.min(max);
     }
 
-    #[test]
-    fn test_clear() {
-        let mut st7567 = create_test_st7567();
-        // Set some pixels first
-        st7567.set_pixel(10, 20, true);
-        st7567.set_pixel(50, 30, true);
-        
-        // Clear should reset all pixels
-        st7567.clear();
-        assert_eq!(st7567.buf, [0; BUFFER_SIZE]);
+    /// The contrast cap, see [`set_max_contrast`](Self::set_max_contrast).
+    pub fn max_contrast(&self) -> u8 {
+        self.max_contrast
+    }
+
+    /// Ramp the contrast from its current value to `target`, by `step` every
+    /// 10ms, to avoid a contrast pop when the panel comes up or goes dark.
+    ///
+    /// `target` follows the [range policy](Self::set_range_policy) like
+    /// [`set_contrast`](Self::set_contrast). A `step` of 0 is taken as 1.
+    pub fn fade_contrast<D: DelayNs>(
+        &mut self,
+        target: u8,
+        step: u8,
+        delay: &mut D,
+    ) -> Result<(), Error<DC, RST, S>> {
+        let target = self
+            .fit(target, ST7567_CONTRAST_MASK)?
+            .min(self.max_contrast);
+        let step = step.max(1);
+        while self.contrast != target {
+            let value = if self.contrast < target {
+                self.contrast.saturating_add(step).min(target)
+            } else {
+                self.contrast.saturating_sub(step).max(target)
+            };
+            self.set_contrast(value)?;
+            if value != target {
+                delay.delay_ms(FADE_STEP_MS);
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the darkness of the pixels from 0 to 100%, larger values are
+    /// taken as 100%.
+    ///
+    /// Picks the regulation ratio and contrast from [`BRIGHTNESS_TABLE`].
+    /// Between two rows with the same ratio, the contrast is interpolated;
+    /// otherwise the closest row is used. Both values are also used by the
+    /// following `init()` calls. The contrast is capped by
+    /// [`set_max_contrast`](Self::set_max_contrast).
+    pub fn set_brightness(&mut self, percent: u8) -> Result<(), Error<DC, RST, S>> {
+        let percent = usize::from(percent.min(100));
+        let row = |index: usize| BRIGHTNESS_TABLE.get(index).copied().unwrap_or_default();
+        let (low, high) = (row(percent / 10), row((percent / 10).saturating_add(1)));
+        let offset = percent % 10;
+        let (ratio, contrast) = if offset == 0 {
+            low
+        } else if low.0 == high.0 {
+            let span = usize::from(high.1.saturating_sub(low.1));
+            let step = span.saturating_mul(offset) / 10;
+            (low.0, low.1.saturating_add(step as u8))
+        } else if offset < 5 {
+            low
+        } else {
+            high
+        };
+        let contrast = contrast.min(self.max_contrast);
+        self.send_commands(&[
+            Command::SetRegulationRatio(ratio),
+            Command::SetContrast(contrast),
+        ])?;
+        self.regulation_ratio = ratio;
+        self.contrast = contrast;
+        Ok(())
+    }
+
+    /// Select the LCD bias ratio.
+    ///
+    /// The choice is remembered and used by the following `init()` calls, so
+    /// it can be made once, before the first `init()`.
+    pub fn set_bias(&mut self, bias: Bias) -> Result<(), Error<DC, RST, S>> {
+        self.bias = bias;
+        self.send_commands(&[Command::SetBias(bias)])
+    }
+
+    /// The LCD bias ratio last set.
+    pub fn bias(&self) -> Bias {
+        self.bias
+    }
+
+    /// Select the regulation resistor ratio, in `0..=7`, which sets the
+    /// range of the LCD driving voltage V0.
+    ///
+    /// Together with the contrast, it tunes the darkness of the panel. The
+    /// choice is remembered and used by the following `init()` calls.
+    /// Returns [`Error::InvalidArgument`] for values above 7.
+    pub fn set_regulation_ratio(&mut self, ratio: u8) -> Result<(), Error<DC, RST, S>> {
+        self.send_commands(&[Command::SetRegulationRatio(ratio)])?;
+        self.regulation_ratio = ratio;
+        Ok(())
+    }
+
+    /// The regulation resistor ratio last set.
+    pub fn regulation_ratio(&self) -> u8 {
+        self.regulation_ratio
+    }
+
+    /// Select the RAM line shown at the top of the screen, from 0 to 63.
+    ///
+    /// Lines wrap around, so stepping the start line scrolls the whole frame
+    /// vertically in hardware, without redrawing the buffer. Larger values
+    /// are handled according to the [range policy](Self::set_range_policy).
+    pub fn set_start_line(&mut self, line: u8) -> Result<(), Error<DC, RST, S>> {
+        let line = self.fit(line, ST7567_STARTLINE_MASK)?;
+        self.send_commands(&[Command::SetStartLine(line)])
+    }
+
+    /// Choose how contrast, start line and raw mode page values out of range
+    /// are handled. Defaults to [`RangePolicy::Wrap`].
+    pub fn set_range_policy(&mut self, range_policy: RangePolicy) {
+        self.range_policy = range_policy;
+    }
+
+    /// `value` made to fit in `mask` according to the range policy.
+    pub(crate) fn fit(&self, value: u8, mask: u8) -> Result<u8, Error<DC, RST, S>> {
+        self.range_policy
+            .apply(value, mask)
+            .ok_or(Error::InvalidArgument)
+    }
+
+    /// Scroll the whole frame by walking the start line from `from` to `to`,
+    /// one line at a time, waiting `step_ms` between lines.
+    ///
+    /// Both ends are included, and brought to `0..=63` according to the
+    /// [range policy](Self::set_range_policy). The buffer is left untouched,
+    /// so this is a cheap transition for content already on screen.
+    pub fn scroll_animate<D: DelayNs>(
+        &mut self,
+        from: u8,
+        to: u8,
+        step_ms: u32,
+        delay: &mut D,
+    ) -> Result<(), Error<DC, RST, S>> {
+        let (mut line, to) = (
+            self.fit(from, ST7567_STARTLINE_MASK)?,
+            self.fit(to, ST7567_STARTLINE_MASK)?,
+        );
+        loop {
+            self.set_start_line(line)?;
+            if line == to {
+                return Ok(());
+            }
+            delay.delay_ms(step_ms);
+            line = if line < to {
+                line.saturating_add(1)
+            } else {
+                line.saturating_sub(1)
+            };
+        }
+    }
+
+    /// Scroll the frame drawn in the buffer onto the screen from below, in
+    /// hardware, moving the start line one line every `step_ms`.
+    ///
+    /// For panels at most 32 rows tall, whose frame fits twice in the 64
+    /// rows of the controller RAM. Draw the next screen into the buffer,
+    /// without calling `show()`, then call this: the buffer is written to
+    /// the RAM rows below the visible ones, which the start line then walks
+    /// to, pushing the old screen up. From then on the frame lives in those
+    /// rows, until `init()` moves it back to the top.
+    ///
+    /// Returns [`Error::InvalidArgument`] for taller panels, which have no
+    /// off-screen rows to draw into: use
+    /// [`scroll_animate`](Self::scroll_animate) on content already on
+    /// screen instead. Start lines set by hand are lost.
+    pub fn scroll_to_screen<D: DelayNs>(
+        &mut self,
+        step_ms: u32,
+        delay: &mut D,
+    ) -> Result<(), Error<DC, RST, S>> {
+        if H > 32 {
+            return Err(Error::InvalidArgument);
+        }
+        let from = self.pages.start();
+        self.pages.set_start(from.saturating_add((H / 8) as u8) & 7);
+        self.forget_controller_ram();
+        if let Err(error) = self.show() {
+            self.pages.set_start(from);
+            self.forget_controller_ram();
+            return Err(error);
+        }
+        let first_line = usize::from(from).saturating_mul(8);
+        for step in 1..=H {
+            delay.delay_ms(step_ms);
+            let line = first_line.saturating_add(step) & 63;
+            self.set_start_line(line as u8)?;
+        }
+        Ok(())
+    }
+
+    /// Mirror the image horizontally, e.g. for a panel mounted upside down.
+    ///
+    /// Only the segment scan direction changes, the framebuffer is left
+    /// untouched. The choice is remembered and used by the following
+    /// `init()` calls.
+    ///
+    /// The controller RAM is 132 columns wide: once reversed, the 128
+    /// visible columns are the last ones, which `show()` takes into account.
+    pub fn set_segment_remap(&mut self, remap: bool) -> Result<(), Error<DC, RST, S>> {
+        self.flags.set(Flags::SEGMENT_REMAP, remap);
+        self.send_commands(&[self.segment_direction()])
+    }
+
+    /// Whether the segment scan is reversed, by the remap or by a 180°
+    /// rotation.
+    fn segment_reversed(&self) -> bool {
+        self.flags.get(Flags::SEGMENT_REMAP) != (self.rotation == Rotation::Rotate180)
+    }
+
+    fn segment_direction(&self) -> Command {
+        Command::SetSegmentReverse(self.segment_reversed())
+    }
+
+    /// Shift the image right by `offset` RAM columns, from 0 to 4, for panels
+    /// whose glass does not start at the first column of the 132 column
+    /// controller RAM.
+    ///
+    /// The offset is the one seen with the normal segment direction, it is
+    /// mirrored when segments are reversed. Returns
+    /// [`Error::InvalidArgument`] above 4, or above `132 - W` for panels
+    /// wider than 128 pixels. Applies from the next `show()`.
+    pub fn set_column_offset(&mut self, offset: u8) -> Result<(), Error<DC, RST, S>> {
+        if offset > Self::SPARE_COLUMNS {
+            return Err(Error::InvalidArgument);
+        }
+        self.column_offset = offset;
+        self.forget_controller_ram();
+        Ok(())
+    }
+
+    /// Set the orientation of the drawing coordinates.
+    ///
+    /// 180° flips both scan directions of the controller, so it applies to
+    /// what is already on screen and costs nothing when drawing. 90° and 270°
+    /// are applied by the drawing methods, to pixels drawn afterwards. The
+    /// choice is remembered and used by the following `init()` calls.
+    pub fn set_rotation(&mut self, rotation: Rotation) -> Result<(), Error<DC, RST, S>> {
+        self.rotation = rotation;
+        self.send_commands(&[self.segment_direction(), self.com_direction()])
+    }
+
+    /// The orientation of the drawing coordinates.
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    /// Width and height of the drawing area, depending on the rotation.
+    pub fn dimensions(&self) -> (usize, usize) {
+        self.rotation.dimensions_of(Self::GEOMETRY)
+    }
+
+    /// Select the COM scan direction, which flips the image vertically.
+    ///
+    /// Reversed by default, matching the GFX HAT. The choice is remembered
+    /// and used by the following `init()` calls.
+    pub fn set_com_reverse(&mut self, reverse: bool) -> Result<(), Error<DC, RST, S>> {
+        self.flags.set(Flags::COM_REVERSE, reverse);
+        self.send_commands(&[self.com_direction()])
+    }
+
+    fn com_direction(&self) -> Command {
+        let reverse = self.flags.get(Flags::COM_REVERSE);
+        Command::SetComReverse(reverse != (self.rotation == Rotation::Rotate180))
+    }
+
+    /// Blank the screen, e.g. for a screensaver. The controller RAM and
+    /// settings are kept, [`display_on`](Self::display_on) restores the
+    /// image without a new `init()`.
+    pub fn display_off(&mut self) -> Result<(), Error<DC, RST, S>> {
+        self.send_commands(&[Command::DisplayOff])
+    }
+
+    /// Show the controller RAM again after [`display_off`](Self::display_off).
+    pub fn display_on(&mut self) -> Result<(), Error<DC, RST, S>> {
+        self.send_commands(&[Command::DisplayOn])
+    }
+
+    /// Invert the whole display in hardware, without touching the buffer.
+    ///
+    /// Useful to flash the screen for alerts.
+    pub fn set_inverted(&mut self, inverted: bool) -> Result<(), Error<DC, RST, S>> {
+        self.send_commands(&[Command::SetInverse(inverted)])?;
+        self.flags.set(Flags::INVERTED, inverted);
+        Ok(())
+    }
+
+    /// Whether the display is inverted in hardware, by
+    /// [`set_inverted`](Self::set_inverted) or night mode. A reset or
+    /// `init()` turns inversion off.
+    pub fn is_inverted(&self) -> bool {
+        self.flags.get(Flags::INVERTED)
+    }
+
+    /// Turn every pixel on regardless of the RAM content, or resume displaying
+    /// the RAM.
+    ///
+    /// Meant for factory smoke tests checking that every segment and common
+    /// line is driven.
+    pub fn set_all_pixels_on(&mut self, on: bool) -> Result<(), Error<DC, RST, S>> {
+        self.send_commands(&[Command::SetAllPixelsOn(on)])
+    }
+
+    /// Switch the whole display to a light-on-dark rendering, or back.
+    ///
+    /// This inverts the panel in hardware, the framebuffer is left untouched,
+    /// so applications get a dark mode without changing their draw calls.
+    /// Backlight control is outside of this driver and must be adjusted
+    /// separately if needed.
+    pub fn set_night_mode(&mut self, enabled: bool) -> Result<(), Error<DC, RST, S>> {
+        self.set_inverted(enabled)
+    }
+
+    /// Shut the panel down before cutting its supply.
+    ///
+    /// Follows the datasheet power off order: display off, internal power
+    /// circuits (booster, regulator, follower) off, then all points on which
+    /// completes the transition to sleep mode. `delay` then leaves time for
+    /// the booster capacitors to discharge, so VDD can be cut without leaving
+    /// a DC bias on the LCD glass. Use `reset()` and `init()` to start again,
+    /// `init()` turns all points off.
+    pub fn power_off<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<DC, RST, S>> {
+        self.flags.set(Flags::INITIALIZED, false);
+        self.send_commands(&[
+            Command::DisplayOff,
+            Command::SetPowerControl(0),
+            Command::SetAllPixelsOn(true),
+        ])?;
+        delay.delay_ms(100);
+        Ok(())
+    }
+
+    /// Send the initialization sequence, see
+    /// [`set_init_sequence`](Self::set_init_sequence).
+    ///
+    /// Required before `show()`, and again after a reset or power off.
+    pub fn init(&mut self) -> Result<(), Error<DC, RST, S>> {
+        self.flags.set(Flags::INITIALIZED, false);
+        self.flags.set(Flags::INVERTED, false);
+        self.reset_start_page();
+        self.send_init()?;
+        self.flags.set(Flags::INITIALIZED, true);
+        Ok(())
+    }
+
+    /// `init()` shows RAM line 0 at the top: move the frame back there.
+    fn reset_start_page(&mut self) {
+        if self.pages.start() != 0 {
+            self.pages.set_start(0);
+            self.forget_controller_ram();
+        }
+    }
+
+    /// Whether `init()` completed since the last reset or power off, i.e.
+    /// whether `show()` can be used.
+    pub fn is_initialized(&self) -> bool {
+        self.flags.get(Flags::INITIALIZED)
+    }
+
+    fn send_init(&mut self) -> Result<(), Error<DC, RST, S>> {
+        if self.flags.get(Flags::REPLACE_INIT) {
+            return self.command(self.init_commands);
+        }
+        self.send_commands(&[
+            Command::SetBias(self.bias),
+            self.segment_direction(),
+            self.com_direction(),
+            Command::SetInverse(false),
+            Command::SetAllPixelsOn(false),
+            Command::SetStartLine(0),
+            Command::SetPowerControl(POWER_ALL),
+            Command::SetRegulationRatio(self.regulation_ratio),
+            Command::DisplayOn,
+            Command::SetContrast(self.contrast),
+        ])?;
+        self.extend_init()
+    }
+
+    /// Replace or extend the commands sent by `init()` and `init_staged()`,
+    /// for panels the built-in sequence does not suit.
+    pub fn set_init_sequence(&mut self, init_sequence: InitSequence) {
+        let (commands, replace) = match init_sequence {
+            InitSequence::BuiltIn => (&[][..], false),
+            InitSequence::Replace(commands) => (commands, true),
+            InitSequence::Extend(commands) => (commands, false),
+        };
+        self.init_commands = commands;
+        self.flags.set(Flags::REPLACE_INIT, replace);
+    }
+
+    fn extend_init(&mut self) -> Result<(), Error<DC, RST, S>> {
+        if self.init_commands.is_empty() {
+            return Ok(());
+        }
+        self.command(self.init_commands)
+    }
+
+    /// Initialize like [`init`](Self::init), but bring the power circuits up
+    /// one at a time, as recommended by the datasheet.
+    ///
+    /// The booster, then the voltage regulator, then the voltage follower are
+    /// enabled, `delay` letting each stage settle for 50ms. Contrast and
+    /// regulation ratio are set before, and the display turned on after, so
+    /// the panel does not flash while V0 ramps up.
+    ///
+    /// A [replacement](InitSequence::Replace) init sequence is sent as is,
+    /// without stages.
+    pub fn init_staged<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<DC, RST, S>> {
+        self.flags.set(Flags::INITIALIZED, false);
+        self.flags.set(Flags::INVERTED, false);
+        self.reset_start_page();
+        self.send_init_staged(delay)?;
+        self.flags.set(Flags::INITIALIZED, true);
+        Ok(())
+    }
+
+    fn send_init_staged<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<DC, RST, S>> {
+        if self.flags.get(Flags::REPLACE_INIT) {
+            return self.command(self.init_commands);
+        }
+        self.send_commands(&[
+            Command::SetBias(self.bias),
+            self.segment_direction(),
+            self.com_direction(),
+            Command::SetInverse(false),
+            Command::SetAllPixelsOn(false),
+            Command::SetStartLine(0),
+            Command::SetRegulationRatio(self.regulation_ratio),
+            Command::SetContrast(self.contrast),
+        ])?;
+        // Booster, then regulator, then follower
+        for &stage in [0b100, 0b110, POWER_ALL].iter() {
+            self.send_commands(&[Command::SetPowerControl(stage)])?;
+            delay.delay_ms(50);
+        }
+        self.send_commands(&[Command::DisplayOn])?;
+        self.extend_init()
+    }
+
+    /// Clear the display buffer
+    pub fn clear(&mut self) {
+        self.fill(false);
+    }
+
+    /// Set every pixel of the display buffer to `value`.
+    pub fn fill(&mut self, value: bool) {
+        self.buf.fill(value);
+        self.dirty_pages = u8::MAX;
+    }
+
+    /// Set every pixel of the `width` x `height` rectangle at `x`, `y` to
+    /// `value`.
+    ///
+    /// The rectangle is clipped to the screen and follows the configured
+    /// [`Rotation`]. Whole bytes are written at once, 8 rows per column, so
+    /// large areas cost far less than a [`set_pixel`](Self::set_pixel) loop.
+    pub fn fill_region(&mut self, x: usize, y: usize, width: usize, height: usize, value: bool) {
+        let area = BoundingBox {
+            x,
+            y,
+            width,
+            height,
+        };
+        if let Some((columns, rows)) = self.buffer_area(area) {
+            self.touch_rows(rows.clone());
+            Self::GEOMETRY.fill_rect(self.buf.as_bytes_mut(), columns, rows, value);
+        }
+    }
+
+    /// Turn off the pixels of a rectangle, see
+    /// [`fill_region`](Self::fill_region).
+    pub fn clear_region(&mut self, x: usize, y: usize, width: usize, height: usize) {
+        self.fill_region(x, y, width, height, false);
+    }
+
+    /// Framebuffer columns and rows covered by `area`, given in drawing
+    /// coordinates and clipped to the screen. `None` if nothing is covered.
+    pub(crate) fn buffer_area(&self, area: BoundingBox) -> Option<(Range<usize>, Range<usize>)> {
+        if area.width == 0 || area.height == 0 {
+            return None;
+        }
+        let (width, height) = self.dimensions();
+        let (x0, y0) = self.rotation.to_buffer(Self::GEOMETRY, area.x, area.y)?;
+        let (x1, y1) = self.rotation.to_buffer(
+            Self::GEOMETRY,
+            area.x
+                .saturating_add(area.width)
+                .min(width)
+                .checked_sub(1)?,
+            area.y
+                .saturating_add(area.height)
+                .min(height)
+                .checked_sub(1)?,
+        )?;
+        let (right, bottom) = (x0.max(x1).saturating_add(1), y0.max(y1).saturating_add(1));
+        Some((x0.min(x1)..right, y0.min(y1)..bottom))
+    }
+
+    /// Send the pages and columns covering `area`, in drawing coordinates.
+    #[cfg(any(feature = "region", feature = "strip-chart"))]
+    pub(crate) fn show_area(&mut self, area: BoundingBox) -> Result<(), Error<DC, RST, S>> {
+        let (columns, rows) = match self.buffer_area(area) {
+            Some(area) => area,
+            None => return Ok(()),
+        };
+        self.begin_flush()?;
+        for page in rows.start / 8..=rows.end.saturating_sub(1) / 8 {
+            self.flush_page(page, columns.clone())?;
+        }
+        self.end_flush()
+    }
+    /// Draw into the buffer line by line, with dithering, e.g. from a UI
+    /// toolkit's software renderer. See [`render`] for details.
+    #[cfg(feature = "render")]
+    pub fn line_renderer(&mut self) -> LineRenderer<'_, B> {
+        self.dirty_pages = u8::MAX;
+        LineRenderer::with_geometry(&mut self.buf, self.rotation, Self::GEOMETRY)
+    }
+
+    /// The display buffer, in controller page layout.
+    ///
+    /// Page `p` holds rows `8 * p` to `8 * p + 7` and takes `W` bytes, one
+    /// per column, starting at byte `p * W`. The least significant bit of a
+    /// byte is the top row of its page. This is the framebuffer layout,
+    /// which does not follow the [`Rotation`].
+    pub fn buffer(&self) -> &[u8] {
+        self.buf
+            .as_bytes()
+            .get(..buffer_size(W, H))
+            .unwrap_or_default()
+    }
+
+    /// The display buffer, for renderers composing whole frames, see
+    /// [`buffer`](Self::buffer) for the layout. Every page counts as changed
+    /// for [`show_dirty`](Self::show_dirty).
+    pub fn buffer_mut(&mut self) -> &mut [u8] {
+        self.dirty_pages = u8::MAX;
+        self.buf
+            .as_bytes_mut()
+            .get_mut(..buffer_size(W, H))
+            .unwrap_or_default()
+    }
+
+    /// Replace the whole display buffer with `frame`, e.g. received over
+    /// the network, see [`buffer`](Self::buffer) for the layout.
+    ///
+    /// Returns [`Error::InvalidArgument`] and leaves the buffer untouched
+    /// unless `frame` holds exactly [`buffer_size(W, H)`](buffer_size) bytes.
+    pub fn try_set_buffer(&mut self, frame: &[u8]) -> Result<(), Error<DC, RST, S>> {
+        let buffer = self.buffer_mut();
+        if frame.len() != buffer.len() {
+            return Err(Error::InvalidArgument);
+        }
+        buffer.copy_from_slice(frame);
+        Ok(())
+    }
+
+    /// Replace the whole display buffer with `frame`, e.g. a splash screen
+    /// prepared at build time, see [`buffer`](Self::buffer) for the layout.
+    ///
+    /// `N` must be [`buffer_size(W, H)`](buffer_size), other sizes fail to
+    /// compile.
+    pub fn set_sized_buffer<const N: usize>(&mut self, frame: &[u8; N]) {
+        let () = FrameSize::<N, W, H>::MATCHES;
+        // Only fails for storage shorter than the frame, left as it was
+        let _ = self.try_set_buffer(frame);
+    }
+
+    /// Count lit pixels, per page and overall, and locate them.
+    ///
+    /// Handy to detect a frame that is unexpectedly blank.
+    pub fn buffer_stats(&self) -> BufferStats {
+        BufferStats::of(self.buf.as_bytes(), Self::GEOMETRY)
+    }
+
+    /// Set a single pixel in the  display buffer.
+    ///
+    /// Ignore out of bound values for x & y. Coordinates follow the
+    /// configured [`Rotation`].
+    pub fn set_pixel(&mut self, x: usize, y: usize, value: bool) {
+        if let Some((x, y)) = self.rotation.to_buffer(Self::GEOMETRY, x, y) {
+            self.touch_rows(y..y.saturating_add(1));
+            Self::GEOMETRY.set(self.buf.as_bytes_mut(), x, y, value);
+        }
+    }
+
+    /// Mark the pages holding the buffer `rows` as changed.
+    pub(crate) fn touch_rows(&mut self, rows: Range<usize>) {
+        for page in rows.start / 8..rows.end.saturating_add(7) / 8 {
+            self.dirty_pages |= 1u8.checked_shl(page as u32).unwrap_or(0);
+        }
+    }
+
+    /// Read back a single pixel of the display buffer.
+    ///
+    /// Out of bound coordinates read as off. Coordinates follow the
+    /// configured [`Rotation`].
+    pub fn get_pixel(&self, x: usize, y: usize) -> bool {
+        match self.rotation.to_buffer(Self::GEOMETRY, x, y) {
+            Some((x, y)) => Self::GEOMETRY.get(self.buf.as_bytes(), x, y),
+            None => false,
+        }
+    }
+
+    /// Let `show()` skip pages that are blank both in the buffer and in the
+    /// controller RAM.
+    ///
+    /// The driver keeps track of which pages it last flushed as blank, so
+    /// sparse content (a small clock on an empty screen) only costs the
+    /// transfer of the pages actually in use. Pages are assumed to hold
+    /// unknown content after a reset, until flushed once.
+    pub fn set_skip_blank_pages(&mut self, skip: bool) {
+        self.flags.set(Flags::SKIP_BLANK_PAGES, skip);
+    }
+
+    /// Tell the driver which bit order the SPI bus was configured with.
+    ///
+    /// Some platforms can only shift bytes out LSB-first. Without this, the
+    /// controller would receive every command and pixel byte mirrored,
+    /// showing garbage. With [`BitOrder::LsbFirst`], bytes are mirrored
+    /// before being sent, which costs a 32 bytes stack chunk per transfer.
+    pub fn set_bit_order(&mut self, bit_order: BitOrder) {
+        self.flags
+            .set(Flags::LSB_FIRST, bit_order == BitOrder::LsbFirst);
+    }
+
+    /// Select the order in which pages are sent by `show()`.
+    pub fn set_page_order(&mut self, page_order: PageOrder) {
+        self.page_order = page_order;
+    }
+
+    /// Bring the controller back to a known state after a failed transfer.
+    ///
+    /// Leaves Read-Modify-Write mode, so the column pointer auto-increment
+    /// behaves normally again. Page and column addresses are re-set by every
+    /// flush, so there is nothing else to restore. `show()` calls this on its
+    /// own when the previous flush did not complete.
+    pub fn recover(&mut self) -> Result<(), Error<DC, RST, S>> {
+        self.flags.set(Flags::RMW_PENDING, true);
+        self.send_commands(&[Command::ExitReadModifyWrite])?;
+        self.flags.set(Flags::RMW_PENDING, false);
+        Ok(())
+    }
+
+    /// Send a no-operation command, as a health check of the transport.
+    ///
+    /// An error points at a wedged SPI bus or a failing DC pin. The ST7567
+    /// cannot be read back over SPI, so a success does not prove that the
+    /// panel is still connected.
+    pub fn ping(&mut self) -> Result<(), Error<DC, RST, S>> {
+        self.send_commands(&[Command::Nop])
+    }
+
+    /// Reset and initialize the controller again, then redraw the buffer.
+    ///
+    /// A supply brown-out resets the controller registers, leaving garbage
+    /// on screen while every transfer still succeeds: kiosks can call this
+    /// periodically, or when [`ping`](Self::ping) fails. The inversion is
+    /// restored too.
+    pub fn reinit<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<DC, RST, S>> {
+        let inverted = self.is_inverted();
+        self.reset(delay)?;
+        self.init()?;
+        if inverted {
+            self.set_inverted(true)?;
+        }
+        self.show()
+    }
+
+    /// Let [`show_or_reinit`](Self::show_or_reinit) call
+    /// [`reinit`](Self::reinit) after `failures` consecutive failures, 0
+    /// disabling it (the default).
+    pub fn set_auto_reinit(&mut self, failures: u8) {
+        self.auto_reinit = failures;
+        self.failed_shows = 0;
+    }
+
+    /// Like [`show`](Self::show), recovering with [`reinit`](Self::reinit)
+    /// when it failed too many times in a row, see
+    /// [`set_auto_reinit`](Self::set_auto_reinit).
+    ///
+    /// Returns the error of `show()` until then, and the result of
+    /// `reinit()` once it is attempted.
+    pub fn show_or_reinit<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<DC, RST, S>> {
+        self.show_or_reinit_tracked(delay).map(|_| ())
+    }
+
+    /// [`show_or_reinit`](Self::show_or_reinit), telling whether `reinit()`
+    /// ran.
+    pub(crate) fn show_or_reinit_tracked<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<bool, Error<DC, RST, S>> {
+        let error = match self.show() {
+            Ok(()) => {
+                self.failed_shows = 0;
+                return Ok(false);
+            }
+            Err(error) => error,
+        };
+        self.failed_shows = self.failed_shows.saturating_add(1);
+        if self.auto_reinit == 0 || self.failed_shows < self.auto_reinit {
+            return Err(error);
+        }
+        self.failed_shows = 0;
+        self.reinit(delay).map(|()| true)
+    }
+
+    /// Update the ST7567 display with the buffer contents.
+    ///
+    /// If a previous call failed halfway, the controller is first taken out
+    /// of Read-Modify-Write mode so one error does not corrupt every
+    /// following frame.
+    pub fn show(&mut self) -> Result<(), Error<DC, RST, S>> {
+        self.begin_flush()?;
+        self.pages.set_resume(0);
+        for i in 0..Self::GEOMETRY.pages() {
+            self.show_page(self.page_at(i))?;
+        }
+        self.end_flush()
+    }
+
+    /// Update the display like [`show`](Self::show), stopping at a page
+    /// boundary once `deadline_passed` returns `true`.
+    ///
+    /// `deadline_passed` is called before each page, sending a page takes
+    /// about 1ms at [`SPI_SPEED_HZ`]: return `true` when less than that is
+    /// left. Read-Modify-Write mode is exited even when stopping early. Pages
+    /// left out are sent first by the next call, so a frame still completes
+    /// over several calls.
+    pub fn show_with_deadline<F: FnMut() -> bool>(
+        &mut self,
+        mut deadline_passed: F,
+    ) -> Result<DeadlineFlush, Error<DC, RST, S>> {
+        let pages = Self::GEOMETRY.pages();
+        let resume = usize::from(self.pages.resume())
+            .checked_rem(pages)
+            .unwrap_or(0);
+        let mut next = resume;
+        self.begin_flush()?;
+        while next < pages && !deadline_passed() {
+            self.show_page(self.page_at(next))?;
+            next = next.saturating_add(1);
+        }
+        self.end_flush()?;
+        let complete = next == pages;
+        self.pages.set_resume(if complete { 0 } else { next as u8 });
+        Ok(DeadlineFlush {
+            pages: next.saturating_sub(resume),
+            complete,
+        })
+    }
+
+    /// Send a whole page, unless it can be skipped as blank.
+    fn show_page(&mut self, page: usize) -> Result<(), Error<DC, RST, S>> {
+        let blank = self.page_is_blank(page);
+        let known_blank = self.blank_pages & (1 << page) != 0;
+        if blank && known_blank && self.flags.get(Flags::SKIP_BLANK_PAGES) {
+            self.dirty_pages &= !(1 << page);
+            return Ok(());
+        }
+        self.flush_page(page, 0..W)
+    }
+
+    /// Update the display, only sending the pages changed in the buffer
+    /// since they were last sent.
+    ///
+    /// Pages count as changed as soon as they are drawn to, even if the
+    /// drawing left them as they were. [`buffer_mut`](Self::buffer_mut) and
+    /// [`line_renderer`](Self::line_renderer) mark every page. After a reset
+    /// or a failed transfer, every page is sent.
+    pub fn show_dirty(&mut self) -> Result<(), Error<DC, RST, S>> {
+        self.begin_flush()?;
+        for i in 0..Self::GEOMETRY.pages() {
+            let page = self.page_at(i);
+            if self.dirty_pages & (1 << page) != 0 {
+                self.flush_page(page, 0..W)?;
+            }
+        }
+        self.end_flush()
+    }
+
+    /// `true` when pages were drawn to since they were last sent, see
+    /// [`show_dirty`](Self::show_dirty).
+    pub fn is_dirty(&self) -> bool {
+        self.dirty_pages & Self::ALL_PAGES != 0
+    }
+
+    /// Write `data` straight to the controller RAM, in `page` from `column`
+    /// on, bypassing the framebuffer.
+    ///
+    /// This is how bufferless builds draw, see [`NoBuffer`]: the application
+    /// renders a page, e.g. a line of text, into a small stack array and
+    /// writes it out. `data` is in the [`buffer`](Self::buffer) layout, one
+    /// byte per column, and does not follow the [`Rotation`]. Returns
+    /// [`Error::InvalidArgument`] and sends nothing if `data` does not fit
+    /// in the page.
+    pub fn write_page(
+        &mut self,
+        page: usize,
+        column: usize,
+        data: &[u8],
+    ) -> Result<(), Error<DC, RST, S>> {
+        let end = column.saturating_add(data.len());
+        if page >= Self::GEOMETRY.pages() || end > W {
+            return Err(Error::InvalidArgument);
+        }
+        self.begin_flush()?;
+        // The page no longer matches the buffer
+        let page_bit = 1 << page;
+        self.blank_pages &= !page_bit;
+        self.dirty_pages |= page_bit;
+        #[cfg(feature = "shadow")]
+        {
+            self.shadow_valid &= !page_bit;
+        }
+        self.set_address(page, column)?;
+        self.raw_data(data)?;
+        self.end_flush()
+    }
+
+    /// Update the ST7567 display, only sending what differs from the
+    /// controller RAM shadow copy.
+    ///
+    /// Within each page, only the span of changed columns is transferred.
+    /// Pages whose controller content is unknown (after a reset or a failed
+    /// transfer) are sent in full.
+    #[cfg(feature = "shadow")]
+    pub fn show_changed(&mut self) -> Result<(), Error<DC, RST, S>> {
+        self.begin_flush()?;
+        for i in 0..Self::GEOMETRY.pages() {
+            let page = self.page_at(i);
+            if let Some(columns) = self.changed_columns(page) {
+                self.flush_page(page, columns)?;
+            }
+        }
+        self.end_flush()
+    }
+
+    /// `true` when the controller RAM is believed to match the buffer.
+    ///
+    /// The ST7567 cannot be read back over SPI, so this relies on the shadow
+    /// copy updated by every successful page transfer. Always `false` for
+    /// frames larger than the 1KB shadow copy.
+    #[cfg(feature = "shadow")]
+    pub fn verify_frame(&self) -> bool {
+        let size = buffer_size(W, H);
+        self.shadow_valid == Self::ALL_PAGES
+            && self.buf.as_bytes().get(..size) == self.shadow.as_slice().get(..size)
+    }
+
+    /// Position of the `i`th page to send, according to the page order.
+    fn page_at(&self, i: usize) -> usize {
+        match self.page_order {
+            PageOrder::TopToBottom => i,
+            PageOrder::BottomToTop => Self::GEOMETRY.pages().saturating_sub(i).saturating_sub(1),
+        }
+    }
+
+    fn page_is_blank(&self, page: usize) -> bool {
+        self.buf
+            .as_bytes()
+            .get(Self::GEOMETRY.page_bytes(page, 0..W))
+            .unwrap_or_default()
+            .iter()
+            .all(|&b| b == 0)
+    }
+
+    /// Span of columns of `page` that differ from the shadow copy.
+    #[cfg(feature = "shadow")]
+    fn changed_columns(&self, page: usize) -> Option<Range<usize>> {
+        if self.shadow_valid & (1 << page) == 0 {
+            return Some(0..W);
+        }
+        let bytes = Self::GEOMETRY.page_bytes(page, 0..W);
+        let current = self.buf.as_bytes().get(bytes.clone())?;
+        let shadow = self.shadow.as_slice().get(bytes)?;
+        let differs = |(a, b): (&u8, &u8)| a != b;
+        let first = current.iter().zip(shadow).position(differs)?;
+        let last = current.iter().zip(shadow).rposition(differs)?;
+        Some(first..last.saturating_add(1))
+    }
+
+    /// Forget anything known about the controller RAM content.
+    fn forget_controller_ram(&mut self) {
+        self.blank_pages = 0;
+        self.dirty_pages = u8::MAX;
+        #[cfg(feature = "shadow")]
+        {
+            self.shadow_valid = 0;
+        }
+    }
+
+    fn begin_flush(&mut self) -> Result<(), Error<DC, RST, S>> {
+        if !self.flags.get(Flags::INITIALIZED) {
+            return Err(Error::NotInitialized);
+        }
+        if self.flags.get(Flags::RMW_PENDING) {
+            self.recover()?;
+        }
+        self.flags.set(Flags::RMW_PENDING, true);
+        self.send_commands(&[Command::EnterReadModifyWrite])
+    }
+
+    fn end_flush(&mut self) -> Result<(), Error<DC, RST, S>> {
+        self.send_commands(&[Command::ExitReadModifyWrite])?;
+        self.flags.set(Flags::RMW_PENDING, false);
+        Ok(())
+    }
+
+    /// Point the controller at `column` of `page`, in panel columns.
+    fn set_address(&mut self, page: usize, column: usize) -> Result<(), Error<DC, RST, S>> {
+        // The RAM is 132 columns wide: with reversed segments, the visible
+        // columns are mirrored to the other end
+        let ram_offset = if self.segment_reversed() {
+            Self::SPARE_COLUMNS.saturating_sub(self.column_offset)
+        } else {
+            self.column_offset
+        };
+        let column = column.saturating_add(usize::from(ram_offset)) as u8;
+        let page = page.saturating_add(usize::from(self.pages.start())) & 7;
+        self.send_commands(&[Command::SetPage(page as u8), Command::SetColumn(column)])
+    }
+
+    /// Send `columns` of `page` and keep track of the controller RAM content.
+    fn flush_page(&mut self, page: usize, columns: Range<usize>) -> Result<(), Error<DC, RST, S>> {
+        let page_bit = 1 << page;
+        let full_page = columns == (0..W);
+        #[cfg(feature = "shadow")]
+        let known = full_page || self.shadow_valid & page_bit != 0;
+        #[cfg(not(feature = "shadow"))]
+        let known = full_page;
+
+        // The page content is unknown until the transfer completes
+        self.blank_pages &= !page_bit;
+        #[cfg(feature = "shadow")]
+        {
+            self.shadow_valid &= !page_bit;
+        }
+
+        self.set_address(page, columns.start)?;
+        let range = Self::GEOMETRY.page_bytes(page, columns);
+        if let Err(error) = self.data(range.clone()) {
+            self.dirty_pages |= page_bit;
+            return Err(error);
+        }
+
+        if full_page {
+            self.dirty_pages &= !page_bit;
+        }
+        if !known {
+            return Ok(());
+        }
+        if self.page_is_blank(page) {
+            self.blank_pages |= page_bit;
+        }
+        #[cfg(feature = "shadow")]
+        {
+            let sent = self.buf.as_bytes().get(range.clone());
+            if let (Some(shadow), Some(sent)) = (self.shadow.get_mut(range), sent) {
+                shadow.copy_from_slice(sent);
+                self.shadow_valid |= page_bit;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum MockError {
+        SpiError,
+        PinError,
+    }
+
+    impl std::error::Error for MockError {}
+
+    impl fmt::Display for MockError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                MockError::SpiError => write!(f, "Mock SPI Error"),
+                MockError::PinError => write!(f, "Mock Pin Error"),
+            }
+        }
+    }
+
+    impl embedded_hal::spi::Error for MockError {
+        fn kind(&self) -> embedded_hal::spi::ErrorKind {
+            embedded_hal::spi::ErrorKind::Other
+        }
+    }
+
+    /// Mock Pin implementation for testing
+    #[derive(Debug)]
+    pub struct MockPin {
+        pub states: RefCell<Vec<PinState>>,
+        pub should_fail: RefCell<bool>,
+    }
+
+    impl MockPin {
+        pub fn new() -> Self {
+            Self {
+                states: RefCell::new(Vec::new()),
+                should_fail: RefCell::new(false),
+            }
+        }
+
+        pub fn set_fail(&self, fail: bool) {
+            *self.should_fail.borrow_mut() = fail;
+        }
+
+        pub fn get_states(&self) -> Vec<PinState> {
+            self.states.borrow().clone()
+        }
+
+        pub fn clear_states(&self) {
+            self.states.borrow_mut().clear();
+        }
+    }
+
+    impl Pin for MockPin {
+        type Error = MockError;
+
+        fn set_value(&mut self, pin_state: PinState) -> Result<(), Self::Error> {
+            if *self.should_fail.borrow() {
+                return Err(MockError::PinError);
+            }
+            self.states.borrow_mut().push(pin_state);
+            Ok(())
+        }
+    }
+
+    /// Mock SPI Device implementation for testing
+    #[derive(Debug)]
+    pub struct MockSpiDevice {
+        pub written_data: RefCell<Vec<u8>>,
+        pub should_fail: RefCell<bool>,
+        pub fail_after: RefCell<Option<usize>>,
+    }
+
+    impl MockSpiDevice {
+        pub fn new() -> Self {
+            Self {
+                written_data: RefCell::new(Vec::new()),
+                should_fail: RefCell::new(false),
+                fail_after: RefCell::new(None),
+            }
+        }
+
+        pub fn set_fail(&self, fail: bool) {
+            *self.should_fail.borrow_mut() = fail;
+        }
+
+        /// Fail every transaction once `count` more have succeeded
+        pub fn set_fail_after(&self, count: usize) {
+            *self.fail_after.borrow_mut() = Some(count);
+        }
+
+        pub fn get_written_data(&self) -> Vec<u8> {
+            self.written_data.borrow().clone()
+        }
+
+        pub fn clear_written_data(&self) {
+            self.written_data.borrow_mut().clear();
+        }
+    }
+
+    impl embedded_hal::spi::ErrorType for MockSpiDevice {
+        type Error = MockError;
+    }
+
+    impl embedded_hal::spi::SpiDevice for MockSpiDevice {
+        #[allow(clippy::single_match)]
+        fn transaction(
+            &mut self,
+            operations: &mut [embedded_hal::spi::Operation<'_, u8>],
+        ) -> Result<(), Self::Error> {
+            if *self.should_fail.borrow() {
+                return Err(MockError::SpiError);
+            }
+            if let Some(remaining) = self.fail_after.borrow_mut().as_mut() {
+                if *remaining == 0 {
+                    return Err(MockError::SpiError);
+                }
+                *remaining -= 1;
+            }
+
+            for operation in operations {
+                match operation {
+                    embedded_hal::spi::Operation::Write(data) => {
+                        self.written_data.borrow_mut().extend_from_slice(data);
+                    }
+                    _ => {} // We only care about write operations for this driver
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// Mock delay recording the requested waits, without sleeping
+    #[derive(Debug)]
+    pub struct MockDelay {
+        pub delays_ns: Vec<u32>,
+    }
+
+    impl MockDelay {
+        pub fn new() -> Self {
+            Self {
+                delays_ns: Vec::new(),
+            }
+        }
+
+        pub fn total_ms(&self) -> u64 {
+            self.delays_ns.iter().map(|&ns| u64::from(ns)).sum::<u64>() / 1_000_000
+        }
+    }
+
+    impl DelayNs for MockDelay {
+        fn delay_ns(&mut self, ns: u32) {
+            self.delays_ns.push(ns);
+        }
+    }
+
+    // Helper to create a test ST7567 instance
+    pub fn create_test_st7567() -> ST7567<MockPin, MockPin, MockSpiDevice> {
+        let spi = MockSpiDevice::new();
+        let dc_pin = MockPin::new();
+        let rst_pin = MockPin::new();
+        let mut st7567 = ST7567::new(spi, dc_pin, rst_pin);
+        // Start as if init() had been sent, so tests see only their own
+        // traffic
+        st7567.flags.set(Flags::INITIALIZED, true);
+        st7567
+    }
+
+    #[test]
+    fn test_new() {
+        let st7567 = create_test_st7567();
+        // Buffer should be initialized to all zeros
+        assert_eq!(st7567.buf, [0; BUFFER_SIZE]);
+    }
+
+    #[test]
+    fn test_spi_config() {
+        let config = SpiConfig::default();
+        assert_eq!(config.check(), Ok(()));
+        let mode_3 = SpiConfig {
+            mode: embedded_hal::spi::MODE_3,
+            speed_hz: SPI_MAX_SPEED_HZ,
+        };
+        assert_eq!(mode_3.check(), Ok(()));
+        let mode_1 = SpiConfig {
+            mode: embedded_hal::spi::MODE_1,
+            ..config
+        };
+        assert_eq!(
+            mode_1.check(),
+            Err(SpiConfigError::Mode(embedded_hal::spi::MODE_1))
+        );
+        for speed_hz in [0, SPI_MAX_SPEED_HZ + 1] {
+            let config = SpiConfig { speed_hz, ..config };
+            assert_eq!(config.check(), Err(SpiConfigError::Speed(speed_hz)));
+        }
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut st7567 = create_test_st7567();
+        // Set some pixels first
+        st7567.set_pixel(10, 20, true);
+        st7567.set_pixel(50, 30, true);
+        
+        // Clear should reset all pixels
+        st7567.clear();
+        assert_eq!(st7567.buf, [0; BUFFER_SIZE]);
+    }
+
+    #[test]
+    fn test_fill_and_clear_region() {
+        let mut st7567 = create_test_st7567();
+        st7567.fill(true);
+        assert_eq!(st7567.buf, [0xff; BUFFER_SIZE]);
+
+        // Rows 6 to 17: the end of page 0, all of page 1, the top of page 2
+        st7567.clear_region(3, 6, 2, 12);
+        assert_eq!(st7567.buf[3..6], [0x3f, 0x3f, 0xff]);
+        assert_eq!(st7567.buf[128 + 2..128 + 6], [0xff, 0x00, 0x00, 0xff]);
+        assert_eq!(st7567.buf[256 + 3..256 + 5], [0xfc, 0xfc]);
+        for x in 2..6 {
+            for y in 5..19 {
+                let inside = (3..5).contains(&x) && (6..18).contains(&y);
+                assert_eq!(st7567.get_pixel(x, y), !inside);
+            }
+        }
+
+        // Clipped to the screen
+        st7567.clear();
+        st7567.fill_region(120, 60, usize::MAX, usize::MAX, true);
+        assert_eq!(st7567.buffer_stats().lit_pixels, 8 * 4);
+        st7567.clear_region(10, 0, usize::MAX, 8);
+        assert_eq!(st7567.buffer_stats().lit_pixels, 8 * 4);
+        st7567.clear();
+        st7567.fill_region(120, 60, 100, 100, true);
+        assert_eq!(st7567.buffer_stats().lit_pixels, 8 * 4);
+        st7567.fill_region(128, 0, 10, 10, true);
+        st7567.fill_region(0, 0, 0, 10, true);
+        assert_eq!(st7567.buffer_stats().lit_pixels, 8 * 4);
+
+        // Follows the rotation
+        st7567.clear();
+        assert!(st7567.set_rotation(Rotation::Rotate90).is_ok());
+        st7567.fill_region(0, 0, 3, 2, true);
+        for (x, y) in [(0, 0), (2, 0), (0, 1), (2, 1)] {
+            assert!(st7567.get_pixel(x, y));
+        }
+        assert_eq!(st7567.buffer_stats().lit_pixels, 6);
+        assert_eq!(st7567.buf[126..128], [0x07, 0x07]);
     }
 
     #[test]
+    fn test_buffer_access() {
+        let mut st7567 = create_test_st7567();
+        assert_eq!(st7567.buffer().len(), BUFFER_SIZE);
+        // Column 3, rows 8 and 15
+        st7567.buffer_mut()[WIDTH as usize + 3] = 0x81;
+        assert!(st7567.get_pixel(3, 8) && st7567.get_pixel(3, 15));
+        assert_eq!(st7567.buffer_stats().lit_pixels, 2);
+        st7567.set_pixel(0, 0, true);
+        assert_eq!(st7567.buffer()[0], 0x01);
+    }
+
+    #[test]
+    fn test_set_buffer() {
+        let mut st7567 = create_test_st7567();
+        let mut frame = [0; BUFFER_SIZE];
+        frame[WIDTH as usize + 3] = 0x81;
+        st7567.set_buffer(&frame);
+        assert!(st7567.get_pixel(3, 8) && st7567.get_pixel(3, 15));
+        assert_eq!(st7567.buffer_stats().lit_pixels, 2);
+
+        assert!(st7567.try_set_buffer(&[0xff; BUFFER_SIZE]).is_ok());
+        assert_eq!(st7567.buf, [0xff; BUFFER_SIZE]);
+        assert!(matches!(
+            st7567.try_set_buffer(&frame[1..]),
+            Err(Error::InvalidArgument)
+        ));
+        assert!(st7567.try_set_buffer(&[0; BUFFER_SIZE + 1]).is_err());
+        assert_eq!(st7567.buf, [0xff; BUFFER_SIZE]);
+    }
+
+    #[test]
+    #[allow(clippy::erasing_op, clippy::identity_op)]
     fn test_set_pixel_valid_coordinates() {
         let mut st7567 = create_test_st7567();
         
@@ -355,119 +2169,763 @@ mod tests {
     }
 
     #[test]
-    fn test_set_pixel_invalid_coordinates() {
-        let mut st7567 = create_test_st7567();
-        
-        // Test coordinates out of bounds - should be ignored
-        st7567.set_pixel(WIDTH as usize, HEIGHT as usize, true);
-        st7567.set_pixel(200, 100, true);
-        
-        // Buffer should remain empty
-        assert_eq!(st7567.buf, [0; BUFFER_SIZE]);
+    fn test_buffer_stats() {
+        let mut st7567 = create_test_st7567();
+        assert!(st7567.buffer_stats().is_blank());
+        st7567.set_pixel(3, 20, true);
+        let stats = st7567.buffer_stats();
+        assert_eq!(stats.lit_pixels, 1);
+        assert_eq!(stats.page_lit_pixels[2], 1);
+        assert_eq!(
+            stats.bounding_box,
+            Some(BoundingBox {
+                x: 3,
+                y: 20,
+                width: 1,
+                height: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_set_pixel_invalid_coordinates() {
+        let mut st7567 = create_test_st7567();
+        
+        // Test coordinates out of bounds - should be ignored
+        st7567.set_pixel(WIDTH as usize, HEIGHT as usize, true);
+        st7567.set_pixel(200, 100, true);
+        
+        // Buffer should remain empty
+        assert_eq!(st7567.buf, [0; BUFFER_SIZE]);
+    }
+
+    #[test]
+    #[allow(clippy::erasing_op, clippy::identity_op)]
+    fn test_set_pixel_multiple_bits_same_byte() {
+        let mut st7567 = create_test_st7567();
+        
+        // Set multiple pixels in the same byte (same x, different y within 8-pixel boundary)
+        st7567.set_pixel(10, 0, true);  // bit 0
+        st7567.set_pixel(10, 1, true);  // bit 1
+        st7567.set_pixel(10, 2, true);  // bit 2
+        
+        let offset = ((0 / 8) * WIDTH as usize) + 10;
+        let expected = (1 << 0) | (1 << 1) | (1 << 2);
+        assert_eq!(st7567.buf[offset], expected);
+    }
+
+    #[test]
+    fn test_set_pixel_never_panics() {
+        let mut st7567 = create_test_st7567();
+        let edges = [
+            0,
+            1,
+            7,
+            8,
+            HEIGHT as usize - 1,
+            HEIGHT as usize,
+            WIDTH as usize - 1,
+            WIDTH as usize,
+            BUFFER_SIZE,
+            BUFFER_SIZE * 8,
+            usize::MAX / 8,
+            usize::MAX - 1,
+            usize::MAX,
+        ];
+        for &x in edges.iter() {
+            for &y in edges.iter() {
+                st7567.set_pixel(x, y, true);
+                st7567.set_pixel(x, y, false);
+            }
+        }
+        assert_eq!(st7567.buf, [0; BUFFER_SIZE]);
+        // Any buffer content can be flushed
+        st7567.buf = [0xff; BUFFER_SIZE];
+        assert!(st7567.show().is_ok());
+    }
+
+    #[test]
+    fn test_areas_never_panic() {
+        let edges = [0, 1, 8, 63, 64, 128, usize::MAX - 1, usize::MAX];
+        for &rotation in [Rotation::Rotate0, Rotation::Rotate90].iter() {
+            let mut st7567 = create_test_st7567();
+            assert!(st7567.set_rotation(rotation).is_ok());
+            for &x in edges.iter() {
+                for &y in edges.iter() {
+                    for &width in edges.iter() {
+                        for &height in edges.iter() {
+                            st7567.fill_region(x, y, width, height, true);
+                            st7567.clear_region(x, y, width, height);
+                            #[cfg(any(feature = "region", feature = "strip-chart"))]
+                            {
+                                let area = BoundingBox {
+                                    x,
+                                    y,
+                                    width,
+                                    height,
+                                };
+                                assert!(st7567.show_area(area).is_ok());
+                                st7567.spi.clear_written_data();
+                            }
+                        }
+                    }
+                }
+            }
+            assert_eq!(st7567.buf, [0; BUFFER_SIZE]);
+        }
+    }
+
+    #[test]
+    fn test_reset_success() {
+        let mut st7567 = create_test_st7567();
+        
+        let mut delay = MockDelay::new();
+        let result = st7567.reset(&mut delay);
+        assert!(result.is_ok());
+        
+        // Check that reset pin was toggled correctly
+        let rst_states = st7567.rst_pin.as_ref().unwrap().get_states();
+        assert_eq!(rst_states.len(), 2);
+        assert!(matches!(rst_states[0], PinState::Low));
+        assert!(matches!(rst_states[1], PinState::High));
+
+        // 10ms pulse followed by 100ms of settle time
+        assert_eq!(delay.total_ms(), 110);
+    }
+
+    #[test]
+    fn test_reset_pin_error() {
+        let mut st7567 = create_test_st7567();
+        st7567.rst_pin.as_ref().unwrap().set_fail(true);
+        
+        let result = st7567.reset(&mut MockDelay::new());
+        assert!(result.is_err());
+        assert!(matches!(result.unwrap_err(), Error::ResetPinError(_)));
+    }
+
+    #[test]
+    fn test_reset_without_reset_pin() {
+        let mut st7567 = ST7567::new_without_reset(MockSpiDevice::new(), MockPin::new());
+        let mut delay = MockDelay::new();
+        assert!(st7567.reset(&mut delay).is_ok());
+        assert_eq!(st7567.spi.get_written_data(), vec![ST7567_EXIT_SOFTRST]);
+        assert_eq!(st7567.dc_pin.get_states(), vec![PinState::Low]);
+        assert_eq!(delay.total_ms(), 100);
+    }
+
+    #[test]
+    fn test_software_reset() {
+        let mut st7567 = create_test_st7567();
+        let mut delay = MockDelay::new();
+        assert!(st7567.software_reset(&mut delay).is_ok());
+        assert_eq!(st7567.spi.get_written_data(), vec![ST7567_EXIT_SOFTRST]);
+        assert!(st7567.rst_pin.as_ref().unwrap().get_states().is_empty());
+        assert_eq!(delay.total_ms(), 100);
+
+        st7567.spi.set_fail(true);
+        assert!(matches!(
+            st7567.software_reset(&mut delay),
+            Err(Error::SpiError(_))
+        ));
+    }
+
+    #[test]
+    fn test_set_contrast_success() {
+        let mut st7567 = create_test_st7567();
+        
+        let result = st7567.set_contrast(128);
+        assert!(result.is_ok());
+        
+        // Check that correct command was sent
+        let written_data = st7567.spi.get_written_data();
+        // Wrapped to the 6 bits of the register
+        assert_eq!(written_data, vec![ST7567_SETCONTRAST, 0]);
+        
+        // Check that DC pin was set to Low for command
+        let dc_states = st7567.dc_pin.get_states();
+        assert_eq!(dc_states.len(), 1);
+        assert!(matches!(dc_states[0], PinState::Low));
+    }
+
+    #[test]
+    fn test_set_contrast_spi_error() {
+        let mut st7567 = create_test_st7567();
+        st7567.spi.set_fail(true);
+        
+        let result = st7567.set_contrast(128);
+        assert!(result.is_err());
+        assert!(matches!(result.unwrap_err(), Error::SpiError(_)));
+        // The panel did not get it, init() must not send it either
+        assert_eq!(st7567.contrast(), 40);
+    }
+
+    #[test]
+    fn test_set_contrast_pin_error() {
+        let mut st7567 = create_test_st7567();
+        st7567.dc_pin.set_fail(true);
+        
+        let result = st7567.set_contrast(128);
+        assert!(result.is_err());
+        assert!(matches!(result.unwrap_err(), Error::PinError(_)));
+    }
+
+    #[test]
+    #[allow(clippy::erasing_op, clippy::identity_op)]
+    fn test_init_success() {
+        let mut st7567 = create_test_st7567();
+        
+        let result = st7567.init();
+        assert!(result.is_ok());
+        
+        // Check that correct initialization sequence was sent
+        let written_data = st7567.spi.get_written_data();
+        let expected = vec![
+            ST7567_BIAS_1_7,
+            ST7567_SEG_DIR_NORMAL,
+            ST7567_SETCOMREVERSE,
+            ST7567_DISPNORMAL,
+            ST7567_DISPRAM,
+            ST7567_SETSTARTLINE | 0,
+            ST7567_POWERCTRL,
+            ST7567_REG_RATIO | 3,
+            ST7567_DISPON,
+            ST7567_SETCONTRAST,
+            40,
+        ];
+        assert_eq!(written_data, expected);
+        
+        // Check that DC pin was set to Low for command
+        let dc_states = st7567.dc_pin.get_states();
+        assert_eq!(dc_states.len(), 1);
+        assert!(matches!(dc_states[0], PinState::Low));
+    }
+
+    #[test]
+    fn test_set_inverted() {
+        let mut st7567 = create_test_st7567();
+        assert!(st7567.set_inverted(true).is_ok());
+        assert!(st7567.set_inverted(false).is_ok());
+        assert_eq!(
+            st7567.spi.get_written_data(),
+            vec![ST7567_DISPINVERSE, ST7567_DISPNORMAL]
+        );
+        assert_eq!(st7567.dc_pin.get_states(), vec![PinState::Low; 2]);
+    }
+
+    #[test]
+    fn test_state_getters() {
+        let mut st7567 = create_test_st7567();
+        assert!(st7567.is_initialized());
+        assert!(!st7567.is_inverted());
+        assert_eq!(st7567.contrast(), 40);
+        assert_eq!(st7567.regulation_ratio(), 3);
+        assert_eq!(st7567.bias(), Bias::OneSeventh);
+        assert_eq!(st7567.rotation(), Rotation::Rotate0);
+
+        assert!(st7567.set_night_mode(true).is_ok());
+        assert!(st7567.set_brightness(100).is_ok());
+        assert!(st7567.set_rotation(Rotation::Rotate90).is_ok());
+        assert!(st7567.is_inverted());
+        assert_eq!(st7567.contrast(), 63);
+        assert_eq!(st7567.regulation_ratio(), 6);
+        assert_eq!(st7567.rotation(), Rotation::Rotate90);
+
+        // A failed command leaves the cached state alone
+        st7567.spi.set_fail(true);
+        assert!(st7567.set_inverted(false).is_err());
+        assert!(st7567.is_inverted());
+        st7567.spi.set_fail(false);
+
+        assert!(st7567.software_reset(&mut MockDelay::new()).is_ok());
+        assert!(!st7567.is_initialized());
+        assert!(!st7567.is_inverted());
+        assert!(st7567.init().is_ok());
+        assert!(st7567.is_initialized());
+    }
+
+    #[test]
+    fn test_set_bias() {
+        let mut st7567 = create_test_st7567();
+        assert!(st7567.init().is_ok());
+        assert_eq!(st7567.spi.get_written_data()[0], ST7567_BIAS_1_7);
+
+        st7567.spi.clear_written_data();
+        assert!(st7567.set_bias(Bias::OneNinth).is_ok());
+        assert_eq!(st7567.spi.get_written_data(), vec![ST7567_BIAS_1_9]);
+
+        // init() keeps the selected bias
+        st7567.spi.clear_written_data();
+        assert!(st7567.init().is_ok());
+        assert_eq!(st7567.spi.get_written_data()[0], ST7567_BIAS_1_9);
+    }
+
+    #[test]
+    fn test_set_regulation_ratio() {
+        let mut st7567 = create_test_st7567();
+        assert!(st7567.set_regulation_ratio(5).is_ok());
+        assert_eq!(st7567.spi.get_written_data(), vec![ST7567_REG_RATIO | 5]);
+
+        st7567.spi.clear_written_data();
+        assert!(matches!(
+            st7567.set_regulation_ratio(8),
+            Err(Error::InvalidArgument)
+        ));
+        assert!(st7567.spi.get_written_data().is_empty());
+
+        // init() keeps the last valid ratio
+        assert!(st7567.init().is_ok());
+        assert!(st7567
+            .spi
+            .get_written_data()
+            .contains(&(ST7567_REG_RATIO | 5)));
+    }
+
+    #[test]
+    fn test_init_staged() {
+        let mut st7567 = create_test_st7567();
+        let mut delay = MockDelay::new();
+        assert!(st7567.init_staged(&mut delay).is_ok());
+        let written_data = st7567.spi.get_written_data();
+        assert_eq!(written_data[9..], [0x2c, 0x2e, 0x2f, ST7567_DISPON]);
+        assert!(written_data[..9].contains(&ST7567_SETCONTRAST));
+        assert!(written_data[..9].contains(&ST7567_DISPRAM));
+        assert_eq!(delay.total_ms(), 150);
+    }
+
+    #[cfg(feature = "unsafe_raw")]
+    #[test]
+    fn test_send_raw() {
+        let mut st7567 = create_test_st7567();
+        assert!(st7567
+            .send_command(&[ST7567_SETBOOSTER, ST7567_SETBOOSTER5X])
+            .is_ok());
+        assert!(st7567.send_data(&[0xaa, 0x55]).is_ok());
+        assert_eq!(
+            st7567.spi.get_written_data(),
+            vec![ST7567_SETBOOSTER, ST7567_SETBOOSTER5X, 0xaa, 0x55]
+        );
+        let dc_states = st7567.dc_pin.get_states();
+        assert!(matches!(dc_states[..], [PinState::Low, PinState::High]));
+    }
+
+    #[test]
+    fn test_display_on_off() {
+        let mut st7567 = create_test_st7567();
+        assert!(st7567.display_off().is_ok());
+        assert!(st7567.display_on().is_ok());
+        assert_eq!(
+            st7567.spi.get_written_data(),
+            vec![ST7567_DISPOFF, ST7567_DISPON]
+        );
+        // Still initialized, the buffer can be flushed right away
+        assert!(st7567.show().is_ok());
+    }
+
+    #[test]
+    fn test_range_policy() {
+        let mut st7567 = create_test_st7567();
+        assert!(st7567.set_contrast(64 + 6).is_ok());
+        assert!(st7567
+            .fade_contrast(64 + 7, 1, &mut MockDelay::new())
+            .is_ok());
+        assert_eq!(
+            st7567.spi.get_written_data(),
+            vec![ST7567_SETCONTRAST, 6, ST7567_SETCONTRAST, 7]
+        );
+        assert_eq!(st7567.contrast, 7);
+
+        st7567.spi.clear_written_data();
+        st7567.set_range_policy(RangePolicy::Saturate);
+        assert!(st7567.set_start_line(64 + 3).is_ok());
+        assert!(st7567.set_contrast(100).is_ok());
+        assert_eq!(
+            st7567.spi.get_written_data(),
+            vec![ST7567_SETSTARTLINE | 63, ST7567_SETCONTRAST, 63]
+        );
+
+        st7567.spi.clear_written_data();
+        st7567.set_range_policy(RangePolicy::Reject);
+        assert!(matches!(
+            st7567.set_start_line(64),
+            Err(Error::InvalidArgument)
+        ));
+        assert!(matches!(
+            st7567.set_contrast(64),
+            Err(Error::InvalidArgument)
+        ));
+        assert!(matches!(
+            st7567.scroll_animate(0, 64, 1, &mut MockDelay::new()),
+            Err(Error::InvalidArgument)
+        ));
+        assert!(st7567.spi.get_written_data().is_empty());
+        assert_eq!(st7567.contrast, 63);
+    }
+
+    #[test]
+    fn test_fade_contrast() {
+        let mut st7567 = create_test_st7567();
+        let mut delay = MockDelay::new();
+        assert!(st7567.fade_contrast(50, 4, &mut delay).is_ok());
+        assert!(st7567.fade_contrast(40, 0, &mut delay).is_ok());
+        assert!(st7567.fade_contrast(40, 4, &mut delay).is_ok());
+        let sent: Vec<u8> = st7567
+            .spi
+            .get_written_data()
+            .chunks(2)
+            .map(|command| command[1])
+            .collect();
+        assert_eq!(
+            sent,
+            vec![44, 48, 50, 49, 48, 47, 46, 45, 44, 43, 42, 41, 40]
+        );
+        assert_eq!(delay.total_ms(), 11 * 10);
+    }
+
+    #[test]
+    fn test_set_brightness() {
+        let mut st7567 = create_test_st7567();
+        for &percent in [0, 15, 35, 37, 100, 255].iter() {
+            assert!(st7567.set_brightness(percent).is_ok());
+        }
+        assert_eq!(
+            st7567.spi.get_written_data(),
+            vec![
+                ST7567_REG_RATIO,
+                ST7567_SETCONTRAST,
+                36,
+                ST7567_REG_RATIO | 1,
+                ST7567_SETCONTRAST,
+                49,
+                ST7567_REG_RATIO | 2,
+                ST7567_SETCONTRAST,
+                52,
+                ST7567_REG_RATIO | 2,
+                ST7567_SETCONTRAST,
+                54,
+                ST7567_REG_RATIO | 6,
+                ST7567_SETCONTRAST,
+                63,
+                ST7567_REG_RATIO | 6,
+                ST7567_SETCONTRAST,
+                63,
+            ]
+        );
+        assert_eq!((st7567.regulation_ratio, st7567.contrast), (6, 63));
+    }
+
+    #[test]
+    fn test_max_contrast() {
+        let mut st7567 = create_test_st7567();
+        st7567.set_max_contrast(30);
+        // Lowered for the next init(), nothing sent yet
+        assert_eq!(st7567.contrast(), 30);
+        assert!(st7567.spi.get_written_data().is_empty());
+
+        assert!(st7567.set_contrast(50).is_ok());
+        assert!(st7567.set_brightness(100).is_ok());
+        let mut delay = MockDelay::new();
+        assert!(st7567.fade_contrast(63, 10, &mut delay).is_ok());
+        assert!(st7567.set_contrast(20).is_ok());
+        assert_eq!(
+            st7567.spi.get_written_data(),
+            [
+                ST7567_SETCONTRAST,
+                30,
+                ST7567_REG_RATIO | 6,
+                ST7567_SETCONTRAST,
+                30,
+                ST7567_SETCONTRAST,
+                20,
+            ]
+        );
+
+        st7567.set_max_contrast(u8::MAX);
+        assert!(st7567.set_contrast(63).is_ok());
+        assert_eq!(st7567.contrast(), 63);
+    }
+
+    #[test]
+    fn test_show_requires_init() {
+        let mut st7567 = ST7567::new(MockSpiDevice::new(), MockPin::new(), MockPin::new());
+        assert!(matches!(st7567.show(), Err(Error::NotInitialized)));
+        assert!(st7567.spi.get_written_data().is_empty());
+        assert!(st7567.init().is_ok());
+        assert!(st7567.show().is_ok());
+
+        let mut delay = MockDelay::new();
+        assert!(st7567.reset(&mut delay).is_ok());
+        assert!(matches!(st7567.show(), Err(Error::NotInitialized)));
+        assert!(st7567.init_staged(&mut delay).is_ok());
+        assert!(st7567.power_off(&mut delay).is_ok());
+        assert!(matches!(st7567.show(), Err(Error::NotInitialized)));
     }
 
     #[test]
-    fn test_set_pixel_multiple_bits_same_byte() {
+    fn test_set_start_line() {
         let mut st7567 = create_test_st7567();
-        
-        // Set multiple pixels in the same byte (same x, different y within 8-pixel boundary)
-        st7567.set_pixel(10, 0, true);  // bit 0
-        st7567.set_pixel(10, 1, true);  // bit 1
-        st7567.set_pixel(10, 2, true);  // bit 2
-        
-        let offset = ((0 / 8) * WIDTH as usize) + 10;
-        let expected = (1 << 0) | (1 << 1) | (1 << 2);
-        assert_eq!(st7567.buf[offset], expected);
+        assert!(st7567.set_start_line(10).is_ok());
+        assert!(st7567.set_start_line(64 + 3).is_ok());
+        assert_eq!(
+            st7567.spi.get_written_data(),
+            vec![ST7567_SETSTARTLINE | 10, ST7567_SETSTARTLINE | 3]
+        );
     }
 
     #[test]
-    fn test_reset_success() {
+    fn test_scroll_animate() {
         let mut st7567 = create_test_st7567();
-        
-        let result = st7567.reset();
-        assert!(result.is_ok());
-        
-        // Check that reset pin was toggled correctly
-        let rst_states = st7567.rst_pin.get_states();
-        assert_eq!(rst_states.len(), 2);
-        assert!(matches!(rst_states[0], PinState::Low));
-        assert!(matches!(rst_states[1], PinState::High));
+        let mut delay = MockDelay::new();
+        assert!(st7567.scroll_animate(2, 5, 16, &mut delay).is_ok());
+        assert!(st7567.scroll_animate(64 + 1, 0, 16, &mut delay).is_ok());
+        assert_eq!(
+            st7567.spi.get_written_data(),
+            [2, 3, 4, 5, 1, 0]
+                .iter()
+                .map(|line| ST7567_SETSTARTLINE | line)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(delay.total_ms(), 4 * 16);
     }
 
     #[test]
-    fn test_reset_pin_error() {
+    fn test_set_segment_remap() {
         let mut st7567 = create_test_st7567();
-        st7567.rst_pin.set_fail(true);
-        
-        let result = st7567.reset();
-        assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), Error::PinError(_)));
+        assert!(st7567.set_segment_remap(true).is_ok());
+        assert!(st7567.init().is_ok());
+        assert!(st7567.set_segment_remap(false).is_ok());
+        let written_data = st7567.spi.get_written_data();
+        assert_eq!(written_data[0], ST7567_SEG_DIR_REV);
+        assert_eq!(written_data[2], ST7567_SEG_DIR_REV);
+        assert_eq!(written_data.last(), Some(&ST7567_SEG_DIR_NORMAL));
     }
 
     #[test]
-    fn test_set_contrast_success() {
+    fn test_set_column_offset() {
         let mut st7567 = create_test_st7567();
-        
-        let result = st7567.set_contrast(128);
-        assert!(result.is_ok());
-        
-        // Check that correct command was sent
+        assert!(matches!(
+            st7567.set_column_offset(5),
+            Err(Error::InvalidArgument)
+        ));
+        assert!(st7567.set_column_offset(2).is_ok());
+        assert!(st7567.show().is_ok());
         let written_data = st7567.spi.get_written_data();
-        assert_eq!(written_data, vec![ST7567_SETCONTRAST, 128]);
-        
-        // Check that DC pin was set to Low for command
-        let dc_states = st7567.dc_pin.get_states();
-        assert_eq!(dc_states.len(), 1);
-        assert!(matches!(dc_states[0], PinState::Low));
+        assert_eq!(written_data[1..4], [0xb0, 0x02, 0x10]);
+
+        st7567.spi.clear_written_data();
+        assert!(st7567.set_segment_remap(true).is_ok());
+        assert!(st7567.show().is_ok());
+        let written_data = st7567.spi.get_written_data();
+        assert_eq!(written_data[2..5], [0xb0, 0x02, 0x10]);
+
+        st7567.spi.clear_written_data();
+        assert!(st7567.set_column_offset(1).is_ok());
+        assert!(st7567.show().is_ok());
+        let written_data = st7567.spi.get_written_data();
+        assert_eq!(written_data[1..4], [0xb0, 0x03, 0x10]);
     }
 
     #[test]
-    fn test_set_contrast_spi_error() {
+    fn test_scroll_to_screen() {
+        let mut st7567: ST7567<MockPin, MockPin, MockSpiDevice, [u8; 512], 128, 32> =
+            ST7567::with_sized_buffer(
+                MockSpiDevice::new(),
+                MockPin::new(),
+                MockPin::new(),
+                [0; 512],
+            );
+        let mut delay = MockDelay::new();
+        assert!(st7567.init().is_ok());
+        assert!(st7567.show().is_ok());
+
+        // The next screen goes to the lower half of the RAM, then the start
+        // line walks down to it
+        st7567.set_pixel(0, 0, true);
+        st7567.spi.clear_written_data();
+        assert!(st7567.scroll_to_screen(5, &mut delay).is_ok());
+        let written_data = st7567.spi.get_written_data();
+        let flush = 1 + 4 * (3 + 128) + 1;
+        assert_eq!(written_data[1], ST7567_SETPAGESTART | 4);
+        assert_eq!(written_data[4], 0x01);
+        let lines: Vec<u8> = (1..=32).map(|line| ST7567_SETSTARTLINE | line).collect();
+        assert_eq!(written_data[flush..], lines[..]);
+        assert_eq!(delay.total_ms(), 32 * 5);
+
+        // Flushes keep going there, and the next scroll wraps around
+        st7567.spi.clear_written_data();
+        assert!(st7567.show().is_ok());
+        assert_eq!(st7567.spi.get_written_data()[1], ST7567_SETPAGESTART | 4);
+        st7567.spi.clear_written_data();
+        assert!(st7567.scroll_to_screen(0, &mut delay).is_ok());
+        let written_data = st7567.spi.get_written_data();
+        assert_eq!(written_data[1], ST7567_SETPAGESTART);
+        assert_eq!(written_data.last(), Some(&ST7567_SETSTARTLINE));
+
+        // init() brings the frame back to the top
+        assert!(st7567.scroll_to_screen(0, &mut delay).is_ok());
+        assert!(st7567.init().is_ok());
+        st7567.spi.clear_written_data();
+        assert!(st7567.show().is_ok());
+        assert_eq!(st7567.spi.get_written_data()[1], ST7567_SETPAGESTART);
+
+        // A full height frame has no room off screen
         let mut st7567 = create_test_st7567();
-        st7567.spi.set_fail(true);
-        
-        let result = st7567.set_contrast(128);
-        assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), Error::SpiError(_)));
+        assert!(matches!(
+            st7567.scroll_to_screen(0, &mut delay),
+            Err(Error::InvalidArgument)
+        ));
     }
 
     #[test]
-    fn test_set_contrast_pin_error() {
+    fn test_panel_sizes() {
+        type Panel<const W: usize, const H: usize, const N: usize> =
+            ST7567<MockPin, MockPin, MockSpiDevice, [u8; N], W, H>;
+
+        let mut st7567: Panel<128, 32, { buffer_size(128, 32) }> = ST7567::with_sized_buffer(
+            MockSpiDevice::new(),
+            MockPin::new(),
+            MockPin::new(),
+            [0; 512],
+        );
+        assert!(st7567.init().is_ok());
+        assert_eq!(st7567.dimensions(), (128, 32));
+        st7567.set_pixel(127, 31, true);
+        st7567.set_pixel(0, 32, true);
+        assert_eq!(st7567.buffer_stats().lit_pixels, 1);
+        assert_eq!(st7567.buf[3 * 128 + 127], 0x80);
+        assert!(PixelBuffer::get(&st7567.buf, 127, 31));
+
+        let mut splash = [0u8; buffer_size(128, 32)];
+        splash[511] = 0x80;
+        st7567.set_sized_buffer(&splash);
+        assert_eq!(st7567.buffer(), splash);
+        st7567.set_pixel(127, 31, true);
+
+        st7567.spi.clear_written_data();
+        st7567.set_page_order(PageOrder::BottomToTop);
+        assert!(st7567.show().is_ok());
+        // RMW, 4 pages of 3 address bytes and 128 data bytes, end RMW
+        let written_data = st7567.spi.get_written_data();
+        assert_eq!(written_data.len(), 1 + 4 * (3 + 128) + 1);
+        assert_eq!(written_data[1], ST7567_SETPAGESTART | 3);
+
+        let mut st7567: Panel<132, 64, { buffer_size(132, 64) }> = ST7567::with_sized_buffer(
+            MockSpiDevice::new(),
+            MockPin::new(),
+            MockPin::new(),
+            [0; 1056],
+        );
+        assert!(st7567.init().is_ok());
+        assert!(matches!(
+            st7567.set_column_offset(1),
+            Err(Error::InvalidArgument)
+        ));
+        st7567.set_pixel(131, 63, true);
+        assert_eq!(st7567.buf[1055], 0x80);
+
+        // No spare column to skip once segments are reversed
+        assert!(st7567.set_segment_remap(true).is_ok());
+        st7567.spi.clear_written_data();
+        assert!(st7567.show().is_ok());
+        let written_data = st7567.spi.get_written_data();
+        assert_eq!(written_data.len(), 1 + 8 * (3 + 132) + 1);
+        assert_eq!(written_data[1..4], [0xb0, 0x00, 0x10]);
+        assert_eq!(written_data[written_data.len() - 2], 0x80);
+    }
+
+    #[test]
+    fn test_set_com_reverse() {
         let mut st7567 = create_test_st7567();
-        st7567.dc_pin.set_fail(true);
-        
-        let result = st7567.set_contrast(128);
-        assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), Error::PinError(_)));
+        assert!(st7567.set_com_reverse(false).is_ok());
+        assert!(st7567.init().is_ok());
+        assert!(st7567.set_com_reverse(true).is_ok());
+        let written_data = st7567.spi.get_written_data();
+        assert_eq!(written_data[0], ST7567_SETCOMNORMAL);
+        assert_eq!(written_data[3], ST7567_SETCOMNORMAL);
+        assert_eq!(written_data.last(), Some(&ST7567_SETCOMREVERSE));
     }
 
     #[test]
-    fn test_init_success() {
+    fn test_rotation() {
         let mut st7567 = create_test_st7567();
-        
-        let result = st7567.init();
-        assert!(result.is_ok());
-        
-        // Check that correct initialization sequence was sent
+        assert_eq!(st7567.dimensions(), (128, 64));
+
+        assert!(st7567.set_rotation(Rotation::Rotate90).is_ok());
+        assert_eq!(st7567.dimensions(), (64, 128));
+        st7567.set_pixel(0, 0, true);
+        st7567.set_pixel(63, 127, true);
+        st7567.set_pixel(64, 0, true);
+        assert!(st7567.buf.get(127, 0));
+        assert!(st7567.buf.get(0, 63));
+        assert_eq!(st7567.buffer_stats().lit_pixels, 2);
+        assert!(st7567.get_pixel(0, 0));
+        assert!(st7567.get_pixel(63, 127));
+        assert!(!st7567.get_pixel(0, 127));
+        assert!(!st7567.get_pixel(64, 0));
+
+        st7567.clear();
+        assert!(st7567.set_rotation(Rotation::Rotate270).is_ok());
+        st7567.set_pixel(0, 0, true);
+        assert!(st7567.buf.get(0, 63));
+
+        // 180° only flips the scan directions
+        st7567.clear();
+        st7567.spi.clear_written_data();
+        assert!(st7567.set_rotation(Rotation::Rotate180).is_ok());
+        st7567.set_pixel(1, 2, true);
+        assert!(st7567.buf.get(1, 2));
+        assert!(st7567.show().is_ok());
         let written_data = st7567.spi.get_written_data();
-        let expected = vec![
-            ST7567_BIAS_1_7,
-            ST7567_SEG_DIR_NORMAL,
-            ST7567_SETCOMREVERSE,
-            ST7567_DISPNORMAL,
-            ST7567_SETSTARTLINE | 0,
-            ST7567_POWERCTRL,
-            ST7567_REG_RATIO | 3,
-            ST7567_DISPON,
-            ST7567_SETCONTRAST,
-            40,
-        ];
-        assert_eq!(written_data, expected);
-        
-        // Check that DC pin was set to Low for command
-        let dc_states = st7567.dc_pin.get_states();
-        assert_eq!(dc_states.len(), 1);
-        assert!(matches!(dc_states[0], PinState::Low));
+        assert_eq!(written_data[..2], [ST7567_SEG_DIR_REV, ST7567_SETCOMNORMAL]);
+        // The visible columns are at the end of the RAM
+        assert_eq!(
+            written_data[3..6],
+            [ST7567_SETPAGESTART, ST7567_SETCOLL | 4, ST7567_SETCOLH]
+        );
+
+        st7567.spi.clear_written_data();
+        assert!(st7567.init().is_ok());
+        let written_data = st7567.spi.get_written_data();
+        assert_eq!(
+            written_data[1..3],
+            [ST7567_SEG_DIR_REV, ST7567_SETCOMNORMAL]
+        );
+    }
+
+    #[test]
+    fn test_set_all_pixels_on() {
+        let mut st7567 = create_test_st7567();
+        assert!(st7567.set_all_pixels_on(true).is_ok());
+        assert!(st7567.set_all_pixels_on(false).is_ok());
+        assert_eq!(
+            st7567.spi.get_written_data(),
+            vec![ST7567_DISPENTIRE, ST7567_DISPRAM]
+        );
+    }
+
+    #[test]
+    fn test_set_night_mode() {
+        let mut st7567 = create_test_st7567();
+        st7567.set_pixel(1, 1, true);
+        assert!(st7567.set_night_mode(true).is_ok());
+        assert!(st7567.set_night_mode(false).is_ok());
+        assert_eq!(
+            st7567.spi.get_written_data(),
+            vec![ST7567_DISPINVERSE, ST7567_DISPNORMAL]
+        );
+        // Framebuffer is not modified
+        assert_eq!(st7567.buf[1], 0b10);
+    }
+
+    #[test]
+    fn test_power_off() {
+        let mut st7567 = create_test_st7567();
+        let mut delay = MockDelay::new();
+        assert!(st7567.power_off(&mut delay).is_ok());
+        assert_eq!(
+            st7567.spi.get_written_data(),
+            vec![ST7567_DISPOFF, ST7567_POWERCTRL_OFF, ST7567_DISPENTIRE]
+        );
+        assert_eq!(delay.total_ms(), 100);
     }
 
     #[test]
@@ -482,7 +2940,9 @@ mod tests {
         
         // Test data - should set DC pin high
         st7567.dc_pin.clear_states();
-        let _ = st7567.data(&[0x03, 0x04]);
+        st7567.buf[0] = 0x03;
+        st7567.buf[1] = 0x04;
+        let _ = st7567.data(0..2);
         let dc_states = st7567.dc_pin.get_states();
         assert_eq!(dc_states.len(), 1);
         assert!(matches!(dc_states[0], PinState::High));
@@ -520,21 +2980,372 @@ mod tests {
         assert!(written_data.len() > 1000); // Should be substantial amount of data
     }
 
+    #[test]
+    fn test_show_page_order() {
+        let mut st7567 = create_test_st7567();
+        // Mark each page with its own number
+        for page in 0..8 {
+            st7567.buf[page * ST7567_PAGESIZE as usize] = page as u8;
+        }
+
+        let sent_pages = |data: &[u8]| -> Vec<(u8, u8)> {
+            // enter RMW, then per page: 3 command bytes + 128 data bytes
+            data[1..data.len() - 1]
+                .chunks(3 + ST7567_PAGESIZE as usize)
+                .map(|chunk| (chunk[0] & !ST7567_PAGESTART_MASK, chunk[0] & 7, chunk[3]))
+                .map(|(cmd, page, first)| {
+                    assert_eq!(cmd, ST7567_SETPAGESTART);
+                    (page, first)
+                })
+                .collect()
+        };
+
+        assert!(st7567.show().is_ok());
+        let expected: Vec<(u8, u8)> = (0..8).map(|p| (p, p)).collect();
+        assert_eq!(sent_pages(&st7567.spi.get_written_data()), expected);
+
+        st7567.set_page_order(PageOrder::BottomToTop);
+        st7567.spi.clear_written_data();
+        assert!(st7567.show().is_ok());
+        let expected: Vec<(u8, u8)> = (0..8).rev().map(|p| (p, p)).collect();
+        assert_eq!(sent_pages(&st7567.spi.get_written_data()), expected);
+    }
+
+    #[test]
+    fn test_show_with_deadline() {
+        let mut st7567 = create_test_st7567();
+        let mut budget = 3;
+        let mut deadline_passed = || {
+            if budget == 0 {
+                return true;
+            }
+            budget -= 1;
+            false
+        };
+        let flushed = st7567.show_with_deadline(&mut deadline_passed).unwrap();
+        assert_eq!(
+            flushed,
+            DeadlineFlush {
+                pages: 3,
+                complete: false
+            }
+        );
+        let written_data = st7567.spi.get_written_data();
+        assert_eq!(written_data.len(), 1 + 3 * (3 + 128) + 1);
+        assert_eq!(written_data.last(), Some(&ST7567_EXIT_RMWMODE));
+
+        // The next call goes on with page 3
+        st7567.spi.clear_written_data();
+        let flushed = st7567.show_with_deadline(|| false).unwrap();
+        assert_eq!(
+            flushed,
+            DeadlineFlush {
+                pages: 5,
+                complete: true
+            }
+        );
+        assert_eq!(st7567.spi.get_written_data()[1], ST7567_SETPAGESTART | 3);
+
+        st7567.spi.clear_written_data();
+        assert_eq!(st7567.show_with_deadline(|| true).unwrap().pages, 0);
+        assert_eq!(
+            st7567.spi.get_written_data(),
+            [ST7567_ENTER_RMWMODE, ST7567_EXIT_RMWMODE]
+        );
+    }
+
+    #[test]
+    fn test_show_skip_blank_pages() {
+        let mut st7567 = create_test_st7567();
+        let full_frame = 1 + 8 * (3 + 128) + 1;
+
+        // Disabled by default
+        assert!(st7567.show().is_ok());
+        assert!(st7567.show().is_ok());
+        assert_eq!(st7567.spi.get_written_data().len(), 2 * full_frame);
+
+        // Controller RAM content is unknown after a reset
+        st7567.set_skip_blank_pages(true);
+        assert!(st7567.reset(&mut MockDelay::new()).is_ok());
+        assert!(st7567.init().is_ok());
+        st7567.spi.clear_written_data();
+        assert!(st7567.show().is_ok());
+        assert_eq!(st7567.spi.get_written_data().len(), full_frame);
+
+        // Then blank pages are not sent again
+        st7567.spi.clear_written_data();
+        assert!(st7567.show().is_ok());
+        assert_eq!(
+            st7567.spi.get_written_data(),
+            vec![ST7567_ENTER_RMWMODE, ST7567_EXIT_RMWMODE]
+        );
+
+        // Only the page in use is sent
+        st7567.set_pixel(10, 42, true);
+        st7567.spi.clear_written_data();
+        assert!(st7567.show().is_ok());
+        let written_data = st7567.spi.get_written_data();
+        assert_eq!(written_data.len(), 1 + 3 + 128 + 1);
+        assert_eq!(written_data[1], ST7567_SETPAGESTART | 5);
+
+        // Clearing it needs one transfer to blank the page on the controller
+        st7567.clear();
+        st7567.spi.clear_written_data();
+        assert!(st7567.show().is_ok());
+        assert_eq!(st7567.spi.get_written_data().len(), 1 + 3 + 128 + 1);
+        st7567.spi.clear_written_data();
+        assert!(st7567.show().is_ok());
+        assert_eq!(st7567.spi.get_written_data().len(), 2);
+    }
+
+    #[test]
+    fn test_show_dirty() {
+        let mut st7567 = create_test_st7567();
+        let full_frame = 1 + 8 * (3 + 128) + 1;
+
+        // Every page is dirty until sent
+        assert!(st7567.is_dirty());
+        assert!(st7567.show_dirty().is_ok());
+        assert_eq!(st7567.spi.get_written_data().len(), full_frame);
+        assert!(!st7567.is_dirty());
+        st7567.spi.clear_written_data();
+        assert!(st7567.show_dirty().is_ok());
+        assert_eq!(st7567.spi.get_written_data().len(), 2);
+
+        // Only the pages drawn to are sent
+        st7567.set_pixel(10, 42, true);
+        st7567.fill_region(0, 0, 4, 9, true);
+        assert!(st7567.is_dirty());
+        st7567.spi.clear_written_data();
+        assert!(st7567.show_dirty().is_ok());
+        let written_data = st7567.spi.get_written_data();
+        assert_eq!(written_data.len(), 1 + 3 * (3 + 128) + 1);
+        assert_eq!(written_data[1], ST7567_SETPAGESTART);
+        assert!(!st7567.is_dirty());
+
+        // A failed transfer leaves the page dirty
+        st7567.set_pixel(10, 42, false);
+        st7567.spi.set_fail(true);
+        assert!(st7567.show_dirty().is_err());
+        st7567.spi.set_fail(false);
+        assert!(st7567.is_dirty());
+
+        // So does a reset, for every page
+        assert!(st7567.show_dirty().is_ok());
+        assert!(st7567.reset(&mut MockDelay::new()).is_ok());
+        assert!(st7567.init().is_ok());
+        st7567.spi.clear_written_data();
+        assert!(st7567.show_dirty().is_ok());
+        assert_eq!(st7567.spi.get_written_data().len(), full_frame);
+
+        st7567.buffer_mut();
+        assert!(st7567.is_dirty());
+    }
+
+    #[test]
+    #[cfg(feature = "shadow")]
+    fn test_show_changed() {
+        let mut st7567 = create_test_st7567();
+        let full_frame = 1 + 8 * (3 + 128) + 1;
+
+        // Nothing is known about the controller RAM yet
+        assert!(!st7567.verify_frame());
+        assert!(st7567.show_changed().is_ok());
+        assert_eq!(st7567.spi.get_written_data().len(), full_frame);
+        assert!(st7567.verify_frame());
+
+        // Nothing changed
+        st7567.spi.clear_written_data();
+        assert!(st7567.show_changed().is_ok());
+        assert_eq!(
+            st7567.spi.get_written_data(),
+            vec![ST7567_ENTER_RMWMODE, ST7567_EXIT_RMWMODE]
+        );
+
+        // Only the changed span of columns is sent
+        st7567.set_pixel(20, 9, true);
+        st7567.set_pixel(35, 12, true);
+        assert!(!st7567.verify_frame());
+        st7567.spi.clear_written_data();
+        assert!(st7567.show_changed().is_ok());
+        let written_data = st7567.spi.get_written_data();
+        assert_eq!(
+            written_data[1..4],
+            [
+                ST7567_SETPAGESTART | 1,
+                ST7567_SETCOLL | 4,
+                ST7567_SETCOLH | 1
+            ]
+        );
+        assert_eq!(written_data.len(), 1 + 3 + 16 + 1);
+        assert_eq!(written_data[4], 0b10);
+        assert_eq!(written_data[4 + 15], 0b10000);
+        assert!(st7567.verify_frame());
+
+        // A failed transfer makes the page unknown again
+        st7567.set_pixel(0, 0, true);
+        st7567.spi.set_fail_after(2);
+        assert!(st7567.show_changed().is_err());
+        assert!(!st7567.verify_frame());
+        *st7567.spi.fail_after.borrow_mut() = None;
+        st7567.spi.clear_written_data();
+        assert!(st7567.show_changed().is_ok());
+        let written_data = st7567.spi.get_written_data();
+        assert_eq!(written_data[0], ST7567_EXIT_RMWMODE);
+        assert_eq!(written_data.len(), 1 + 1 + 3 + 128 + 1);
+
+        // So does a reset
+        assert!(st7567.reset(&mut MockDelay::new()).is_ok());
+        assert!(!st7567.verify_frame());
+        assert!(st7567.init().is_ok());
+        st7567.spi.clear_written_data();
+        assert!(st7567.show().is_ok());
+        assert!(st7567.verify_frame());
+    }
+
+    #[test]
+    fn test_lsb_first_bit_order() {
+        let mut st7567 = create_test_st7567();
+        st7567.set_bit_order(BitOrder::LsbFirst);
+        assert!(st7567.set_contrast(0b0000_0011).is_ok());
+        assert_eq!(
+            st7567.spi.get_written_data(),
+            vec![ST7567_SETCONTRAST.reverse_bits(), 0b1100_0000]
+        );
+
+        st7567.spi.clear_written_data();
+        st7567.set_pixel(0, 0, true);
+        st7567.set_pixel(127, 7, true);
+        assert!(st7567.show().is_ok());
+        let written_data = st7567.spi.get_written_data();
+        assert_eq!(written_data.len(), 1 + 8 * (3 + 128) + 1);
+        assert_eq!(written_data[4], 0b1000_0000);
+        assert_eq!(written_data[4 + 127], 0b0000_0001);
+        // The buffer itself is left in controller order
+        assert_eq!(st7567.buf[0], 0b0000_0001);
+    }
+
+    #[test]
+    fn test_show_recovers_from_interrupted_flush() {
+        let mut st7567 = create_test_st7567();
+
+        // Fail in the middle of the third page
+        st7567.spi.set_fail_after(6);
+        assert!(matches!(st7567.show(), Err(Error::SpiError(_))));
+        assert!(st7567.flags.get(Flags::RMW_PENDING));
+
+        // Next flush first leaves RMW mode, then sends a full frame
+        *st7567.spi.fail_after.borrow_mut() = None;
+        st7567.spi.clear_written_data();
+        assert!(st7567.show().is_ok());
+        assert!(!st7567.flags.get(Flags::RMW_PENDING));
+        let written_data = st7567.spi.get_written_data();
+        assert_eq!(written_data[0], ST7567_EXIT_RMWMODE);
+        assert_eq!(written_data[1], ST7567_ENTER_RMWMODE);
+        assert_eq!(written_data.len(), 1 + 1 + 8 * (3 + 128) + 1);
+
+        // Once recovered, no extra exit command is sent
+        st7567.spi.clear_written_data();
+        assert!(st7567.show().is_ok());
+        assert_eq!(st7567.spi.get_written_data()[0], ST7567_ENTER_RMWMODE);
+    }
+
+    #[test]
+    fn test_recover() {
+        let mut st7567 = create_test_st7567();
+        st7567.spi.set_fail(true);
+        assert!(st7567.recover().is_err());
+        assert!(st7567.flags.get(Flags::RMW_PENDING));
+
+        st7567.spi.set_fail(false);
+        assert!(st7567.recover().is_ok());
+        assert!(!st7567.flags.get(Flags::RMW_PENDING));
+        assert_eq!(st7567.spi.get_written_data(), vec![ST7567_EXIT_RMWMODE]);
+    }
+
+    #[test]
+    fn test_reinit() {
+        let mut st7567 = create_test_st7567();
+        let mut delay = MockDelay::new();
+        st7567.set_pixel(0, 0, true);
+        assert!(st7567.set_inverted(true).is_ok());
+        st7567.spi.clear_written_data();
+        assert!(st7567.reinit(&mut delay).is_ok());
+        let rst_states = st7567.rst_pin.as_ref().unwrap().get_states();
+        assert!(matches!(rst_states[..], [PinState::Low, PinState::High]));
+        let written_data = st7567.spi.get_written_data();
+        let init_len = crate::sequence::GFX_HAT.len();
+        assert_eq!(written_data[..init_len], *crate::sequence::GFX_HAT);
+        assert_eq!(written_data[init_len], ST7567_DISPINVERSE);
+        // Every page is sent again
+        assert_eq!(written_data.len(), init_len + 1 + 1 + 8 * (3 + 128) + 1);
+        assert!(st7567.is_inverted());
+    }
+
+    #[test]
+    fn test_show_or_reinit() {
+        let mut st7567 = create_test_st7567();
+        let mut delay = MockDelay::new();
+        st7567.spi.set_fail(true);
+        // Disabled by default
+        for _ in 0..3 {
+            assert!(st7567.show_or_reinit(&mut delay).is_err());
+        }
+        assert!(st7567.rst_pin.as_ref().unwrap().get_states().is_empty());
+
+        // A success resets the count
+        st7567.set_auto_reinit(2);
+        assert!(st7567.show_or_reinit(&mut delay).is_err());
+        st7567.spi.set_fail(false);
+        assert!(st7567.show_or_reinit(&mut delay).is_ok());
+        st7567.spi.set_fail(true);
+        assert!(st7567.show_or_reinit(&mut delay).is_err());
+        assert!(st7567.rst_pin.as_ref().unwrap().get_states().is_empty());
+
+        // The second failure in a row triggers reinit()
+        st7567.spi.set_fail(false);
+        st7567.flags.set(Flags::INITIALIZED, false);
+        assert!(st7567.show_or_reinit(&mut delay).is_ok());
+        assert_eq!(st7567.rst_pin.as_ref().unwrap().get_states().len(), 2);
+        assert!(st7567.is_initialized());
+    }
+
+    #[test]
+    fn test_ping() {
+        let mut st7567 = create_test_st7567();
+        assert!(st7567.ping().is_ok());
+        assert_eq!(st7567.spi.get_written_data(), vec![ST7567_NOP]);
+
+        st7567.spi.set_fail(true);
+        assert!(matches!(st7567.ping(), Err(Error::SpiError(_))));
+        st7567.spi.set_fail(false);
+        st7567.dc_pin.set_fail(true);
+        assert!(matches!(st7567.ping(), Err(Error::PinError(_))));
+    }
+
     #[test]
     fn test_error_display_and_debug() {
-        let spi_error: Error<MockPin, MockSpiDevice> = Error::SpiError(MockError::SpiError);
-        let pin_error: Error<MockPin, MockSpiDevice> = Error::PinError(MockError::PinError);
+        let spi_error: Error<MockPin, MockPin, MockSpiDevice> =
+            Error::SpiError(MockError::SpiError);
+        let pin_error: Error<MockPin, MockPin, MockSpiDevice> =
+            Error::PinError(MockError::PinError);
+        
+        let rst_error: Error<MockPin, MockPin, MockSpiDevice> =
+            Error::ResetPinError(MockError::PinError);
         
         // Test Display trait
         assert_eq!(format!("{}", spi_error), "SpiError");
         assert_eq!(format!("{}", pin_error), "PinError");
+        assert_eq!(format!("{}", rst_error), "ResetPinError");
         
         // Test Debug trait
         assert_eq!(format!("{:?}", spi_error), "SpiError");
         assert_eq!(format!("{:?}", pin_error), "PinError");
+        assert_eq!(format!("{:?}", rst_error), "ResetPinError");
     }
 
     #[test]
+    #[allow(clippy::erasing_op, clippy::identity_op)]
     fn test_pixel_coordinates_edge_cases() {
         let mut st7567 = create_test_st7567();
         
@@ -563,6 +3374,160 @@ mod tests {
         assert_eq!(st7567.buf[offset], 1 << bit);
     }
 
+    #[test]
+    fn test_driver_footprint() {
+        // With zero-sized pins and SPI device, the driver must cost little
+        // more RAM than the display buffer itself
+        struct ZstPin;
+        impl Pin for ZstPin {
+            type Error = MockError;
+            fn set_value(&mut self, _: PinState) -> Result<(), Self::Error> {
+                Ok(())
+            }
+        }
+        struct ZstSpi;
+        impl embedded_hal::spi::ErrorType for ZstSpi {
+            type Error = MockError;
+        }
+        impl embedded_hal::spi::SpiDevice for ZstSpi {
+            fn transaction(
+                &mut self,
+                _: &mut [embedded_hal::spi::Operation<'_, u8>],
+            ) -> Result<(), Self::Error> {
+                Ok(())
+            }
+        }
+        let buffers = if cfg!(feature = "shadow") { 2 } else { 1 };
+        assert!(
+            std::mem::size_of::<ST7567<ZstPin, ZstPin, ZstSpi>>() <= buffers * BUFFER_SIZE + 32
+        );
+        assert!(
+            std::mem::size_of::<ST7567<ZstPin, ZstPin, ZstSpi, NoBuffer>>()
+                <= (buffers - 1) * BUFFER_SIZE + 32
+        );
+    }
+
+    #[test]
+    fn test_write_page() {
+        let mut st7567 = ST7567::with_buffer(
+            MockSpiDevice::new(),
+            MockPin::new(),
+            MockPin::new(),
+            NoBuffer,
+        );
+        assert!(matches!(
+            st7567.write_page(0, 0, &[0xff]),
+            Err(Error::NotInitialized)
+        ));
+        assert!(st7567.init().is_ok());
+        st7567.spi.clear_written_data();
+        assert!(st7567.write_page(2, 10, &[1, 2, 3]).is_ok());
+        assert_eq!(
+            st7567.spi.get_written_data(),
+            vec![
+                ST7567_ENTER_RMWMODE,
+                ST7567_SETPAGESTART | 2,
+                0x0a,
+                0x10,
+                1,
+                2,
+                3,
+                ST7567_EXIT_RMWMODE
+            ]
+        );
+
+        // Nothing is sent for data outside of the panel
+        st7567.spi.clear_written_data();
+        assert!(matches!(
+            st7567.write_page(8, 0, &[0xff]),
+            Err(Error::InvalidArgument)
+        ));
+        assert!(matches!(
+            st7567.write_page(0, 126, &[0; 3]),
+            Err(Error::InvalidArgument)
+        ));
+        assert!(st7567.spi.get_written_data().is_empty());
+
+        // Without storage, drawing into the buffer does nothing
+        st7567.set_pixel(0, 0, true);
+        assert!(!st7567.get_pixel(0, 0));
+        assert!(st7567.buffer().is_empty());
+    }
+
+    #[test]
+    fn test_with_borrowed_buffer() {
+        let mut storage = StaticFramebuffer::new();
+        let mut st7567 = ST7567::with_buffer(
+            MockSpiDevice::new(),
+            MockPin::new(),
+            MockPin::new(),
+            &mut storage,
+        );
+        assert!(st7567.init().is_ok());
+        st7567.spi.clear_written_data();
+        st7567.set_pixel(0, 8, true);
+        assert!(st7567.show().is_ok());
+        let written_data = st7567.spi.get_written_data();
+        // enter RMW + page 0 + page 1 commands, then page 1 data
+        assert_eq!(written_data[1 + 3 + 128 + 3], 1);
+        drop(st7567);
+        assert!(storage.get(0, 8));
+    }
+
+    #[test]
+    fn test_output_pin_blanket_impl() {
+        #[derive(Default)]
+        struct HalPin {
+            levels: Vec<bool>,
+        }
+        impl embedded_hal::digital::ErrorType for HalPin {
+            type Error = core::convert::Infallible;
+        }
+        impl OutputPin for HalPin {
+            fn set_low(&mut self) -> Result<(), Self::Error> {
+                self.levels.push(false);
+                Ok(())
+            }
+            fn set_high(&mut self) -> Result<(), Self::Error> {
+                self.levels.push(true);
+                Ok(())
+            }
+        }
+
+        let mut st7567 = ST7567::new(MockSpiDevice::new(), HalPin::default(), HalPin::default());
+        assert!(st7567.set_contrast(10).is_ok());
+        assert!(st7567.reset(&mut MockDelay::new()).is_ok());
+        assert_eq!(st7567.dc_pin.levels, vec![false]);
+        assert_eq!(st7567.rst_pin.unwrap().levels, vec![false, true]);
+    }
+
+    #[test]
+    fn test_heterogeneous_pin_types() {
+        // e.g. HALs exposing each GPIO as its own zero-sized type
+        struct Gpio24;
+        impl embedded_hal::digital::ErrorType for Gpio24 {
+            type Error = core::convert::Infallible;
+        }
+        impl OutputPin for Gpio24 {
+            fn set_low(&mut self) -> Result<(), Self::Error> {
+                Ok(())
+            }
+            fn set_high(&mut self) -> Result<(), Self::Error> {
+                Ok(())
+            }
+        }
+
+        let mut st7567: ST7567<MockPin, Gpio24, MockSpiDevice> =
+            ST7567::new(MockSpiDevice::new(), MockPin::new(), Gpio24);
+        assert!(st7567.reset(&mut MockDelay::new()).is_ok());
+        assert!(st7567.init().is_ok());
+        st7567.dc_pin.set_fail(true);
+        assert!(matches!(
+            st7567.show(),
+            Err(Error::PinError(MockError::PinError))
+        ));
+    }
+
     #[test]
     fn test_constants() {
         // Test that our constants match expected values
@@ -0,0 +1,253 @@
+//! Linux backend, using spidev and the GPIO character device
+//!
+//! Gives a ready to use driver on a Raspberry Pi or any Linux board exposing
+//! the panel through `/dev/spidevX.Y` and `/dev/gpiochipN`:
+//!
+//! ```no_run
+//! use st7567::linux::LinuxDelay;
+//! use st7567::ST7567;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut display = ST7567::open_gfx_hat()?;
+//! display.reset(&mut LinuxDelay)?;
+//! display.init()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Other wirings can use [`ST7567::open`](crate::ST7567::open), or assemble
+//! [`LinuxSpi`] and [`LinuxPin`] by hand.
+use crate::{SPI_SPEED_HZ, ST7567};
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{self, OutputPin};
+use embedded_hal::spi::{self, Operation, SpiDevice};
+use gpio_cdev::{Chip, LineHandle, LineRequestFlags};
+use spidev::{SpiModeFlags, Spidev, SpidevOptions, SpidevTransfer};
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+/// A driver instance on Linux, with DC and RST pins on GPIO lines.
+pub type LinuxST7567 = ST7567<LinuxPin, LinuxPin, LinuxSpi>;
+
+/// SPI device of the Pimoroni GFX HAT: bus 0, chip select 1.
+pub const GFX_HAT_SPIDEV: &str = "/dev/spidev0.1";
+/// GPIO chip of the Raspberry Pi header.
+pub const GFX_HAT_GPIOCHIP: &str = "/dev/gpiochip0";
+/// BCM line of the GFX HAT DC pin.
+pub const GFX_HAT_DC_LINE: u32 = 6;
+/// BCM line of the GFX HAT RST pin.
+pub const GFX_HAT_RST_LINE: u32 = 5;
+
+impl LinuxST7567 {
+    /// Open the display on a spidev device, with DC and RST on lines of a
+    /// GPIO chip.
+    pub fn open<P: AsRef<Path>, C: AsRef<Path>>(
+        spidev: P,
+        gpiochip: C,
+        dc_line: u32,
+        rst_line: u32,
+    ) -> Result<Self, OpenError> {
+        Self::open_with_speed(spidev, gpiochip, dc_line, rst_line, SPI_SPEED_HZ)
+    }
+
+    /// Like [`open`](Self::open), with another SPI clock frequency.
+    pub fn open_with_speed<P: AsRef<Path>, C: AsRef<Path>>(
+        spidev: P,
+        gpiochip: C,
+        dc_line: u32,
+        rst_line: u32,
+        speed_hz: u32,
+    ) -> Result<Self, OpenError> {
+        let spi = LinuxSpi::open_with_speed(spidev, speed_hz).map_err(OpenError::Spi)?;
+        let dc = LinuxPin::open(&gpiochip, dc_line).map_err(OpenError::Gpio)?;
+        let rst = LinuxPin::open(&gpiochip, rst_line).map_err(OpenError::Gpio)?;
+        Ok(ST7567::new(spi, dc, rst))
+    }
+
+    /// Open the display of a Pimoroni GFX HAT on a Raspberry Pi.
+    ///
+    /// The SPI interface must be enabled, e.g. with `dtparam=spi=on`.
+    pub fn open_gfx_hat() -> Result<Self, OpenError> {
+        Self::open(
+            GFX_HAT_SPIDEV,
+            GFX_HAT_GPIOCHIP,
+            GFX_HAT_DC_LINE,
+            GFX_HAT_RST_LINE,
+        )
+    }
+}
+
+/// Error opening the spidev device or requesting a GPIO line.
+#[derive(Debug)]
+pub enum OpenError {
+    Spi(io::Error),
+    Gpio(gpio_cdev::Error),
+}
+
+impl fmt::Display for OpenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpenError::Spi(e) => write!(f, "cannot open SPI device: {}", e),
+            OpenError::Gpio(e) => write!(f, "cannot request GPIO line: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for OpenError {}
+
+/// A spidev device, configured for the ST7567.
+pub struct LinuxSpi(Spidev);
+
+impl LinuxSpi {
+    /// Open and configure a spidev device: mode 0, 8 bits MSB-first words,
+    /// at [`SPI_SPEED_HZ`].
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::open_with_speed(path, SPI_SPEED_HZ)
+    }
+
+    /// Like [`open`](Self::open), with another clock frequency, e.g. for
+    /// long wires.
+    pub fn open_with_speed<P: AsRef<Path>>(path: P, speed_hz: u32) -> io::Result<Self> {
+        let mut spidev = Spidev::open(path)?;
+        spidev.configure(
+            &SpidevOptions::new()
+                .bits_per_word(8)
+                .lsb_first(false)
+                .max_speed_hz(speed_hz)
+                .mode(SpiModeFlags::SPI_MODE_0)
+                .build(),
+        )?;
+        Ok(LinuxSpi(spidev))
+    }
+}
+
+/// Error returned by [`LinuxSpi`].
+#[derive(Debug)]
+pub struct SpidevError(pub io::Error);
+
+impl spi::Error for SpidevError {
+    fn kind(&self) -> spi::ErrorKind {
+        spi::ErrorKind::Other
+    }
+}
+
+impl spi::ErrorType for LinuxSpi {
+    type Error = SpidevError;
+}
+
+impl SpiDevice for LinuxSpi {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), SpidevError> {
+        // spidev wants equal length transmit and receive buffers, stage every
+        // operation through owned ones
+        let mut staged: Vec<(Vec<u8>, Vec<u8>)> = operations
+            .iter()
+            .map(|operation| match operation {
+                Operation::Read(read) => (vec![0; read.len()], vec![0; read.len()]),
+                Operation::Write(write) => (write.to_vec(), Vec::new()),
+                Operation::Transfer(read, write) => {
+                    let len = read.len().max(write.len());
+                    let mut tx = write.to_vec();
+                    tx.resize(len, 0);
+                    (tx, vec![0; len])
+                }
+                Operation::TransferInPlace(words) => (words.to_vec(), vec![0; words.len()]),
+                Operation::DelayNs(_) => (Vec::new(), Vec::new()),
+            })
+            .collect();
+
+        let mut transfers: Vec<SpidevTransfer> = operations
+            .iter()
+            .zip(staged.iter_mut())
+            .map(|(operation, (tx, rx))| match operation {
+                Operation::Write(_) => SpidevTransfer::write(tx),
+                Operation::DelayNs(ns) => {
+                    let us = ns.div_ceil(1000);
+                    SpidevTransfer::delay(us.min(u32::from(u16::MAX)) as u16)
+                }
+                _ => SpidevTransfer::read_write(tx, rx),
+            })
+            .collect();
+        self.0
+            .transfer_multiple(&mut transfers)
+            .map_err(SpidevError)?;
+        drop(transfers);
+
+        for (operation, (_, rx)) in operations.iter_mut().zip(staged.iter()) {
+            let read: &mut [u8] = match operation {
+                Operation::Read(read) | Operation::Transfer(read, _) => read,
+                Operation::TransferInPlace(words) => words,
+                _ => continue,
+            };
+            for (word, received) in read.iter_mut().zip(rx) {
+                *word = *received;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A GPIO line requested as an output.
+pub struct LinuxPin(LineHandle);
+
+impl LinuxPin {
+    /// Request `line` of a GPIO chip, e.g. `/dev/gpiochip0`, as an output.
+    pub fn open<P: AsRef<Path>>(chip: P, line: u32) -> Result<Self, gpio_cdev::Error> {
+        let handle =
+            Chip::new(chip)?
+                .get_line(line)?
+                .request(LineRequestFlags::OUTPUT, 0, "st7567")?;
+        Ok(LinuxPin(handle))
+    }
+}
+
+/// Error returned by [`LinuxPin`].
+#[derive(Debug)]
+pub struct GpioError(pub gpio_cdev::Error);
+
+impl digital::Error for GpioError {
+    fn kind(&self) -> digital::ErrorKind {
+        digital::ErrorKind::Other
+    }
+}
+
+impl digital::ErrorType for LinuxPin {
+    type Error = GpioError;
+}
+
+impl OutputPin for LinuxPin {
+    fn set_low(&mut self) -> Result<(), GpioError> {
+        self.0.set_value(0).map_err(GpioError)
+    }
+
+    fn set_high(&mut self) -> Result<(), GpioError> {
+        self.0.set_value(1).map_err(GpioError)
+    }
+}
+
+/// Delays implemented by putting the thread to sleep.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LinuxDelay;
+
+impl DelayNs for LinuxDelay {
+    fn delay_ns(&mut self, ns: u32) {
+        std::thread::sleep(Duration::from_nanos(u64::from(ns)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_missing_device() {
+        let result = LinuxST7567::open("/nonexistent/spidev", GFX_HAT_GPIOCHIP, 6, 5);
+        match result {
+            Err(error @ OpenError::Spi(_)) => {
+                assert!(error.to_string().starts_with("cannot open SPI device"))
+            }
+            _ => panic!("opening a missing spidev device should fail"),
+        }
+    }
+}
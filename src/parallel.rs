@@ -0,0 +1,232 @@
+//! 8-bit parallel bus transport, 8080 or 6800 style
+//!
+//! Some modules only break out the parallel interface. [`ParallelBus`]
+//! bit-bangs it with GPIOs and is handed to the driver in place of the SPI
+//! device, the DC pin (A0 on the datasheet) staying a regular pin of the
+//! driver:
+//!
+//! ```ignore
+//! let data = [d0, d1, d2, d3, d4, d5, d6, d7];
+//! let bus = ParallelBus::new(data, wr, rd, cs, delay, BusMode::I8080);
+//! let mut display = ST7567::new(bus, dc, rst);
+//! ```
+//!
+//! The delay only waits out the `DelayNs` operations of transactions, none
+//! is inserted between pin changes: GPIO writes are slow enough
+//! for the controller timings on most microcontrollers. A fast core with
+//! single-cycle GPIO may need pins wrapped in a [`Pin`] implementation that
+//! waits a little after each change.
+use crate::{Pin, PinState};
+use embedded_hal::delay::DelayNs;
+use embedded_hal::spi::{self, ErrorKind, ErrorType, Operation, SpiDevice};
+
+/// Handshake of the parallel interface, selected by the P/S and C86 pins of
+/// the module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BusMode {
+    /// Data latched on the rising edge of /WR, /RD held high
+    #[default]
+    I8080,
+    /// /WR is R/W, held low for writes; data latched on the falling edge of
+    /// /RD, which is E
+    M6800,
+}
+
+/// Error of a [`ParallelBus`] transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParallelError<E> {
+    /// Error driving a pin
+    Pin(E),
+    /// The bus is driven write-only
+    ReadUnsupported,
+}
+
+impl<E: core::fmt::Debug> spi::Error for ParallelError<E> {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+/// Writes bytes on D0 to D7, see the [module](self) docs.
+pub struct ParallelBus<D, WR, RD, CS, DL> {
+    data: [D; 8],
+    wr: WR,
+    rd: RD,
+    cs: CS,
+    delay: DL,
+    mode: BusMode,
+}
+
+impl<D, WR, RD, CS, DL> ParallelBus<D, WR, RD, CS, DL>
+where
+    D: Pin,
+    WR: Pin<Error = D::Error>,
+    RD: Pin<Error = D::Error>,
+    CS: Pin<Error = D::Error>,
+    DL: DelayNs,
+{
+    /// `data` holds D0 first. Pins are put in their idle state at the start
+    /// of every transaction.
+    pub fn new(data: [D; 8], wr: WR, rd: RD, cs: CS, delay: DL, mode: BusMode) -> Self {
+        ParallelBus {
+            data,
+            wr,
+            rd,
+            cs,
+            delay,
+            mode,
+        }
+    }
+
+    /// Give the pins and the delay back.
+    pub fn release(self) -> ([D; 8], WR, RD, CS, DL) {
+        (self.data, self.wr, self.rd, self.cs, self.delay)
+    }
+
+    fn select(&mut self) -> Result<(), D::Error> {
+        match self.mode {
+            BusMode::I8080 => {
+                self.wr.set_value(PinState::High)?;
+                self.rd.set_value(PinState::High)?;
+            }
+            BusMode::M6800 => {
+                self.wr.set_value(PinState::Low)?;
+                self.rd.set_value(PinState::Low)?;
+            }
+        }
+        self.cs.set_value(PinState::Low)
+    }
+
+    fn write_byte(&mut self, byte: u8) -> Result<(), D::Error> {
+        for (bit, pin) in self.data.iter_mut().enumerate() {
+            pin.set_value(if byte >> bit & 1 != 0 {
+                PinState::High
+            } else {
+                PinState::Low
+            })?;
+        }
+        match self.mode {
+            BusMode::I8080 => {
+                self.wr.set_value(PinState::Low)?;
+                self.wr.set_value(PinState::High)
+            }
+            BusMode::M6800 => {
+                self.rd.set_value(PinState::High)?;
+                self.rd.set_value(PinState::Low)
+            }
+        }
+    }
+}
+
+impl<D, WR, RD, CS, DL> ErrorType for ParallelBus<D, WR, RD, CS, DL>
+where
+    D: Pin,
+    D::Error: core::fmt::Debug,
+{
+    type Error = ParallelError<D::Error>;
+}
+
+impl<D, WR, RD, CS, DL> SpiDevice for ParallelBus<D, WR, RD, CS, DL>
+where
+    D: Pin,
+    D::Error: core::fmt::Debug,
+    WR: Pin<Error = D::Error>,
+    RD: Pin<Error = D::Error>,
+    CS: Pin<Error = D::Error>,
+    DL: DelayNs,
+{
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        self.select().map_err(ParallelError::Pin)?;
+        let mut result = Ok(());
+        for operation in operations.iter() {
+            result = match operation {
+                Operation::Write(bytes) => bytes
+                    .iter()
+                    .try_for_each(|&byte| self.write_byte(byte))
+                    .map_err(ParallelError::Pin),
+                Operation::DelayNs(ns) => {
+                    self.delay.delay_ns(*ns);
+                    Ok(())
+                }
+                _ => Err(ParallelError::ReadUnsupported),
+            };
+            if result.is_err() {
+                break;
+            }
+        }
+        // Release the bus even after an error
+        let deselect = self
+            .cs
+            .set_value(PinState::High)
+            .map_err(ParallelError::Pin);
+        result.and(deselect)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{MockDelay, MockError, MockPin};
+    use crate::ST7567;
+    use PinState::{High, Low};
+
+    fn bus(mode: BusMode) -> ParallelBus<MockPin, MockPin, MockPin, MockPin, MockDelay> {
+        let data = [(); 8].map(|_| MockPin::new());
+        let (wr, rd, cs) = (MockPin::new(), MockPin::new(), MockPin::new());
+        ParallelBus::new(data, wr, rd, cs, MockDelay::new(), mode)
+    }
+
+    /// Bytes put on D0 to D7, one per strobe.
+    fn written_bytes(data: &[MockPin; 8]) -> Vec<u8> {
+        let states: Vec<Vec<PinState>> = data.iter().map(|pin| pin.get_states()).collect();
+        (0..states[0].len())
+            .map(|i| {
+                (0..8)
+                    .filter(|&bit| states[bit][i] == High)
+                    .fold(0, |byte, bit| byte | 1 << bit)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_8080_writes() {
+        let mut st7567 = ST7567::new(bus(BusMode::I8080), MockPin::new(), MockPin::new());
+        assert!(st7567.init().is_ok());
+        assert_eq!(written_bytes(&st7567.spi.data), crate::sequence::GFX_HAT);
+        assert_eq!(st7567.spi.cs.get_states(), [Low, High]);
+        // Idle high, then one low pulse per byte
+        let wr = st7567.spi.wr.get_states();
+        assert_eq!(wr.len(), 1 + 2 * crate::sequence::GFX_HAT.len());
+        assert_eq!(wr[..5], [High, Low, High, Low, High]);
+        assert_eq!(st7567.spi.rd.get_states(), [High]);
+    }
+
+    #[test]
+    fn test_6800_writes() {
+        let mut bus = bus(BusMode::M6800);
+        let mut operations = [Operation::Write(&[0xa5]), Operation::DelayNs(300)];
+        assert!(bus.transaction(&mut operations).is_ok());
+        let (data, wr, rd, cs, delay) = bus.release();
+        assert_eq!(written_bytes(&data), [0xa5]);
+        assert_eq!(wr.get_states(), [Low]);
+        assert_eq!(rd.get_states(), [Low, High, Low]);
+        assert_eq!(cs.get_states(), [Low, High]);
+        assert_eq!(delay.delays_ns, [300]);
+    }
+
+    #[test]
+    fn test_errors_release_the_bus() {
+        let mut bus = bus(BusMode::I8080);
+        let mut read = [0];
+        assert_eq!(
+            bus.transaction(&mut [Operation::Read(&mut read)]),
+            Err(ParallelError::ReadUnsupported)
+        );
+        bus.data[3].set_fail(true);
+        assert_eq!(
+            bus.transaction(&mut [Operation::Write(&[0xff])]),
+            Err(ParallelError::Pin(MockError::PinError))
+        );
+        assert_eq!(bus.cs.get_states(), [Low, High, Low, High]);
+    }
+}
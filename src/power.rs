@@ -0,0 +1,114 @@
+//! Sequencing of an external display supply
+//!
+//! Battery devices often cut the LCD (and backlight) supply with a load
+//! switch while idle. The controller must then be reset and initialized
+//! after the supply comes back, and put to sleep before it goes away, or
+//! the glass is left with a DC bias. [`ST7567::power_up`] and
+//! [`ST7567::power_down`] drive the enable pin of that switch in the right
+//! order around the driver's own sequences:
+//!
+//! ```ignore
+//! display.power_down(&mut rail_enable, &mut delay)?;
+//! // ... idle ...
+//! display.power_up(&mut rail_enable, &mut delay)?;
+//! display.show()?;
+//! ```
+use crate::transport::Transport;
+use crate::PinState::{High, Low};
+use crate::{Error, Pin, PixelBuffer, ST7567};
+use core::fmt;
+use embedded_hal::delay::DelayNs;
+
+/// Time for the supply to rise before the controller is reset.
+const RAIL_SETTLE_MS: u32 = 10;
+
+/// Error of a power sequence.
+pub enum PowerError<P: Pin, DC: Pin, RST: Pin, S: Transport> {
+    /// Error driving the supply enable pin
+    RailPinError(P::Error),
+    /// Error talking to the display
+    Display(Error<DC, RST, S>),
+}
+
+impl<P: Pin, DC: Pin, RST: Pin, S: Transport> From<Error<DC, RST, S>>
+    for PowerError<P, DC, RST, S>
+{
+    fn from(error: Error<DC, RST, S>) -> Self {
+        PowerError::Display(error)
+    }
+}
+
+impl<P: Pin, DC: Pin, RST: Pin, S: Transport> fmt::Debug for PowerError<P, DC, RST, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PowerError::RailPinError(_) => write!(f, "RailPinError"),
+            PowerError::Display(error) => write!(f, "Display({:?})", error),
+        }
+    }
+}
+
+impl<DC: Pin, RST: Pin, S: Transport, B: PixelBuffer, const W: usize, const H: usize>
+    ST7567<DC, RST, S, B, W, H>
+{
+    /// Enable the display supply with `rail`, then reset and initialize the
+    /// controller.
+    ///
+    /// `rail` is driven high and given 10ms to settle before the reset.
+    pub fn power_up<P: Pin, D: DelayNs>(
+        &mut self,
+        rail: &mut P,
+        delay: &mut D,
+    ) -> Result<(), PowerError<P, DC, RST, S>> {
+        rail.set_value(High).map_err(PowerError::RailPinError)?;
+        delay.delay_ms(RAIL_SETTLE_MS);
+        self.reset(delay)?;
+        self.init()?;
+        Ok(())
+    }
+
+    /// Put the controller to sleep with [`power_off`](Self::power_off), then
+    /// cut the display supply with `rail`.
+    pub fn power_down<P: Pin, D: DelayNs>(
+        &mut self,
+        rail: &mut P,
+        delay: &mut D,
+    ) -> Result<(), PowerError<P, DC, RST, S>> {
+        self.power_off(delay)?;
+        rail.set_value(Low).map_err(PowerError::RailPinError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::*;
+    use crate::tests::{create_test_st7567, MockDelay, MockPin};
+    use crate::PinState;
+
+    #[test]
+    fn test_power_sequence() {
+        let mut st7567 = create_test_st7567();
+        let mut rail = MockPin::new();
+        let mut delay = MockDelay::new();
+
+        assert!(st7567.power_down(&mut rail, &mut delay).is_ok());
+        assert!(matches!(rail.get_states()[..], [PinState::Low]));
+        assert_eq!(st7567.spi.get_written_data()[0], ST7567_DISPOFF);
+        assert!(matches!(st7567.show(), Err(Error::NotInitialized)));
+
+        st7567.spi.clear_written_data();
+        rail.clear_states();
+        assert!(st7567.power_up(&mut rail, &mut delay).is_ok());
+        assert!(matches!(rail.get_states()[..], [PinState::High]));
+        let rst_states = st7567.rst_pin.as_ref().unwrap().get_states();
+        assert!(matches!(rst_states[..], [PinState::Low, PinState::High]));
+        assert_eq!(st7567.spi.get_written_data().last(), Some(&40));
+        assert!(st7567.show().is_ok());
+
+        rail.set_fail(true);
+        assert!(matches!(
+            st7567.power_up(&mut rail, &mut delay),
+            Err(PowerError::RailPinError(_))
+        ));
+    }
+}
@@ -0,0 +1,122 @@
+//! Python bindings
+//!
+//! Exposes the Linux backed driver as a `st7567` Python module, with the same
+//! constructor and method names as the Pimoroni `st7567` library, so existing
+//! GFX HAT scripts can switch backend without changes:
+//!
+//! ```python
+//! import st7567
+//!
+//! display = st7567.ST7567()
+//! display.setup()
+//! display.set_pixel(10, 10, 1)
+//! display.show()
+//! ```
+//!
+//! Build the extension module with maturin, enabling the `python` feature
+//! and `pyo3/extension-module`. The crate has no text or backlight API yet,
+//! so only the driver and its framebuffer are exposed.
+#![allow(unsafe_code)]
+
+use crate::linux::{LinuxDelay, LinuxST7567};
+use crate::{PixelBuffer, BUFFER_SIZE, HEIGHT, SPI_SPEED_HZ, WIDTH};
+use pyo3::exceptions::{PyOSError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+fn io_error<E: core::fmt::Debug>(error: E) -> PyErr {
+    PyOSError::new_err(format!("{:?}", error))
+}
+
+/// A GFX HAT style display: DC and RST on GPIO lines, on `/dev/spidevB.C`.
+#[pyclass(name = "ST7567", module = "st7567", unsendable)]
+pub struct PyST7567(LinuxST7567);
+
+#[pymethods]
+impl PyST7567 {
+    #[new]
+    #[pyo3(signature = (
+        pin_rst = 5,
+        pin_dc = 6,
+        spi_bus = 0,
+        spi_cs = 1,
+        spi_speed_hz = SPI_SPEED_HZ,
+        gpiochip = "/dev/gpiochip0"
+    ))]
+    fn new(
+        pin_rst: u32,
+        pin_dc: u32,
+        spi_bus: u32,
+        spi_cs: u32,
+        spi_speed_hz: u32,
+        gpiochip: &str,
+    ) -> PyResult<Self> {
+        let spidev = format!("/dev/spidev{}.{}", spi_bus, spi_cs);
+        match LinuxST7567::open_with_speed(spidev, gpiochip, pin_dc, pin_rst, spi_speed_hz) {
+            Ok(display) => Ok(PyST7567(display)),
+            Err(e) => Err(PyOSError::new_err(e.to_string())),
+        }
+    }
+
+    /// Reset and initialize the controller.
+    fn setup(&mut self) -> PyResult<()> {
+        self.0.reset(&mut LinuxDelay).map_err(io_error)?;
+        self.0.init().map_err(io_error)
+    }
+
+    fn dimensions(&self) -> (u8, u8) {
+        (WIDTH, HEIGHT)
+    }
+
+    /// Turn the pixel on for any true value, like `1`, off for `0`.
+    fn set_pixel(&mut self, x: usize, y: usize, value: &Bound<'_, PyAny>) -> PyResult<()> {
+        self.0.set_pixel(x, y, value.is_truthy()?);
+        Ok(())
+    }
+
+    fn get_pixel(&self, x: usize, y: usize) -> bool {
+        self.0.buf.get(x, y)
+    }
+
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    fn show(&mut self) -> PyResult<()> {
+        self.0.show().map_err(io_error)
+    }
+
+    fn contrast(&mut self, value: u8) -> PyResult<()> {
+        self.0.set_contrast(value).map_err(io_error)
+    }
+
+    fn set_inverted(&mut self, inverted: bool) -> PyResult<()> {
+        self.0.set_inverted(inverted).map_err(io_error)
+    }
+
+    /// The framebuffer, in controller page layout.
+    fn buffer<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, self.0.buf.as_bytes())
+    }
+
+    /// Replace the framebuffer with 1024 bytes in controller page layout.
+    fn blit(&mut self, frame: &[u8]) -> PyResult<()> {
+        if frame.len() != BUFFER_SIZE {
+            return Err(PyValueError::new_err(format!(
+                "expected {} bytes, got {}",
+                BUFFER_SIZE,
+                frame.len()
+            )));
+        }
+        self.0.buffer_mut().copy_from_slice(frame);
+        Ok(())
+    }
+}
+
+#[pymodule]
+fn st7567(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyST7567>()?;
+    module.add("WIDTH", WIDTH)?;
+    module.add("HEIGHT", HEIGHT)?;
+    Ok(())
+}
@@ -0,0 +1,228 @@
+//! Direct writes to the controller RAM, bypassing the framebuffer
+//!
+//! [`RawMode`] is obtained by consuming the driver, so raw writes and the
+//! buffered pipeline cannot be mixed by accident. Display data can only be
+//! sent through a writer returned by a page selection, and a
+//! Read-Modify-Write sequence only through a writer that leaves the mode when
+//! finished:
+//!
+//! ```ignore
+//! let mut raw = display.into_raw_mode();
+//! raw.page(2, 10)?.write(&[0xff, 0x81, 0xff])?;
+//!
+//! let mut rmw = raw.read_modify_write(2, 10)?;
+//! rmw.write(&[0x00])?;
+//! rmw.finish()?; // column address is back to 10
+//!
+//! let display = raw.release();
+//! ```
+use crate::consts::{RAM_COLUMNS, ST7567_PAGESTART_MASK};
+use crate::transport::Transport;
+use crate::{Command, Error, Flags, Pin, PixelBuffer, BUFFER_SIZE, ST7567};
+
+type RawResult<T, DC, RST, S> = Result<T, Error<DC, RST, S>>;
+
+/// The driver in raw mode, see the [module](self) docs.
+pub struct RawMode<
+    DC: Pin,
+    RST: Pin,
+    S: Transport,
+    B: PixelBuffer = [u8; BUFFER_SIZE],
+    const W: usize = 128,
+    const H: usize = 64,
+> {
+    display: ST7567<DC, RST, S, B, W, H>,
+}
+
+/// Writes display data from a selected page and column.
+pub struct PageWriter<
+    'a,
+    DC: Pin,
+    RST: Pin,
+    S: Transport,
+    B: PixelBuffer,
+    const W: usize,
+    const H: usize,
+> {
+    display: &'a mut ST7567<DC, RST, S, B, W, H>,
+    column: usize,
+}
+
+/// Writes display data in Read-Modify-Write mode, see
+/// [`RawMode::read_modify_write`].
+///
+/// Dropping it without calling [`finish`](Self::finish) leaves the
+/// controller in Read-Modify-Write mode until the next `show()`.
+pub struct RmwWriter<
+    'a,
+    DC: Pin,
+    RST: Pin,
+    S: Transport,
+    B: PixelBuffer,
+    const W: usize,
+    const H: usize,
+> {
+    page: PageWriter<'a, DC, RST, S, B, W, H>,
+}
+
+impl<DC: Pin, RST: Pin, S: Transport, B: PixelBuffer, const W: usize, const H: usize>
+    ST7567<DC, RST, S, B, W, H>
+{
+    /// Switch to raw RAM writes.
+    pub fn into_raw_mode(self) -> RawMode<DC, RST, S, B, W, H> {
+        RawMode { display: self }
+    }
+}
+
+impl<DC: Pin, RST: Pin, S: Transport, B: PixelBuffer, const W: usize, const H: usize>
+    RawMode<DC, RST, S, B, W, H>
+{
+    /// Select `page` (0 to 7) and `column` (0 to 131) for the following
+    /// writes.
+    ///
+    /// Pages out of range are handled according to the driver
+    /// [range policy](ST7567::set_range_policy), columns out of range are
+    /// rejected.
+    pub fn page(
+        &mut self,
+        page: u8,
+        column: u8,
+    ) -> RawResult<PageWriter<'_, DC, RST, S, B, W, H>, DC, RST, S> {
+        let page = self.display.fit(page, ST7567_PAGESTART_MASK)?;
+        let address = [Command::SetPage(page), Command::SetColumn(column)];
+        if address.iter().any(|command| command.encode().is_none()) {
+            return Err(Error::InvalidArgument);
+        }
+        if self.display.flags.get(Flags::RMW_PENDING) {
+            self.display.recover()?;
+        }
+        self.display.send_commands(&address)?;
+        Ok(PageWriter {
+            display: &mut self.display,
+            column: column as usize,
+        })
+    }
+
+    /// Select `page` and `column`, then enter Read-Modify-Write mode: the
+    /// column address goes back to `column` when the writer is finished.
+    pub fn read_modify_write(
+        &mut self,
+        page: u8,
+        column: u8,
+    ) -> RawResult<RmwWriter<'_, DC, RST, S, B, W, H>, DC, RST, S> {
+        let page = self.page(page, column)?;
+        page.display.flags.set(Flags::RMW_PENDING, true);
+        page.display
+            .send_commands(&[Command::EnterReadModifyWrite])?;
+        Ok(RmwWriter { page })
+    }
+
+    /// Go back to the buffered API.
+    ///
+    /// What `show()` knows about the controller RAM is forgotten, the next
+    /// flush sends every page.
+    pub fn release(mut self) -> ST7567<DC, RST, S, B, W, H> {
+        self.display.forget_controller_ram();
+        self.display
+    }
+}
+
+impl<DC: Pin, RST: Pin, S: Transport, B: PixelBuffer, const W: usize, const H: usize>
+    PageWriter<'_, DC, RST, S, B, W, H>
+{
+    /// Send display data, one byte per column, 8 pixels tall.
+    ///
+    /// Data that would run past the end of the RAM page is rejected with
+    /// [`Error::InvalidArgument`], nothing is sent.
+    pub fn write(&mut self, data: &[u8]) -> Result<(), Error<DC, RST, S>> {
+        let end = self.column.saturating_add(data.len());
+        if end > usize::from(RAM_COLUMNS) {
+            return Err(Error::InvalidArgument);
+        }
+        self.display.raw_data(data)?;
+        self.column = end;
+        Ok(())
+    }
+}
+
+impl<DC: Pin, RST: Pin, S: Transport, B: PixelBuffer, const W: usize, const H: usize>
+    RmwWriter<'_, DC, RST, S, B, W, H>
+{
+    /// Send display data, see [`PageWriter::write`].
+    pub fn write(&mut self, data: &[u8]) -> Result<(), Error<DC, RST, S>> {
+        self.page.write(data)
+    }
+
+    /// Leave Read-Modify-Write mode.
+    pub fn finish(self) -> Result<(), Error<DC, RST, S>> {
+        self.page.display.recover()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::*;
+    use crate::tests::create_test_st7567;
+
+    #[test]
+    fn test_page_writes() {
+        let mut raw = create_test_st7567().into_raw_mode();
+        let mut page = raw.page(2, 0x2a).unwrap();
+        assert!(page.write(&[1, 2]).is_ok());
+        assert!(page.write(&[3]).is_ok());
+        assert!(matches!(
+            page.write(&[0; 132 - 0x2a - 2]),
+            Err(Error::InvalidArgument)
+        ));
+        assert!(matches!(raw.page(0, 132), Err(Error::InvalidArgument)));
+        raw.display.set_range_policy(crate::RangePolicy::Reject);
+        assert!(matches!(raw.page(8, 0), Err(Error::InvalidArgument)));
+
+        let st7567 = raw.release();
+        assert_eq!(
+            st7567.spi.get_written_data(),
+            vec![0xb2, 0x0a, 0x12, 1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn test_read_modify_write() {
+        let mut raw = create_test_st7567().into_raw_mode();
+        let mut rmw = raw.read_modify_write(0, 0).unwrap();
+        assert!(rmw.write(&[0xff]).is_ok());
+        assert!(rmw.finish().is_ok());
+
+        // An unfinished sequence is closed by the next page selection
+        {
+            let mut rmw = raw.read_modify_write(1, 0).unwrap();
+            assert!(rmw.write(&[0xff]).is_ok());
+        }
+        assert!(raw.page(1, 1).is_ok());
+
+        let mut st7567 = raw.release();
+        assert_eq!(
+            st7567.spi.get_written_data(),
+            vec![
+                0xb0,
+                0x00,
+                0x10,
+                ST7567_ENTER_RMWMODE,
+                0xff,
+                ST7567_EXIT_RMWMODE,
+                0xb1,
+                0x00,
+                0x10,
+                ST7567_ENTER_RMWMODE,
+                0xff,
+                ST7567_EXIT_RMWMODE,
+                0xb1,
+                0x01,
+                0x10,
+            ]
+        );
+        st7567.spi.clear_written_data();
+        assert!(st7567.show().is_ok());
+        assert_eq!(st7567.spi.get_written_data()[0], ST7567_ENTER_RMWMODE);
+    }
+}
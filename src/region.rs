@@ -0,0 +1,341 @@
+//! Screen areas owned by independent components
+//!
+//! A [`Region`] is a named rectangle of the drawing area, kept by the
+//! component drawing into it along with its dirty state. Drawing goes
+//! through a [`RegionCanvas`], clipped to the region and in coordinates
+//! relative to it, so a component cannot clobber its neighbours. Each
+//! component flushes its own area when it is done:
+//!
+//! ```ignore
+//! let mut status = Region::new("statusbar", BoundingBox { x: 0, y: 0, width: 128, height: 8 });
+//! let mut body = Region::new("body", BoundingBox { x: 0, y: 8, width: 128, height: 56 });
+//! display.check_regions(&[&status, &body])?;
+//!
+//! let mut canvas = display.region(&mut status);
+//! canvas.clear();
+//! draw_battery(&mut canvas, level);
+//! display.show_region(&mut status)?;
+//! ```
+//!
+//! The controller RAM is written by pages of 8 rows. Regions whose top and
+//! bottom are not multiples of 8 share pages with their neighbours, and
+//! flushing one of them also sends the neighbour rows of those pages, as
+//! currently drawn.
+use crate::transport::Transport;
+use crate::{BoundingBox, Error, Pin, PixelBuffer, ST7567};
+
+/// Why a set of regions is rejected by [`ST7567::check_regions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionError {
+    /// The region does not fit in the drawing area
+    OutOfBounds(&'static str),
+    /// The two regions share pixels
+    Overlap(&'static str, &'static str),
+}
+
+/// A named area of the screen and its dirty state, see the [module](self)
+/// docs.
+///
+/// The area is in drawing coordinates, following the rotation of the
+/// driver.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    name: &'static str,
+    area: BoundingBox,
+    dirty: bool,
+}
+
+impl Region {
+    /// A region covering `area`. It starts dirty, as the controller RAM
+    /// content is unknown.
+    pub const fn new(name: &'static str, area: BoundingBox) -> Self {
+        Region {
+            name,
+            area,
+            dirty: true,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn area(&self) -> BoundingBox {
+        self.area
+    }
+
+    /// `true` when drawn into since the last [`ST7567::show_region`].
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// `true` when the two regions share pixels.
+    pub fn overlaps(&self, other: &Region) -> bool {
+        let (a, b) = (self.area, other.area);
+        let (a_right, a_bottom) = (a.x.saturating_add(a.width), a.y.saturating_add(a.height));
+        let (b_right, b_bottom) = (b.x.saturating_add(b.width), b.y.saturating_add(b.height));
+        a.x < b_right && b.x < a_right && a.y < b_bottom && b.y < a_bottom
+    }
+}
+
+/// Draws into a [`Region`], see [`ST7567::region`].
+pub struct RegionCanvas<
+    'a,
+    DC: Pin,
+    RST: Pin,
+    S: Transport,
+    B: PixelBuffer,
+    const W: usize,
+    const H: usize,
+> {
+    display: &'a mut ST7567<DC, RST, S, B, W, H>,
+    region: &'a mut Region,
+}
+
+impl<DC: Pin, RST: Pin, S: Transport, B: PixelBuffer, const W: usize, const H: usize>
+    RegionCanvas<'_, DC, RST, S, B, W, H>
+{
+    /// Width and height of the region, in pixels.
+    pub fn size(&self) -> (usize, usize) {
+        (self.region.area.width, self.region.area.height)
+    }
+
+    /// Set a pixel, relative to the top left corner of the region. Pixels
+    /// out of the region are ignored.
+    pub fn set_pixel(&mut self, x: usize, y: usize, value: bool) {
+        let area = self.region.area;
+        if x >= area.width || y >= area.height {
+            return;
+        }
+        if let (Some(x), Some(y)) = (area.x.checked_add(x), area.y.checked_add(y)) {
+            self.display.set_pixel(x, y, value);
+            self.region.dirty = true;
+        }
+    }
+
+    /// Clear the region.
+    pub fn clear(&mut self) {
+        let area = self.region.area;
+        self.display
+            .clear_region(area.x, area.y, area.width, area.height);
+        self.region.dirty = true;
+    }
+}
+
+impl<DC: Pin, RST: Pin, S: Transport, B: PixelBuffer, const W: usize, const H: usize>
+    ST7567<DC, RST, S, B, W, H>
+{
+    /// Draw into `region` only.
+    pub fn region<'a>(
+        &'a mut self,
+        region: &'a mut Region,
+    ) -> RegionCanvas<'a, DC, RST, S, B, W, H> {
+        RegionCanvas {
+            display: self,
+            region,
+        }
+    }
+
+    /// Check that `regions` fit in the drawing area and do not overlap.
+    pub fn check_regions(&self, regions: &[&Region]) -> Result<(), RegionError> {
+        let (width, height) = self.dimensions();
+        for (i, region) in regions.iter().enumerate() {
+            let area = region.area;
+            let fits = |start: usize, len: usize, max| matches!(start.checked_add(len), Some(end) if end <= max);
+            if !fits(area.x, area.width, width) || !fits(area.y, area.height, height) {
+                return Err(RegionError::OutOfBounds(region.name));
+            }
+            let others = regions.get(i.saturating_add(1)..).unwrap_or_default();
+            if let Some(other) = others.iter().find(|other| region.overlaps(other)) {
+                return Err(RegionError::Overlap(region.name, other.name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Send the pages and columns covering `region`, if it is dirty.
+    ///
+    /// The other regions, drawn or not, are left alone apart from the rows
+    /// sharing a page with `region`.
+    pub fn show_region(&mut self, region: &mut Region) -> Result<(), Error<DC, RST, S>> {
+        if !region.dirty {
+            return Ok(());
+        }
+        self.show_area(region.area)?;
+        region.dirty = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::*;
+    use crate::tests::create_test_st7567;
+    use crate::Rotation;
+
+    const STATUS: BoundingBox = BoundingBox {
+        x: 0,
+        y: 0,
+        width: 128,
+        height: 8,
+    };
+
+    const BODY: BoundingBox = BoundingBox {
+        x: 16,
+        y: 8,
+        width: 32,
+        height: 16,
+    };
+
+    #[test]
+    fn test_check_regions() {
+        let st7567 = create_test_st7567();
+        let status = Region::new("statusbar", STATUS);
+        let body = Region::new("body", BODY);
+        assert_eq!(st7567.check_regions(&[&status, &body]), Ok(()));
+
+        let popup = Region::new("popup", BoundingBox { y: 4, ..BODY });
+        assert_eq!(
+            st7567.check_regions(&[&status, &body, &popup]),
+            Err(RegionError::Overlap("statusbar", "popup"))
+        );
+        let tall = Region::new("tall", BoundingBox { height: 57, ..BODY });
+        assert_eq!(
+            st7567.check_regions(&[&tall]),
+            Err(RegionError::OutOfBounds("tall"))
+        );
+        let huge = Region::new(
+            "huge",
+            BoundingBox {
+                x: usize::MAX,
+                y: 1,
+                width: usize::MAX,
+                height: usize::MAX,
+            },
+        );
+        assert_eq!(
+            st7567.check_regions(&[&huge]),
+            Err(RegionError::OutOfBounds("huge"))
+        );
+        assert!(!huge.overlaps(&status));
+        let everything = Region::new(
+            "everything",
+            BoundingBox {
+                width: usize::MAX,
+                height: usize::MAX,
+                ..STATUS
+            },
+        );
+        assert!(everything.overlaps(&status) && status.overlaps(&everything));
+    }
+
+    #[test]
+    fn test_region_drawing() {
+        let mut st7567 = create_test_st7567();
+        let mut status = Region::new("statusbar", STATUS);
+        let mut body = Region::new("body", BODY);
+        assert!(st7567.show_region(&mut status).is_ok());
+        assert!(st7567.show_region(&mut body).is_ok());
+        assert!(!body.is_dirty());
+
+        // Drawing is clipped to the region
+        let mut canvas = st7567.region(&mut body);
+        assert_eq!(canvas.size(), (32, 16));
+        canvas.set_pixel(0, 0, true);
+        canvas.set_pixel(32, 0, true);
+        canvas.set_pixel(0, 16, true);
+        canvas.set_pixel(usize::MAX, usize::MAX, true);
+        assert!(body.is_dirty());
+        assert_eq!(st7567.buffer_stats().lit_pixels, 1);
+        assert_eq!(st7567.buf[128 + 16], 0x01);
+
+        // Only the dirty region, on its own pages and columns, is sent
+        st7567.spi.clear_written_data();
+        assert!(st7567.show_region(&mut status).is_ok());
+        assert!(st7567.show_region(&mut body).is_ok());
+        let written_data = st7567.spi.get_written_data();
+        assert_eq!(written_data.len(), 2 + 2 * (3 + 32));
+        assert_eq!(written_data[0], ST7567_ENTER_RMWMODE);
+        assert_eq!(written_data[1..4], [0xb1, 0x00, 0x11]);
+        assert_eq!(written_data[4], 0x01);
+        assert_eq!(written_data[36..39], [0xb2, 0x00, 0x11]);
+        assert!(!body.is_dirty());
+
+        // Clearing leaves the neighbours alone
+        st7567.fill(true);
+        st7567.region(&mut body).clear();
+        assert!(body.is_dirty());
+        assert_eq!(st7567.buffer_stats().lit_pixels, 128 * 64 - 32 * 16);
+        assert!(st7567.get_pixel(15, 8) && !st7567.get_pixel(16, 8));
+
+        // Areas running off the end of the address space are clipped
+        let mut huge = Region::new(
+            "huge",
+            BoundingBox {
+                x: 100,
+                y: 60,
+                width: usize::MAX,
+                height: usize::MAX,
+            },
+        );
+        let mut canvas = st7567.region(&mut huge);
+        canvas.clear();
+        canvas.set_pixel(usize::MAX - 1, 0, true);
+        assert!(st7567.show_region(&mut huge).is_ok());
+        assert!(!st7567.get_pixel(127, 63) && st7567.get_pixel(99, 63));
+    }
+
+    #[test]
+    fn test_regions_never_panic() {
+        let edges = [0, 1, 63, 64, 128, usize::MAX - 1, usize::MAX];
+        let mut st7567 = create_test_st7567();
+        let status = Region::new("statusbar", STATUS);
+        for &x in edges.iter() {
+            for &y in edges.iter() {
+                for &width in edges.iter() {
+                    for &height in edges.iter() {
+                        let area = BoundingBox {
+                            x,
+                            y,
+                            width,
+                            height,
+                        };
+                        let mut region = Region::new("edge", area);
+                        let _ = st7567.check_regions(&[&region, &status]);
+                        assert_eq!(region.overlaps(&status), status.overlaps(&region));
+                        let mut canvas = st7567.region(&mut region);
+                        canvas.set_pixel(x, y, true);
+                        canvas.set_pixel(usize::MAX, usize::MAX, true);
+                        canvas.clear();
+                        assert!(st7567.show_region(&mut region).is_ok());
+                        st7567.spi.clear_written_data();
+                    }
+                }
+            }
+        }
+        assert_eq!(st7567.buffer_stats().lit_pixels, 0);
+    }
+
+    #[test]
+    fn test_rotated_region() {
+        let mut st7567 = create_test_st7567();
+        assert!(st7567.set_rotation(Rotation::Rotate90).is_ok());
+        // Top 8 rows of the 64x128 drawing area, the right columns of the
+        // framebuffer
+        let mut top = Region::new(
+            "top",
+            BoundingBox {
+                x: 0,
+                y: 0,
+                width: 64,
+                height: 8,
+            },
+        );
+        st7567.spi.clear_written_data();
+        assert!(st7567.show_region(&mut top).is_ok());
+        let written_data = st7567.spi.get_written_data();
+        assert_eq!(written_data.len(), 2 + 8 * (3 + 8));
+        assert_eq!(written_data[1..4], [0xb0, 0x08, 0x17]);
+    }
+}
@@ -0,0 +1,326 @@
+//! Line-by-line rendering of color or grayscale content
+//!
+//! Software renderers of UI toolkits produce one line of color pixels at a
+//! time. [`LineRenderer`] turns such lines into framebuffer pixels using a 4x4
+//! ordered (Bayer) dither, so gradients and anti-aliased text keep some of
+//! their shading on the 1 bit panel.
+//!
+//! With the `slint` feature, [`LineRenderer`] implements the
+//! `LineBufferProvider` trait of Slint's software renderer:
+//!
+//! ```ignore
+//! renderer.render_by_line(display.line_renderer());
+//! display.show()?;
+//! ```
+//!
+//! Other toolkits can feed lines of luminance to
+//! [`LineRenderer::write_luma`].
+//!
+//! Photos and camera snapshots often read better with a plain threshold than
+//! dithered. A [`Histogram`] of the image picks one automatically (Otsu's
+//! method), in a first pass over the lines:
+//!
+//! ```
+//! use st7567::render::{Conversion, Histogram, LineRenderer};
+//! # let image = [[40u8; 128], [200; 128]];
+//! # let mut buf = [0u8; st7567::BUFFER_SIZE];
+//!
+//! let mut histogram = Histogram::new();
+//! for line in image.iter() {
+//!     histogram.add(line);
+//! }
+//! let mut renderer = LineRenderer::new(&mut buf);
+//! renderer.set_conversion(Conversion::Threshold(histogram.otsu_threshold()));
+//! for (y, line) in image.iter().enumerate() {
+//!     renderer.write_luma(y, 0, line);
+//! }
+//! ```
+use crate::buffer::{Geometry, PixelBuffer};
+use crate::Rotation;
+
+/// 4x4 Bayer matrix, thresholds spread over `0..16`
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// `true` if a pixel of luminance `luma` (0 black, 255 white) at `(x, y)`
+/// should be turned on, i.e. drawn dark.
+pub fn dither(x: usize, y: usize, luma: u8) -> bool {
+    let level = BAYER
+        .get(y % 4)
+        .and_then(|row| row.get(x % 4))
+        .copied()
+        .unwrap_or(0);
+    luma < level.saturating_mul(16).saturating_add(8)
+}
+
+/// Perceived luminance of an RGB565 color, from 0 (black) to 255 (white).
+// Channels are at most 255 and the weights add up to 256
+#[allow(clippy::arithmetic_side_effects)]
+pub fn rgb565_luma(color: u16) -> u8 {
+    let r = u32::from(color >> 11) * 255 / 31;
+    let g = u32::from((color >> 5) & 0x3f) * 255 / 63;
+    let b = u32::from(color & 0x1f) * 255 / 31;
+    ((77 * r + 150 * g + 29 * b) >> 8) as u8
+}
+
+/// How [`LineRenderer`] turns luminance into pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Conversion {
+    /// 4x4 ordered dither, see [`dither`]
+    #[default]
+    Dither,
+    /// Pixels darker than the given luminance are turned on
+    Threshold(u8),
+}
+
+/// Luminance histogram of an image, to pick a threshold for
+/// [`Conversion::Threshold`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histogram {
+    bins: [u32; 256],
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Histogram {
+    pub const fn new() -> Self {
+        Histogram { bins: [0; 256] }
+    }
+
+    /// Count a run of luminance values.
+    pub fn add(&mut self, luma: &[u8]) {
+        for &luma in luma {
+            if let Some(bin) = self.bins.get_mut(usize::from(luma)) {
+                *bin = bin.saturating_add(1);
+            }
+        }
+    }
+
+    /// Threshold separating the dark and light pixels best, by Otsu's
+    /// method: the one maximizing the variance between both classes.
+    ///
+    /// 128 when the image has a single luminance, or is empty.
+    // At most 2^40 pixels of 8 bit luma: sums fit in 48 bits, the variance
+    // in 112 bits
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn otsu_threshold(&self) -> u8 {
+        let total: u64 = self.bins.iter().map(|&count| u64::from(count)).sum();
+        let total_sum: u64 = (0..)
+            .zip(self.bins.iter())
+            .map(|(luma, &count)| luma * u64::from(count))
+            .sum();
+        let (mut dark, mut dark_sum) = (0u64, 0u64);
+        let (mut best, mut best_variance) = (128, 0u128);
+        for (threshold, &count) in (1..=255u8).zip(self.bins.iter()) {
+            dark += u64::from(count);
+            dark_sum += u64::from(threshold - 1) * u64::from(count);
+            let light = total - dark;
+            if dark == 0 || light == 0 {
+                continue;
+            }
+            // Class means with 8 fractional bits
+            let dark_mean = (dark_sum << 8) / dark;
+            let light_mean = ((total_sum - dark_sum) << 8) / light;
+            let spread = u128::from(light_mean - dark_mean);
+            let variance = u128::from(dark) * u128::from(light) * spread * spread;
+            if variance > best_variance {
+                best = threshold;
+                best_variance = variance;
+            }
+        }
+        best
+    }
+}
+
+/// Writes lines into a framebuffer, dithered by default, see the
+/// [module](self) docs.
+///
+/// Lines are in drawing coordinates, which follow a [`Rotation`].
+pub struct LineRenderer<'a, B: PixelBuffer + ?Sized> {
+    buf: &'a mut B,
+    rotation: Rotation,
+    geometry: Geometry,
+    conversion: Conversion,
+}
+
+impl<'a, B: PixelBuffer + ?Sized> LineRenderer<'a, B> {
+    pub fn new(buf: &'a mut B) -> Self {
+        Self::with_rotation(buf, Rotation::default())
+    }
+
+    pub fn with_rotation(buf: &'a mut B, rotation: Rotation) -> Self {
+        Self::with_geometry(buf, rotation, Geometry::DEFAULT)
+    }
+
+    pub(crate) fn with_geometry(buf: &'a mut B, rotation: Rotation, geometry: Geometry) -> Self {
+        Self {
+            buf,
+            rotation,
+            geometry,
+            conversion: Conversion::default(),
+        }
+    }
+
+    /// Use `conversion` for the following lines, dithering by default.
+    pub fn set_conversion(&mut self, conversion: Conversion) {
+        self.conversion = conversion;
+    }
+
+    /// Write a run of luminance values on line `y`, starting at column `x`.
+    ///
+    /// Values falling outside of the screen are ignored.
+    pub fn write_luma(&mut self, y: usize, x: usize, luma: &[u8]) {
+        for (x, &luma) in (x..).zip(luma) {
+            self.set(x, y, self.shade(x, y, luma));
+        }
+    }
+
+    /// Whether a pixel of luminance `luma` is turned on.
+    fn shade(&self, x: usize, y: usize, luma: u8) -> bool {
+        match self.conversion {
+            Conversion::Dither => dither(x, y, luma),
+            Conversion::Threshold(threshold) => luma < threshold,
+        }
+    }
+
+    #[cfg(feature = "slint")]
+    fn get(&self, x: usize, y: usize) -> bool {
+        match self.rotation.to_buffer(self.geometry, x, y) {
+            Some((x, y)) => self.geometry.get(self.buf.as_bytes(), x, y),
+            None => false,
+        }
+    }
+
+    fn set(&mut self, x: usize, y: usize, value: bool) {
+        if let Some((x, y)) = self.rotation.to_buffer(self.geometry, x, y) {
+            self.geometry.set(self.buf.as_bytes_mut(), x, y, value);
+        }
+    }
+}
+
+#[cfg(feature = "slint")]
+impl<B: PixelBuffer + ?Sized> slint::platform::software_renderer::LineBufferProvider
+    for LineRenderer<'_, B>
+{
+    type TargetPixel = slint::platform::software_renderer::Rgb565Pixel;
+
+    fn process_line(
+        &mut self,
+        line: usize,
+        range: core::ops::Range<usize>,
+        render_fn: impl FnOnce(&mut [Self::TargetPixel]),
+    ) {
+        use slint::platform::software_renderer::Rgb565Pixel;
+
+        // The renderer blends onto the line, start from the current content
+        let mut pixels = [Rgb565Pixel(0xffff); crate::consts::RAM_COLUMNS as usize];
+        for (x, pixel) in pixels.iter_mut().enumerate() {
+            if self.get(x, line) {
+                *pixel = Rgb565Pixel(0);
+            }
+        }
+        let pixels = pixels.get_mut(range.clone()).unwrap_or_default();
+        render_fn(pixels);
+        for (x, pixel) in range.zip(pixels.iter()) {
+            self.set(x, line, self.shade(x, line, rgb565_luma(pixel.0)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::Geometry;
+    use crate::{BufferStats, BUFFER_SIZE, WIDTH};
+
+    #[test]
+    fn test_luma() {
+        assert_eq!(rgb565_luma(0x0000), 0);
+        assert_eq!(rgb565_luma(0xffff), 255);
+        // Green weighs more than blue
+        assert!(rgb565_luma(0x07e0) > rgb565_luma(0x001f));
+    }
+
+    #[test]
+    fn test_dither_levels() {
+        let mut buf = [0u8; BUFFER_SIZE];
+        let mut renderer = LineRenderer::new(&mut buf);
+        for y in 0..8 {
+            renderer.write_luma(y, 0, &[0; 4]);
+            renderer.write_luma(y, 4, &[255; 4]);
+            renderer.write_luma(y, 8, &[128; 4]);
+        }
+        // Off-screen values are ignored
+        renderer.write_luma(0, WIDTH as usize - 2, &[0; 8]);
+        let stats = BufferStats::of(&buf, Geometry::DEFAULT);
+        assert_eq!(stats.lit_pixels, 32 + 16 + 2);
+        assert!((0..4).all(|x| buf.get(x, 5)));
+        assert!((4..8).all(|x| !buf.get(x, 5)));
+    }
+
+    #[test]
+    fn test_otsu_threshold() {
+        let mut histogram = Histogram::new();
+        assert_eq!(histogram.otsu_threshold(), 128);
+        histogram.add(&[200; 10]);
+        assert_eq!(histogram.otsu_threshold(), 128);
+
+        // Dark text on a light but uneven background
+        histogram.add(&[20, 25, 30, 30, 35]);
+        histogram.add(&[180, 190, 210, 220]);
+        let threshold = histogram.otsu_threshold();
+        assert!((36..=180).contains(&threshold));
+
+        // A dim photo: dithering with the fixed levels would be mostly dark
+        let mut histogram = Histogram::new();
+        histogram.add(&[10; 50]);
+        histogram.add(&[60; 50]);
+        let threshold = histogram.otsu_threshold();
+        assert!((11..=60).contains(&threshold));
+
+        let mut buf = [0u8; BUFFER_SIZE];
+        let mut renderer = LineRenderer::new(&mut buf);
+        renderer.set_conversion(Conversion::Threshold(threshold));
+        renderer.write_luma(0, 0, &[10, 60, 10, 60]);
+        assert_eq!(
+            (0..4).map(|x| buf.get(x, 0)).collect::<Vec<_>>(),
+            [true, false, true, false]
+        );
+    }
+
+    #[test]
+    fn test_rotated_lines() {
+        let mut buf = [0u8; BUFFER_SIZE];
+        let mut renderer = LineRenderer::with_rotation(&mut buf, Rotation::Rotate90);
+        renderer.write_luma(127, 0, &[0; 64]);
+        assert!((0..64).all(|y| buf.get(0, y)));
+        assert_eq!(BufferStats::of(&buf, Geometry::DEFAULT).lit_pixels, 64);
+    }
+
+    #[test]
+    #[cfg(feature = "slint")]
+    fn test_slint_line_buffer_provider() {
+        use slint::platform::software_renderer::{LineBufferProvider, Rgb565Pixel};
+
+        let mut buf = [0u8; BUFFER_SIZE];
+        buf.set(0, 3, true);
+        buf.set(20, 3, true);
+        let mut renderer = LineRenderer::new(&mut buf);
+        renderer.process_line(3, 10..30, |pixels| {
+            assert_eq!(pixels.len(), 20);
+            // Existing content is handed to the renderer
+            assert_eq!(pixels[10], Rgb565Pixel(0));
+            assert_eq!(pixels[11], Rgb565Pixel(0xffff));
+            for pixel in pixels.iter_mut().take(5) {
+                *pixel = Rgb565Pixel(0);
+            }
+        });
+        assert!(buf.get(0, 3));
+        assert!((10..15).all(|x| buf.get(x, 3)));
+        assert!(buf.get(20, 3));
+        assert_eq!((15..30).filter(|&x| buf.get(x, 3)).count(), 1);
+    }
+}
@@ -0,0 +1,172 @@
+//! Retries of transient transport errors
+//!
+//! On a shared bus, a transfer can fail because another device holds the
+//! bus, and succeed when tried again. [`Retry`] wraps the SPI device, or a
+//! DC/RST pin, and retries failed operations according to a
+//! [`RetryPolicy`], waiting with the given delay in between:
+//!
+//! ```ignore
+//! let policy = RetryPolicy { attempts: 3, backoff_us: 200 };
+//! let spi = Retry::new(spi, delay, policy);
+//! let mut display = ST7567::new(spi, dc, rst);
+//! ```
+//!
+//! A failed SPI transaction is sent again as a whole. This suits errors
+//! raised before anything is clocked out, like a busy bus: a transfer that
+//! broke halfway leaves the controller column pointer advanced, and only the
+//! next `show()` repairs the frame.
+use crate::{Pin, PinState};
+use embedded_hal::delay::DelayNs;
+use embedded_hal::spi::{ErrorType, Operation, SpiDevice};
+
+/// How often and how patiently [`Retry`] tries again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts in total, including the first one. 0 is taken as 1.
+    pub attempts: u8,
+    /// Wait before the first retry, doubled before each following one
+    pub backoff_us: u32,
+}
+
+impl Default for RetryPolicy {
+    /// No retry.
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: 1,
+            backoff_us: 0,
+        }
+    }
+}
+
+/// An SPI device or pin whose operations are retried, see the
+/// [module](self) docs.
+pub struct Retry<T, D: DelayNs> {
+    inner: T,
+    delay: D,
+    policy: RetryPolicy,
+}
+
+impl<T, D: DelayNs> Retry<T, D> {
+    pub fn new(inner: T, delay: D, policy: RetryPolicy) -> Self {
+        Retry {
+            inner,
+            delay,
+            policy,
+        }
+    }
+
+    /// The wrapped device or pin.
+    pub fn release(self) -> T {
+        self.inner
+    }
+
+    /// Run `operation` until it succeeds or the attempts are exhausted,
+    /// returning the last error.
+    fn run<R, E, F: FnMut(&mut T) -> Result<R, E>>(&mut self, mut operation: F) -> Result<R, E> {
+        let mut backoff_us = self.policy.backoff_us;
+        let mut attempts = self.policy.attempts.max(1);
+        loop {
+            attempts = attempts.saturating_sub(1);
+            match operation(&mut self.inner) {
+                Err(_) if attempts > 0 => {
+                    self.delay.delay_us(backoff_us);
+                    backoff_us = backoff_us.saturating_mul(2);
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl<S: ErrorType, D: DelayNs> ErrorType for Retry<S, D> {
+    type Error = S::Error;
+}
+
+impl<Word: Copy + 'static, S: SpiDevice<Word>, D: DelayNs> SpiDevice<Word> for Retry<S, D> {
+    fn transaction(&mut self, operations: &mut [Operation<'_, Word>]) -> Result<(), Self::Error> {
+        self.run(|spi| spi.transaction(operations))
+    }
+}
+
+impl<P: Pin, D: DelayNs> Pin for Retry<P, D> {
+    type Error = P::Error;
+
+    fn set_value(&mut self, pin_state: PinState) -> Result<(), Self::Error> {
+        self.run(|pin| pin.set_value(pin_state.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{MockDelay, MockError, MockPin, MockSpiDevice};
+    use crate::{Error, ST7567};
+
+    /// Fails the first `failures` transactions.
+    struct FlakySpi {
+        failures: usize,
+        inner: MockSpiDevice,
+    }
+
+    impl ErrorType for FlakySpi {
+        type Error = MockError;
+    }
+
+    impl SpiDevice for FlakySpi {
+        fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), MockError> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(MockError::SpiError);
+            }
+            self.inner.transaction(operations)
+        }
+    }
+
+    fn flaky(failures: usize, attempts: u8) -> Retry<FlakySpi, MockDelay> {
+        let spi = FlakySpi {
+            failures,
+            inner: MockSpiDevice::new(),
+        };
+        let policy = RetryPolicy {
+            attempts,
+            backoff_us: 100,
+        };
+        Retry::new(spi, MockDelay::new(), policy)
+    }
+
+    #[test]
+    fn test_retry_spi() {
+        let mut st7567 = ST7567::new(flaky(2, 3), MockPin::new(), MockPin::new());
+        assert!(st7567.init().is_ok());
+        assert_eq!(
+            st7567.spi.inner.inner.get_written_data(),
+            crate::sequence::GFX_HAT
+        );
+        assert_eq!(st7567.spi.delay.delays_ns, [100_000, 200_000]);
+
+        let mut st7567 = ST7567::new(flaky(3, 3), MockPin::new(), MockPin::new());
+        assert!(matches!(st7567.init(), Err(Error::SpiError(_))));
+
+        // The default policy does not retry
+        let mut spi = flaky(1, 3);
+        spi.policy = RetryPolicy::default();
+        assert!(spi.transaction(&mut []).is_err());
+        assert!(spi.transaction(&mut []).is_ok());
+        assert!(spi.delay.delays_ns.is_empty());
+    }
+
+    #[test]
+    fn test_retry_pin() {
+        let policy = RetryPolicy {
+            attempts: 2,
+            backoff_us: 0,
+        };
+        let mut pin = Retry::new(MockPin::new(), MockDelay::new(), policy);
+        pin.inner.set_fail(true);
+        assert!(pin.set_value(PinState::High).is_err());
+        assert_eq!(pin.delay.delays_ns.len(), 1);
+        pin.inner.set_fail(false);
+        assert!(pin.set_value(PinState::Low).is_ok());
+        assert_eq!(pin.release().get_states(), [PinState::Low]);
+    }
+}
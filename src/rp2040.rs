@@ -0,0 +1,62 @@
+//! RP2040 quickstart, using `rp2040-hal`
+//!
+//! [`st7567`] checks the bus settings against what the controller supports,
+//! configures a disabled SPI peripheral with them, wraps it with its chip
+//! select pin into an [`ExclusiveDevice`], and creates the driver:
+//!
+//! ```ignore
+//! let spi = hal::Spi::<_, _, _, 8>::new(pac.SPI0, (mosi, sclk));
+//! let mut display = st7567::rp2040::st7567(
+//!     spi,
+//!     &mut pac.RESETS,
+//!     clocks.peripheral_clock.freq(),
+//!     SpiConfig::default(),
+//!     pins.gpio17.into_push_pull_output(),
+//!     pins.gpio20.into_push_pull_output(),
+//!     pins.gpio21.into_push_pull_output(),
+//! )?;
+//! display.reset(&mut timer)?;
+//! display.init()?;
+//! ```
+use crate::{Pin, SetupError, SpiConfig, ST7567};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_bus::spi::{ExclusiveDevice, NoDelay};
+use rp2040_hal::fugit::{HertzU32, RateExtU32};
+use rp2040_hal::pac::RESETS;
+use rp2040_hal::spi::{Disabled, Enabled, Spi, SpiDevice, ValidSpiPinout};
+
+/// The SPI device type built by [`st7567`].
+pub type Rp2040Spi<D, P, CS> = ExclusiveDevice<Spi<Enabled, D, P, 8>, CS, NoDelay>;
+
+/// The driver type built by [`st7567`].
+pub type Rp2040ST7567<D, P, CS, DC, RST> = ST7567<DC, RST, Rp2040Spi<D, P, CS>>;
+
+/// Configure `spi` with `config` and create a driver using it.
+///
+/// `peripheral_clock` is the frequency of the peripheral clock feeding the
+/// SPI block. Fails with [`SetupError::Config`] if `config` does not suit
+/// the controller, see [`SpiConfig::check`], and with [`SetupError::Hal`]
+/// if the chip select pin cannot be driven high.
+// The error depends on the chip select pin, no alias makes it shorter
+#[allow(clippy::type_complexity)]
+pub fn st7567<D, P, CS, DC, RST>(
+    spi: Spi<Disabled, D, P, 8>,
+    resets: &mut RESETS,
+    peripheral_clock: HertzU32,
+    config: SpiConfig,
+    cs: CS,
+    dc: DC,
+    rst: RST,
+) -> Result<Rp2040ST7567<D, P, CS, DC, RST>, SetupError<CS::Error>>
+where
+    D: SpiDevice,
+    P: ValidSpiPinout<D>,
+    CS: OutputPin,
+    DC: Pin,
+    RST: Pin,
+{
+    config.check()?;
+    let bus = spi.init(resets, peripheral_clock, config.speed_hz.Hz(), config.mode);
+    let device = ExclusiveDevice::new_no_delay(bus, cs).map_err(SetupError::Hal)?;
+    Ok(ST7567::new(device, dc, rst))
+}
@@ -0,0 +1,166 @@
+//! Command grammar of the controller, checked at compile time
+//!
+//! A wrong byte in an init sequence does not fail, it shows as a blank or
+//! garbled screen. [`validate`] decodes the sequence with
+//! [`Command::decode`], which knows which opcodes exist, which ones take a
+//! parameter byte and the valid range of those parameters. Both are
+//! `const fn`, so the [`init_sequence!`](crate::init_sequence) macro can
+//! reject a bad sequence while compiling:
+//!
+//! ```
+//! const CLONE_PANEL: &[u8] = st7567::init_sequence![
+//!     0xa2,       // 1/9 bias
+//!     0xa0, 0xc8, // SEG normal, COM reverse
+//!     0x2f,       // power circuits on
+//!     0x81, 0x20, // contrast
+//!     0xaf,       // display on
+//! ];
+//! ```
+//!
+//! ```compile_fail
+//! // 0x81 takes a contrast byte, 0x40 is out of its range
+//! const BROKEN: &[u8] = st7567::init_sequence![0x81, 0x40, 0xaf];
+//! ```
+use crate::command::{Command, DecodeError};
+
+/// Why a command sequence is rejected by [`validate`].
+///
+/// `index` is the offset of the offending byte in the sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceError {
+    /// The byte is not an opcode of the controller
+    UnknownOpcode { index: usize },
+    /// The sequence ends before the parameter of the opcode at `index`
+    MissingParameter { index: usize },
+    /// The parameter byte is out of range for its opcode
+    ParameterOutOfRange { index: usize },
+}
+
+/// Check a command sequence against the controller command grammar.
+///
+/// Usable in constant expressions, see the [module](self) docs.
+pub const fn validate(commands: &[u8]) -> Result<(), SequenceError> {
+    let mut index = 0;
+    let mut rest = commands;
+    while !rest.is_empty() {
+        let len = match Command::decode(rest) {
+            Ok((_, len)) => len,
+            Err(DecodeError::UnknownOpcode) => return Err(SequenceError::UnknownOpcode { index }),
+            Err(DecodeError::MissingParameter) => {
+                return Err(SequenceError::MissingParameter { index })
+            }
+            Err(DecodeError::ParameterOutOfRange) => {
+                return Err(SequenceError::ParameterOutOfRange {
+                    index: index.saturating_add(1),
+                })
+            }
+        };
+        rest = match rest {
+            [_, _, tail @ ..] if len == 2 => tail,
+            [_, tail @ ..] => tail,
+            [] => rest,
+        };
+        index = index.saturating_add(len);
+    }
+    Ok(())
+}
+
+/// A `&'static [u8]` command sequence, rejected at compile time if
+/// [`validate`](crate::sequence::validate) fails on it.
+#[macro_export]
+macro_rules! init_sequence {
+    ($($byte:expr),* $(,)?) => {{
+        const SEQUENCE: &[u8] = &[$($byte),*];
+        const _: () = assert!(
+            $crate::sequence::validate(SEQUENCE).is_ok(),
+            "invalid ST7567 command sequence"
+        );
+        SEQUENCE
+    }};
+}
+
+/// Commands sent by [`ST7567::init`](crate::ST7567::init), see
+/// [`ST7567::set_init_sequence`](crate::ST7567::set_init_sequence).
+///
+/// Clone panels often need a slightly different list: build it with
+/// [`init_sequence!`](crate::init_sequence) so it is checked while compiling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InitSequence {
+    /// The built-in sequence, following the driver settings (bias, scan
+    /// directions, regulation ratio, contrast)
+    #[default]
+    BuiltIn,
+    /// Send these commands instead of the built-in sequence. The driver
+    /// settings are not sent, the list must turn the display on by itself.
+    Replace(&'static [u8]),
+    /// Send these commands after the built-in sequence
+    Extend(&'static [u8]),
+}
+
+/// What [`ST7567::init`](crate::ST7567::init) sends with the default
+/// settings, for the Pimoroni GFX HAT.
+pub const GFX_HAT: &[u8] = init_sequence![
+    0xa3, // 1/7 bias
+    0xa0, // SEG normal
+    0xc8, // COM reverse
+    0xa6, // normal display
+    0xa4, // show the RAM
+    0x40, // start line 0
+    0x2f, // power circuits on
+    0x23, // regulation ratio 3
+    0xaf, // display on
+    0x81, 40, // contrast
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{create_test_st7567, MockDelay};
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(validate(&[]), Ok(()));
+        assert_eq!(validate(GFX_HAT), Ok(()));
+        assert_eq!(validate(&[0xf8, 0x01, 0xb8, 0x18]), Ok(()));
+        assert_eq!(
+            validate(&[0xaf, 0x19]),
+            Err(SequenceError::UnknownOpcode { index: 1 })
+        );
+        assert_eq!(
+            validate(&[0xaf, 0x81]),
+            Err(SequenceError::MissingParameter { index: 1 })
+        );
+        assert_eq!(
+            validate(&[0xf8, 0x02]),
+            Err(SequenceError::ParameterOutOfRange { index: 1 })
+        );
+        // A parameter byte is not read as an opcode
+        assert_eq!(validate(&[0x81, 0x00, 0xaf]), Ok(()));
+    }
+
+    #[test]
+    fn test_gfx_hat_matches_init() {
+        let mut st7567 = create_test_st7567();
+        assert!(st7567.init().is_ok());
+        assert_eq!(st7567.spi.get_written_data(), GFX_HAT);
+    }
+
+    #[test]
+    fn test_init_sequence_override() {
+        const CLONE: &[u8] = init_sequence![0xa2, 0x2f, 0xaf];
+
+        let mut st7567 = create_test_st7567();
+        st7567.set_init_sequence(InitSequence::Replace(CLONE));
+        assert!(st7567.init().is_ok());
+        assert!(st7567.init_staged(&mut MockDelay::new()).is_ok());
+        assert_eq!(st7567.spi.get_written_data(), [CLONE, CLONE].concat());
+
+        let mut st7567 = create_test_st7567();
+        st7567.set_init_sequence(InitSequence::Extend(&[0xf8, 0x01]));
+        assert!(st7567.init().is_ok());
+        assert_eq!(
+            st7567.spi.get_written_data(),
+            [GFX_HAT, &[0xf8, 0x01]].concat()
+        );
+    }
+}
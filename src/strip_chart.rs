@@ -0,0 +1,447 @@
+//! Pen recorder view for long-running sensor logs
+//!
+//! [`StripChart`] plots one sample per column across a rectangle of the
+//! screen, like the pen of a chart recorder: when it reaches the right edge
+//! it starts over from the left, erasing the oldest samples just ahead of
+//! it. A sample only changes a few columns, and only those are sent to the
+//! controller, so the chart keeps up with the sensor even on a slow bus.
+//!
+//! The bottom row is the time axis, with a tick every
+//! [`tick_interval`](StripChart::set_tick_interval) samples. The value axis
+//! is labelled with [`label`](StripChart::label), left of the chart. Events
+//! are marked with a dotted vertical line under a glyph:
+//!
+//! ```ignore
+//! let area = BoundingBox { x: 0, y: 16, width: 128, height: 48 };
+//! let mut chart = StripChart::new(area, 0, 1000);
+//! chart.set_tick_interval(60);
+//! chart.label(&mut display, 0, &ZERO_GLYPH)?;
+//! chart.label(&mut display, 1000, &MAX_GLYPH)?;
+//! loop {
+//!     chart.push(&mut display, sensor.read()?)?;
+//!     if door.opened() {
+//!         chart.mark(&mut display, &DOOR_GLYPH)?;
+//!     }
+//!     delay.delay_ms(1000);
+//! }
+//! ```
+//!
+//! The driver has no text rendering, so labels and markers are bitmaps
+//! provided by the application, e.g. rendered from a font at build time.
+//! Labels of the time axis would scroll with the pen and are not drawn:
+//! the ticks give the time scale.
+use crate::bitmap::Bitmap;
+use crate::transport::Transport;
+use crate::{BoundingBox, Error, Pin, PixelBuffer, ST7567};
+
+/// Columns erased ahead of the pen, showing where the newest sample is.
+const GAP: usize = 2;
+
+/// Rows at the bottom of the chart taken by the time axis and its ticks.
+const AXIS_ROWS: usize = 2;
+
+/// A sweeping chart of samples, see the [module](self) docs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StripChart {
+    /// In drawing coordinates
+    area: BoundingBox,
+    min: i32,
+    max: i32,
+    /// Samples between two ticks, 0 for none
+    tick_interval: u32,
+    /// Column of the next sample, relative to the area
+    cursor: usize,
+    /// Row of the previous sample, `None` at the left edge
+    last_row: Option<usize>,
+    samples: u32,
+}
+
+impl StripChart {
+    /// A chart over `area`, in drawing coordinates, scaled for values from
+    /// `min` (bottom) to `max` (top). Values out of the range are drawn at
+    /// its edges.
+    ///
+    /// Areas less than 3 rows high, too short for a plot above the axis,
+    /// are left empty.
+    pub fn new(area: BoundingBox, min: i32, max: i32) -> Self {
+        let height = match area.height.min(usize::MAX.saturating_sub(area.y)) {
+            height if height <= AXIS_ROWS => 0,
+            height => height,
+        };
+        StripChart {
+            area: BoundingBox {
+                width: area.width.min(usize::MAX.saturating_sub(area.x)),
+                height,
+                ..area
+            },
+            min: min.min(max),
+            max: max.max(min),
+            tick_interval: 0,
+            cursor: 0,
+            last_row: None,
+            samples: 0,
+        }
+    }
+
+    pub fn area(&self) -> BoundingBox {
+        self.area
+    }
+
+    /// Draw a tick on the time axis every `samples` samples, counted from
+    /// the next one. 0 removes the ticks (the default).
+    pub fn set_tick_interval(&mut self, samples: u32) {
+        self.tick_interval = samples;
+        self.samples = 0;
+    }
+
+    /// Drawing row of `value` on the chart scale.
+    pub fn value_y(&self, value: i32) -> usize {
+        self.area.y.saturating_add(self.row(value))
+    }
+
+    /// Row of `value`, relative to the area.
+    fn row(&self, value: i32) -> usize {
+        let rows = i128::from(self.area.height.saturating_sub(AXIS_ROWS + 1) as u64);
+        let max = i128::from(self.max);
+        let span = max.saturating_sub(i128::from(self.min)).max(1);
+        let value = i128::from(value.clamp(self.min, self.max));
+        max.saturating_sub(value)
+            .saturating_mul(rows)
+            .checked_div(span)
+            .unwrap_or(0) as usize
+    }
+
+    /// Plot `value` at the pen and send the columns that changed.
+    ///
+    /// Consecutive samples are joined by a vertical segment, so steep
+    /// changes stay readable.
+    pub fn push<DC, RST, S, B, const W: usize, const H: usize>(
+        &mut self,
+        display: &mut ST7567<DC, RST, S, B, W, H>,
+        value: i32,
+    ) -> Result<(), Error<DC, RST, S>>
+    where
+        DC: Pin,
+        RST: Pin,
+        S: Transport,
+        B: PixelBuffer,
+    {
+        let BoundingBox {
+            x,
+            y,
+            width,
+            height,
+        } = self.area;
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        let column = x.saturating_add(self.cursor);
+        display.clear_region(column, y, 1, height);
+
+        let row = self.row(value);
+        let top = self.last_row.map_or(row, |last| last.min(row));
+        let bottom = self.last_row.map_or(row, |last| last.max(row));
+        let rows = bottom.saturating_sub(top).saturating_add(1);
+        display.fill_region(column, y.saturating_add(top), 1, rows, true);
+
+        let axis = y.saturating_add(height.saturating_sub(1));
+        display.set_pixel(column, axis, true);
+        if self.samples.checked_rem(self.tick_interval) == Some(0) {
+            display.set_pixel(column, axis.saturating_sub(1), true);
+        }
+
+        // Erase ahead of the pen, wrapping to the left edge
+        let end = self
+            .cursor
+            .saturating_add(GAP.min(width.saturating_sub(1)))
+            .saturating_add(1);
+        // Columns from the pen to the right edge of the chart
+        let ahead = end.min(width).saturating_sub(self.cursor);
+        display.clear_region(column.saturating_add(1), y, ahead.saturating_sub(1), height);
+        let wrapped = end.saturating_sub(width);
+        display.clear_region(x, y, wrapped, height);
+
+        let changed = |left, columns| BoundingBox {
+            x: left,
+            y,
+            width: columns,
+            height,
+        };
+        display.show_area(changed(column, ahead))?;
+        display.show_area(changed(x, wrapped))?;
+
+        self.samples = self.samples.wrapping_add(1);
+        if self.tick_interval > 0 && self.samples == self.tick_interval {
+            self.samples = 0;
+        }
+        self.cursor = self.cursor.saturating_add(1);
+        self.last_row = Some(row);
+        if self.cursor == width {
+            self.cursor = 0;
+            self.last_row = None;
+        }
+        Ok(())
+    }
+
+    /// Mark an event at the newest sample: a dotted line across the plot,
+    /// under `glyph` drawn from the top of the chart, then send the columns
+    /// that changed.
+    ///
+    /// The glyph extends to the right of the line and is clipped to the
+    /// chart. Like the samples, it is erased when the pen comes round.
+    pub fn mark<DC, RST, S, B, const W: usize, const H: usize>(
+        &mut self,
+        display: &mut ST7567<DC, RST, S, B, W, H>,
+        glyph: &Bitmap,
+    ) -> Result<(), Error<DC, RST, S>>
+    where
+        DC: Pin,
+        RST: Pin,
+        S: Transport,
+        B: PixelBuffer,
+    {
+        let BoundingBox {
+            x,
+            y,
+            width,
+            height,
+        } = self.area;
+        if width == 0 || height <= AXIS_ROWS {
+            return Ok(());
+        }
+        let newest = self
+            .cursor
+            .checked_sub(1)
+            .unwrap_or(width.saturating_sub(1));
+        let column = x.saturating_add(newest);
+        let plot_rows = height.saturating_sub(AXIS_ROWS);
+        // Only the rows on screen, the area may extend past them
+        let visible = display.dimensions().1.saturating_sub(y);
+        for row in (glyph.height()..plot_rows.min(visible)).step_by(2) {
+            display.set_pixel(column, y.saturating_add(row), true);
+        }
+        let columns = glyph.width().min(width.saturating_sub(newest));
+        for gx in 0..columns {
+            for gy in 0..glyph.height().min(plot_rows) {
+                if glyph.get(gx, gy) {
+                    display.set_pixel(column.saturating_add(gx), y.saturating_add(gy), true);
+                }
+            }
+        }
+        display.show_area(BoundingBox {
+            x: column,
+            y,
+            width: columns.max(1),
+            height,
+        })
+    }
+
+    /// Label `value` on the value axis: draw `glyph` left of the chart, one
+    /// column away from it and centred on the row of `value`, then send it.
+    ///
+    /// The glyph is clipped at the left edge of the screen. Being outside
+    /// of the chart, labels are never erased by the pen.
+    pub fn label<DC, RST, S, B, const W: usize, const H: usize>(
+        &self,
+        display: &mut ST7567<DC, RST, S, B, W, H>,
+        value: i32,
+        glyph: &Bitmap,
+    ) -> Result<(), Error<DC, RST, S>>
+    where
+        DC: Pin,
+        RST: Pin,
+        S: Transport,
+        B: PixelBuffer,
+    {
+        if self.area.height == 0 {
+            return Ok(());
+        }
+        let top = self.value_y(value).saturating_sub(glyph.height() / 2);
+        // One column of spacing, and as much of the glyph as fits
+        let right = self.area.x.saturating_sub(1);
+        let columns = glyph.width().min(right);
+        let left = right.saturating_sub(columns);
+        let skipped = glyph.width().saturating_sub(columns);
+        for gx in 0..columns {
+            for gy in 0..glyph.height() {
+                if glyph.get(skipped.saturating_add(gx), gy) {
+                    display.set_pixel(left.saturating_add(gx), top.saturating_add(gy), true);
+                }
+            }
+        }
+        display.show_area(BoundingBox {
+            x: left,
+            y: top,
+            width: columns,
+            height: glyph.height(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::create_test_st7567;
+
+    /// 8 columns, 2 pages: rows 0 to 13 plotted, axis on row 15
+    const AREA: BoundingBox = BoundingBox {
+        x: 8,
+        y: 16,
+        width: 8,
+        height: 16,
+    };
+
+    /// Lit rows of a chart column.
+    fn column<DC, RST, S, B>(display: &ST7567<DC, RST, S, B>, x: usize) -> Vec<usize>
+    where
+        DC: Pin,
+        RST: Pin,
+        S: Transport,
+        B: PixelBuffer,
+    {
+        (0..AREA.height)
+            .filter(|&row| display.get_pixel(AREA.x + x, AREA.y + row))
+            .collect()
+    }
+
+    #[test]
+    fn test_plot() {
+        let mut st7567 = create_test_st7567();
+        st7567.fill(true);
+        let mut chart = StripChart::new(AREA, 0, 13);
+        chart.set_tick_interval(2);
+        for value in [13, 10, 100, -5] {
+            assert!(chart.push(&mut st7567, value).is_ok());
+        }
+        assert_eq!(column(&st7567, 0), [0, 14, 15]);
+        // Joined to the previous sample
+        assert_eq!(column(&st7567, 1), [0, 1, 2, 3, 15]);
+        assert_eq!(column(&st7567, 2), [0, 1, 2, 3, 14, 15]);
+        assert_eq!(column(&st7567, 3), (0..=13).chain([15]).collect::<Vec<_>>());
+        // The gap ahead of the pen
+        assert!(column(&st7567, 4).is_empty() && column(&st7567, 5).is_empty());
+        assert_eq!(column(&st7567, 6).len(), 16);
+        // Outside of the chart
+        assert!(st7567.get_pixel(AREA.x - 1, AREA.y) && st7567.get_pixel(AREA.x, AREA.y - 1));
+    }
+
+    #[test]
+    fn test_sweep() {
+        let mut st7567 = create_test_st7567();
+        let mut chart = StripChart::new(AREA, 0, 13);
+        for _ in 0..7 {
+            assert!(chart.push(&mut st7567, 0).is_ok());
+        }
+        // The gap wraps to the left edge
+        assert_eq!(column(&st7567, 6), [13, 15]);
+        assert!(column(&st7567, 7).is_empty() && column(&st7567, 0).is_empty());
+        assert!(chart.push(&mut st7567, 0).is_ok());
+        assert_eq!(chart.cursor, 0);
+        // Not joined across the edge
+        assert!(chart.push(&mut st7567, 13).is_ok());
+        assert_eq!(column(&st7567, 0), [0, 15]);
+    }
+
+    #[test]
+    fn test_partial_refresh() {
+        let mut st7567 = create_test_st7567();
+        let mut chart = StripChart::new(AREA, 0, 13);
+        assert!(chart.push(&mut st7567, 5).is_ok());
+        let pushed = st7567.spi.get_written_data().len();
+
+        st7567.spi.clear_written_data();
+        assert!(st7567.show().is_ok());
+        let full = st7567.spi.get_written_data().len();
+        // 3 columns of 2 pages, against 8 pages of 128 columns
+        assert!(pushed < 3 * 2 + 20, "{} bytes", pushed);
+        assert!(full > 8 * 128);
+    }
+
+    #[test]
+    fn test_degenerate_areas() {
+        let mut st7567 = create_test_st7567();
+        let flat = BoundingBox {
+            x: 0,
+            y: 0,
+            width: 8,
+            height: 1,
+        };
+        let mut chart = StripChart::new(flat, 0, 10);
+        chart.set_tick_interval(1);
+        assert_eq!(chart.area().height, 0);
+        assert!(chart.push(&mut st7567, 5).is_ok());
+        let glyph = [0x01];
+        let glyph = Bitmap::new(&glyph, 1, 1).unwrap();
+        assert!(chart.mark(&mut st7567, &glyph).is_ok());
+        assert_eq!(st7567.buffer_stats().lit_pixels, 0);
+
+        let huge = BoundingBox {
+            x: 120,
+            y: 40,
+            width: usize::MAX,
+            height: usize::MAX,
+        };
+        let mut chart = StripChart::new(huge, 0, 10);
+        chart.set_tick_interval(1);
+        for value in [0, 10, i32::MIN, i32::MAX] {
+            assert!(chart.push(&mut st7567, value).is_ok());
+        }
+        assert!(chart.mark(&mut st7567, &glyph).is_ok());
+        assert!(chart.label(&mut st7567, 5, &glyph).is_ok());
+        // The maximum on the top row, joined to the minimum far off-screen
+        assert!(!st7567.get_pixel(120, 40) && st7567.get_pixel(121, 40));
+        assert!(st7567.get_pixel(121, 63));
+    }
+
+    #[test]
+    fn test_label() {
+        let mut st7567 = create_test_st7567();
+        let chart = StripChart::new(AREA, 0, 13);
+        // 4 wide, 3 high
+        let glyph = [0x01, 0x07, 0x02, 0x04];
+        let glyph = Bitmap::new(&glyph, 4, 3).unwrap();
+        assert_eq!(chart.value_y(13), AREA.y);
+        assert_eq!(chart.value_y(0), AREA.y + 13);
+        assert!(chart.label(&mut st7567, 7, &glyph).is_ok());
+        // Row 6 of the chart, centred
+        let lit: Vec<(usize, usize)> = (0..AREA.x)
+            .flat_map(|x| (0..64).map(move |y| (x, y)))
+            .filter(|&(x, y)| st7567.get_pixel(x, y))
+            .collect();
+        let row = AREA.y + 6;
+        assert_eq!(
+            lit,
+            [
+                (3, row - 1),
+                (4, row - 1),
+                (4, row),
+                (4, row + 1),
+                (5, row),
+                (6, row + 1)
+            ]
+        );
+
+        // Clipped at the left edge of the screen: 2 columns fit
+        st7567.clear();
+        let chart = StripChart::new(BoundingBox { x: 3, ..AREA }, 0, 13);
+        assert!(chart.label(&mut st7567, 13, &glyph).is_ok());
+        assert_eq!(st7567.buffer_stats().lit_pixels, 2);
+        assert!(st7567.get_pixel(0, AREA.y) && st7567.get_pixel(1, AREA.y + 1));
+    }
+
+    #[test]
+    fn test_mark() {
+        let mut st7567 = create_test_st7567();
+        let mut chart = StripChart::new(AREA, 0, 13);
+        for _ in 0..7 {
+            assert!(chart.push(&mut st7567, 0).is_ok());
+        }
+        let glyph = [0x07, 0x05, 0x07];
+        let glyph = Bitmap::new(&glyph, 3, 3).unwrap();
+        assert!(chart.mark(&mut st7567, &glyph).is_ok());
+        assert_eq!(column(&st7567, 6), [0, 1, 2, 3, 5, 7, 9, 11, 13, 15]);
+        // Clipped to the chart
+        assert_eq!(column(&st7567, 7), [0, 2]);
+        assert!(!st7567.get_pixel(AREA.x + AREA.width, AREA.y));
+    }
+}
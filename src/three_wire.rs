@@ -0,0 +1,211 @@
+//! 3-wire serial interface, for boards without a DC line
+//!
+//! In 3-wire mode the controller reads 9-bit words: the D/C bit first, then
+//! the byte. [`ThreeWireSpi`] packs those words into an ordinary 8-bit SPI
+//! stream, 8 words every 9 bytes, and [`DcFlag`] stands in for the DC pin,
+//! telling it which D/C bit to send:
+//!
+//! ```ignore
+//! let dc = Cell::new(false);
+//! let (spi, dc_pin) = ThreeWireSpi::new(spi, &dc);
+//! let mut display = ST7567::new(spi, dc_pin, rst);
+//! ```
+//!
+//! The interface pins of the module must select the serial 3-wire mode.
+//! The bits after the last word of a transfer are padding, dropped by the
+//! controller as an incomplete word when CS goes high at the end of the
+//! transaction.
+pub use crate::dc_flag::DcFlag;
+use core::cell::Cell;
+use embedded_hal::spi::{self, ErrorKind, ErrorType, Operation, SpiDevice};
+
+/// Words packed per transfer, a multiple of 8 so only the last transfer of
+/// a write ends with padding.
+const CHUNK_WORDS: usize = 32;
+
+/// Error of a [`ThreeWireSpi`] transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreeWireError<E> {
+    /// Error of the underlying SPI device
+    Spi(E),
+    /// The controller cannot be read from in 3-wire mode
+    ReadUnsupported,
+}
+
+impl<E: spi::Error> spi::Error for ThreeWireError<E> {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            ThreeWireError::Spi(e) => e.kind(),
+            ThreeWireError::ReadUnsupported => ErrorKind::Other,
+        }
+    }
+}
+
+/// Writes 9-bit words on an 8-bit SPI device, see the [module](self) docs.
+pub struct ThreeWireSpi<'a, S> {
+    spi: S,
+    dc: &'a Cell<bool>,
+}
+
+impl<'a, S: SpiDevice> ThreeWireSpi<'a, S> {
+    /// Wrap `spi`, returning the DC pin to give the driver along with it.
+    /// `dc` holds the D/C bit between the two.
+    pub fn new(spi: S, dc: &'a Cell<bool>) -> (Self, DcFlag<'a>) {
+        (ThreeWireSpi { spi, dc }, DcFlag(dc))
+    }
+
+    /// The wrapped SPI device.
+    pub fn release(self) -> S {
+        self.spi
+    }
+
+    /// Send `bytes` as 9-bit words with the current D/C bit.
+    fn write(&mut self, bytes: &[u8]) -> Result<(), ThreeWireError<S::Error>> {
+        let dc = u32::from(self.dc.get());
+        let mut packed = [0u8; CHUNK_WORDS * 9 / 8];
+        for words in bytes.chunks(CHUNK_WORDS) {
+            let mut len = 0usize;
+            let mut bits = 0u32;
+            let mut pending = 0u32;
+            for &byte in words {
+                bits = bits << 9 | dc << 8 | u32::from(byte);
+                pending = pending.saturating_add(9);
+                while pending >= 8 {
+                    pending = pending.saturating_sub(8);
+                    if let Some(out) = packed.get_mut(len) {
+                        *out = (bits >> pending) as u8;
+                    }
+                    len = len.saturating_add(1);
+                }
+            }
+            if pending > 0 {
+                if let Some(out) = packed.get_mut(len) {
+                    *out = (bits << 8u32.saturating_sub(pending)) as u8;
+                }
+                len = len.saturating_add(1);
+            }
+            self.spi
+                .write(packed.get(..len).unwrap_or_default())
+                .map_err(ThreeWireError::Spi)?;
+        }
+        Ok(())
+    }
+}
+
+impl<S: SpiDevice> ErrorType for ThreeWireSpi<'_, S> {
+    type Error = ThreeWireError<S::Error>;
+}
+
+impl<S: SpiDevice> SpiDevice for ThreeWireSpi<'_, S> {
+    /// Writes are split in transfers of up to 32 words, so CS may be
+    /// released between them.
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        for operation in operations {
+            match operation {
+                Operation::Write(bytes) => self.write(bytes)?,
+                Operation::DelayNs(ns) => self
+                    .spi
+                    .transaction(&mut [Operation::DelayNs(*ns)])
+                    .map_err(ThreeWireError::Spi)?,
+                _ => return Err(ThreeWireError::ReadUnsupported),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{MockError, MockPin};
+    use crate::{Pin, PinState, ST7567};
+
+    /// Records every write, one entry per transfer.
+    #[derive(Default)]
+    struct TransferLog(Vec<Vec<u8>>);
+
+    impl ErrorType for TransferLog {
+        type Error = MockError;
+    }
+
+    impl SpiDevice for TransferLog {
+        fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), MockError> {
+            for operation in operations {
+                if let Operation::Write(bytes) = operation {
+                    self.0.push(bytes.to_vec());
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// Split the transfers back into (D/C bit, byte) words, dropping the
+    /// padding of each.
+    fn unpack(transfers: &[Vec<u8>]) -> Vec<(bool, u8)> {
+        let mut words = Vec::new();
+        for transfer in transfers {
+            let bits: Vec<bool> = transfer
+                .iter()
+                .flat_map(|byte| (0..8).rev().map(move |bit| byte >> bit & 1 != 0))
+                .collect();
+            for word in bits.chunks_exact(9) {
+                let byte = word[1..].iter().fold(0, |byte, &bit| byte << 1 | bit as u8);
+                words.push((word[0], byte));
+            }
+        }
+        words
+    }
+
+    #[test]
+    fn test_packing() {
+        let dc = Cell::new(false);
+        let (mut spi, mut dc_pin) = ThreeWireSpi::new(TransferLog::default(), &dc);
+        assert!(spi.write(&[0xaf]).is_ok());
+        assert!(dc_pin.set_value(PinState::High).is_ok());
+        assert!(spi.write(&[0xff, 0x01]).is_ok());
+        assert!(spi.write(&[0x5a; 40]).is_ok());
+        let transfers = spi.release().0;
+        assert_eq!(transfers[0], [0b0101_0111, 0b1000_0000]);
+        assert_eq!(transfers[1], [0b1111_1111, 0b1100_0000, 0b0100_0000]);
+        // 32 words in 36 bytes, then 8 words in 9 bytes
+        assert_eq!(transfers[2].len(), 36);
+        assert_eq!(transfers[3].len(), 9);
+        assert_eq!(unpack(&transfers[2..]), [(true, 0x5a); 40]);
+    }
+
+    #[test]
+    fn test_three_wire_driver() {
+        let dc = Cell::new(false);
+        let (spi, dc_pin) = ThreeWireSpi::new(TransferLog::default(), &dc);
+        let mut st7567 = ST7567::new(spi, dc_pin, MockPin::new());
+        assert!(st7567.init().is_ok());
+        let init: Vec<(bool, u8)> = crate::sequence::GFX_HAT
+            .iter()
+            .map(|&byte| (false, byte))
+            .collect();
+        assert_eq!(unpack(&st7567.spi.spi.0), init);
+
+        st7567.spi.spi.0.clear();
+        st7567.set_pixel(0, 0, true);
+        assert!(st7567.show().is_ok());
+        let words = unpack(&st7567.spi.spi.0);
+        let data: Vec<u8> = words
+            .iter()
+            .filter(|(dc, _)| *dc)
+            .map(|(_, byte)| *byte)
+            .collect();
+        assert_eq!(data.len(), crate::BUFFER_SIZE);
+        assert_eq!(data.iter().filter(|&&byte| byte != 0).count(), 1);
+
+        let mut st7567 = ST7567::new(
+            ThreeWireSpi::new(TransferLog::default(), &dc).0,
+            MockPin::new(),
+            MockPin::new(),
+        );
+        let mut read = [0];
+        assert!(matches!(
+            st7567.spi.transaction(&mut [Operation::Read(&mut read)]),
+            Err(ThreeWireError::ReadUnsupported)
+        ));
+    }
+}
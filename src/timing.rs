@@ -0,0 +1,228 @@
+//! Enforced waits, for clone panels missing back-to-back commands
+//!
+//! Some clone controllers drop commands streamed at full SPI speed, or the
+//! first ones sent right after power-on. [`Timed`] wraps the SPI device and
+//! spaces the bytes out according to a [`TimingProfile`], waiting with the
+//! given delay before the first transaction:
+//!
+//! ```ignore
+//! let spi = Timed::new(spi, delay, TimingProfile::STRICT);
+//! let mut display = ST7567::new(spi, dc, rst);
+//! ```
+//!
+//! [`ControllerVariant::timing`] gives the profile matching a controller
+//! variant. Writes go to the SPI device as one transaction per 16 bytes, each
+//! byte followed by the gap as an `Operation::DelayNs`, so a command batch
+//! keeps CS asserted while the bus idles between bytes. A profile without
+//! gap forwards transactions as they are.
+use crate::ControllerVariant;
+use embedded_hal::delay::DelayNs;
+use embedded_hal::spi::{ErrorType, Operation, SpiDevice};
+
+/// Bytes written per transaction of the wrapped device, each followed by
+/// its gap.
+const CHUNK_BYTES: usize = 16;
+
+/// Waits enforced by [`Timed`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TimingProfile {
+    /// Wait after each byte written
+    pub byte_gap_ns: u32,
+    /// Wait before the first transaction, for the supply and the booster to
+    /// settle
+    pub power_on_ms: u32,
+}
+
+impl TimingProfile {
+    /// 1µs after each byte and 50ms after power-on, a wide margin to start
+    /// from and shorten for a given panel.
+    pub const STRICT: TimingProfile = TimingProfile {
+        byte_gap_ns: 1_000,
+        power_on_ms: 50,
+    };
+}
+
+impl ControllerVariant {
+    /// Waits to enforce with [`Timed`] for modules built around `self`.
+    ///
+    /// The Sitronix parts and the UC1701 keep up with the bus, only the
+    /// power-on wait of their datasheets applies. NT7538 modules are mostly
+    /// clones dropping commands at full speed, they get the
+    /// [strict](TimingProfile::STRICT) profile.
+    pub fn timing(self) -> TimingProfile {
+        match self {
+            ControllerVariant::St7567 | ControllerVariant::St7565 => TimingProfile {
+                byte_gap_ns: 0,
+                power_on_ms: 5,
+            },
+            ControllerVariant::Uc1701 => TimingProfile {
+                byte_gap_ns: 0,
+                power_on_ms: 10,
+            },
+            ControllerVariant::Nt7538 => TimingProfile::STRICT,
+        }
+    }
+}
+
+/// An SPI device enforcing a [`TimingProfile`], see the [module](self)
+/// docs.
+pub struct Timed<S, D: DelayNs> {
+    inner: S,
+    delay: D,
+    profile: TimingProfile,
+    powered_on: bool,
+}
+
+impl<S, D: DelayNs> Timed<S, D> {
+    /// Wrap `inner`, for a controller that was just powered on.
+    pub fn new(inner: S, delay: D, profile: TimingProfile) -> Self {
+        Timed {
+            inner,
+            delay,
+            profile,
+            powered_on: false,
+        }
+    }
+
+    /// The supply was cut and restored: wait again before the next
+    /// transaction.
+    pub fn power_cycled(&mut self) {
+        self.powered_on = false;
+    }
+
+    /// Give the SPI device and the delay back.
+    pub fn release(self) -> (S, D) {
+        (self.inner, self.delay)
+    }
+}
+
+impl<S: ErrorType, D: DelayNs> ErrorType for Timed<S, D> {
+    type Error = S::Error;
+}
+
+impl<S: SpiDevice, D: DelayNs> SpiDevice for Timed<S, D> {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        if !self.powered_on {
+            self.delay.delay_ms(self.profile.power_on_ms);
+            self.powered_on = true;
+        }
+        let gap_ns = self.profile.byte_gap_ns;
+        if gap_ns == 0 {
+            return self.inner.transaction(operations);
+        }
+        for operation in operations.iter_mut() {
+            match operation {
+                Operation::Write(bytes) => {
+                    for chunk in bytes.chunks(CHUNK_BYTES) {
+                        let mut spaced: [Operation<'_, u8>; 2 * CHUNK_BYTES] =
+                            core::array::from_fn(|_| Operation::DelayNs(gap_ns));
+                        for (pair, byte) in spaced.chunks_mut(2).zip(chunk) {
+                            if let Some(write) = pair.first_mut() {
+                                *write = Operation::Write(core::slice::from_ref(byte));
+                            }
+                        }
+                        let len = chunk.len().saturating_mul(2);
+                        self.inner
+                            .transaction(spaced.get_mut(..len).unwrap_or_default())?;
+                    }
+                }
+                _ => self.inner.transaction(core::slice::from_mut(operation))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{MockDelay, MockError, MockPin};
+    use crate::{Error, ST7567};
+
+    /// Records each transaction, writes and delays in order.
+    #[derive(Default)]
+    struct OperationLog {
+        transactions: Vec<Vec<(Vec<u8>, u32)>>,
+        fail: bool,
+    }
+
+    impl ErrorType for OperationLog {
+        type Error = MockError;
+    }
+
+    impl SpiDevice for OperationLog {
+        fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), MockError> {
+            if self.fail {
+                return Err(MockError::SpiError);
+            }
+            let log = operations
+                .iter()
+                .map(|operation| match operation {
+                    Operation::Write(bytes) => (bytes.to_vec(), 0),
+                    Operation::DelayNs(ns) => (Vec::new(), *ns),
+                    _ => (Vec::new(), 0),
+                })
+                .collect();
+            self.transactions.push(log);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_timed_writes() {
+        let spi = Timed::new(
+            OperationLog::default(),
+            MockDelay::new(),
+            TimingProfile::STRICT,
+        );
+        let mut st7567 = ST7567::new(spi, MockPin::new(), MockPin::new());
+        assert!(st7567.init().is_ok());
+        assert_eq!(st7567.spi.delay.delays_ns, [50_000_000]);
+        let spaced: Vec<(Vec<u8>, u32)> = crate::sequence::GFX_HAT
+            .iter()
+            .flat_map(|&byte| [(vec![byte], 0), (Vec::new(), 1_000)])
+            .collect();
+        assert_eq!(st7567.spi.inner.transactions, [spaced]);
+
+        // Long writes are split in transactions of 16 bytes
+        st7567.spi.inner.transactions.clear();
+        assert!(st7567.spi.write(&[0xaa; 40]).is_ok());
+        let lengths: Vec<usize> = st7567
+            .spi
+            .inner
+            .transactions
+            .iter()
+            .map(|transaction| transaction.len())
+            .collect();
+        assert_eq!(lengths, [32, 32, 16]);
+
+        // The power-on wait only comes again after a power cycle
+        assert!(st7567.send_commands(&[crate::Command::Nop]).is_ok());
+        assert_eq!(st7567.spi.delay.delays_ns.len(), 1);
+        st7567.spi.power_cycled();
+        assert!(st7567.send_commands(&[crate::Command::Nop]).is_ok());
+        assert_eq!(st7567.spi.delay.delays_ns.len(), 2);
+
+        // Other operations are forwarded as they are
+        st7567.spi.inner.transactions.clear();
+        let mut operations = [Operation::DelayNs(300)];
+        assert!(st7567.spi.transaction(&mut operations).is_ok());
+        assert_eq!(st7567.spi.inner.transactions, [[(Vec::new(), 300)]]);
+
+        st7567.spi.inner.fail = true;
+        assert!(matches!(st7567.init(), Err(Error::SpiError(_))));
+    }
+
+    #[test]
+    fn test_variant_timing() {
+        assert_eq!(ControllerVariant::Nt7538.timing(), TimingProfile::STRICT);
+        let profile = ControllerVariant::St7567.timing();
+        assert_eq!(profile.byte_gap_ns, 0);
+
+        // Without gap, transactions are forwarded as they are
+        let mut spi = Timed::new(OperationLog::default(), MockDelay::new(), profile);
+        assert!(spi.write(&[0xaf, 0xa4]).is_ok());
+        assert_eq!(spi.delay.delays_ns, [5_000_000]);
+        assert_eq!(spi.inner.transactions, [[(vec![0xaf, 0xa4], 0)]]);
+    }
+}
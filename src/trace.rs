@@ -0,0 +1,206 @@
+//! Human readable decoding of recorded ST7567 traffic
+//!
+//! When debugging wiring or reviewing a golden trace, a raw byte dump is hard
+//! to read. Given the bytes sent over SPI, split by level of the DC line, the
+//! decoder turns them back into commands with [`Command::decode`]:
+//!
+//! ```
+//! use st7567::trace::{Pretty, Transfer};
+//!
+//! let page = [0u8; 128];
+//! let transfers = [
+//!     Transfer::Command(&[0xb3, 0x00, 0x10]),
+//!     Transfer::Data(&page),
+//! ];
+//! assert_eq!(
+//!     Pretty(&transfers).to_string(),
+//!     "SetPage(3), SetCol(0), Data[128]"
+//! );
+//! ```
+use crate::command::DecodeError;
+use crate::{Bias, Command};
+use core::fmt;
+
+/// Bytes written in one go, with the DC line low (command) or high (data).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transfer<'a> {
+    Command(&'a [u8]),
+    Data(&'a [u8]),
+}
+
+/// A decoded command, or a run of display data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Entry {
+    Command(Command),
+    /// A two-byte command whose parameter is missing from the transfer
+    Incomplete(u8),
+    /// A two-byte command with its out of range parameter
+    OutOfRange(u8, u8),
+    Unknown(u8),
+    /// Number of display data bytes
+    Data(usize),
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pick = |condition: bool, yes, no| if condition { yes } else { no };
+        match self {
+            Entry::Command(command) => match *command {
+                Command::DisplayOn => write!(f, "DisplayOn"),
+                Command::DisplayOff => write!(f, "DisplayOff"),
+                Command::SetStartLine(line) => write!(f, "SetStartLine({})", line),
+                Command::SetPage(page) => write!(f, "SetPage({})", page),
+                Command::SetColumn(col) => write!(f, "SetCol({})", col),
+                Command::SetColumnLow(nibble) => write!(f, "SetColLow({})", nibble),
+                Command::SetColumnHigh(nibble) => write!(f, "SetColHigh({})", nibble),
+                Command::SetSegmentReverse(reverse) => {
+                    write!(f, "{}", pick(reverse, "SegReverse", "SegNormal"))
+                }
+                Command::SetComReverse(reverse) => {
+                    write!(f, "{}", pick(reverse, "ComReverse", "ComNormal"))
+                }
+                Command::SetInverse(inverse) => {
+                    write!(f, "{}", pick(inverse, "DisplayInverse", "DisplayNormal"))
+                }
+                Command::SetAllPixelsOn(on) => {
+                    write!(f, "{}", pick(on, "AllPixelsOn", "ResumeRam"))
+                }
+                Command::SetBias(Bias::OneNinth) => write!(f, "Bias(1/9)"),
+                Command::SetBias(Bias::OneSeventh) => write!(f, "Bias(1/7)"),
+                Command::SetPowerControl(bits) => write!(f, "PowerControl({:#05b})", bits),
+                Command::SetRegulationRatio(ratio) => write!(f, "RegulationRatio({})", ratio),
+                Command::SetContrast(value) => write!(f, "SetContrast({})", value),
+                Command::SetBooster(value) => write!(f, "SetBooster({})", value),
+                Command::EnterReadModifyWrite => write!(f, "EnterRmw"),
+                Command::ExitReadModifyWrite => write!(f, "ExitRmw"),
+                Command::SoftwareReset => write!(f, "SoftwareReset"),
+                Command::Nop => write!(f, "Nop"),
+            },
+            Entry::Incomplete(opcode) => write!(f, "Incomplete({:#04x})", opcode),
+            Entry::OutOfRange(opcode, value) => {
+                write!(f, "OutOfRange({:#04x}, {})", opcode, value)
+            }
+            Entry::Unknown(byte) => write!(f, "Unknown({:#04x})", byte),
+            Entry::Data(len) => write!(f, "Data[{}]", len),
+        }
+    }
+}
+
+/// Iterator over the entries decoded from a list of transfers.
+pub struct Entries<'a> {
+    transfers: core::slice::Iter<'a, Transfer<'a>>,
+    pending: &'a [u8],
+}
+
+/// Decode a recorded list of transfers.
+pub fn decode<'a>(transfers: &'a [Transfer<'a>]) -> Entries<'a> {
+    Entries {
+        transfers: transfers.iter(),
+        pending: &[],
+    }
+}
+
+impl Iterator for Entries<'_> {
+    type Item = Entry;
+
+    fn next(&mut self) -> Option<Entry> {
+        while self.pending.is_empty() {
+            match self.transfers.next()? {
+                Transfer::Command(bytes) => self.pending = bytes,
+                Transfer::Data(bytes) => return Some(Entry::Data(bytes.len())),
+            }
+        }
+        let opcode = self.pending.first().copied().unwrap_or_default();
+        let (entry, used) = match Command::decode(self.pending) {
+            Ok((command, len)) => (Entry::Command(command), len),
+            Err(DecodeError::UnknownOpcode) => (Entry::Unknown(opcode), 1),
+            Err(DecodeError::MissingParameter) => (Entry::Incomplete(opcode), 1),
+            Err(DecodeError::ParameterOutOfRange) => {
+                let value = self.pending.get(1).copied().unwrap_or_default();
+                (Entry::OutOfRange(opcode, value), 2)
+            }
+        };
+        self.pending = self.pending.get(used..).unwrap_or_default();
+        Some(entry)
+    }
+}
+
+/// Displays a list of transfers as comma separated commands.
+pub struct Pretty<'a>(pub &'a [Transfer<'a>]);
+
+impl fmt::Display for Pretty<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, entry) in decode(self.0).enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::*;
+
+    #[test]
+    fn test_decode_init_sequence() {
+        let init = [
+            ST7567_BIAS_1_7,
+            ST7567_SEG_DIR_NORMAL,
+            ST7567_SETCOMREVERSE,
+            ST7567_DISPNORMAL,
+            ST7567_SETSTARTLINE,
+            ST7567_POWERCTRL,
+            ST7567_REG_RATIO | 3,
+            ST7567_DISPON,
+            ST7567_SETCONTRAST,
+            40,
+        ];
+        let transfers = [Transfer::Command(&init)];
+        assert_eq!(
+            Pretty(&transfers).to_string(),
+            "Bias(1/7), SegNormal, ComReverse, DisplayNormal, SetStartLine(0), \
+             PowerControl(0b111), RegulationRatio(3), DisplayOn, SetContrast(40)"
+        );
+    }
+
+    #[test]
+    fn test_decode_columns_and_data() {
+        let data = [0xffu8; 4];
+        let transfers = [
+            Transfer::Command(&[0x14, 0x02]),
+            Transfer::Data(&data),
+            Transfer::Command(&[0x05]),
+            Transfer::Command(&[0x1f, 0xb8]),
+            Transfer::Data(&[]),
+        ];
+        let entries: Vec<Entry> = decode(&transfers).collect();
+        assert_eq!(
+            entries,
+            vec![
+                Entry::Command(Command::SetColumn(0x42)),
+                Entry::Data(4),
+                Entry::Command(Command::SetColumnLow(5)),
+                Entry::Unknown(0x1f),
+                Entry::Command(Command::SetPage(8)),
+                Entry::Data(0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_malformed() {
+        let transfers = [
+            Transfer::Command(&[ST7567_SETCONTRAST]),
+            Transfer::Command(&[0x30, 0xff]),
+            Transfer::Command(&[ST7567_SETBOOSTER, 0x02]),
+        ];
+        assert_eq!(
+            Pretty(&transfers).to_string(),
+            "Incomplete(0x81), Unknown(0x30), Unknown(0xff), OutOfRange(0xf8, 2)"
+        );
+    }
+}
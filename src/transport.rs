@@ -0,0 +1,34 @@
+//! Bus carrying the commands and display data of the driver
+//!
+//! The driver writes through a [`Transport`], after setting the DC pin low
+//! for commands and high for display data. Every `SpiDevice` is a transport,
+//! so HAL buses and the wrappers of this crate can be given to
+//! [`ST7567::new`](crate::ST7567::new) as they are. Buses telling commands
+//! from data themselves, like the `display-interface` ones, implement the
+//! two methods separately and leave the DC pin unused.
+use embedded_hal::spi::SpiDevice;
+
+/// Bus the driver writes commands and display data to, see the
+/// [module](self) docs.
+pub trait Transport {
+    type Error;
+
+    /// Write command bytes, the DC pin was just set low.
+    fn write_commands(&mut self, bytes: &[u8]) -> Result<(), Self::Error>;
+
+    /// Write display data bytes, the DC pin was just set high.
+    fn write_data(&mut self, bytes: &[u8]) -> Result<(), Self::Error>;
+}
+
+/// An SPI device writes both the same way, the DC pin selects which.
+impl<S: SpiDevice> Transport for S {
+    type Error = S::Error;
+
+    fn write_commands(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.write(bytes)
+    }
+
+    fn write_data(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.write(bytes)
+    }
+}
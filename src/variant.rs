@@ -0,0 +1,118 @@
+//! Init profiles for register compatible controllers
+//!
+//! The ST7565, UC1701 and NT7538 understand the same commands as the ST7567
+//! but ship on modules wanting a different bias, booster level or
+//! regulation ratio. [`ControllerVariant`] selects a known-good starting
+//! point, to refine with [`set_contrast`](crate::ST7567::set_contrast) or
+//! [`set_column_offset`](crate::ST7567::set_column_offset) for a given
+//! module.
+//!
+//! Every profile uses column offset 0. Where the glass starts in the 132
+//! column RAM depends on the module, not on the controller: ST7565 modules
+//! such as the NHD C12864 start at column 4, the EA DOGM128 and the UC1701
+//! "mini 12864" at column 0. A picture shifted by 4 columns, with garbage on
+//! the other edge, calls for an offset of 4:
+//!
+//! ```ignore
+//! let mut display = ST7567::builder()
+//!     .controller_variant(ControllerVariant::St7565)
+//!     .column_offset(4)
+//!     .build(spi, dc, rst)?;
+//! ```
+//!
+//! With the `timing` feature, `ControllerVariant::timing()` gives the waits
+//! to enforce between bytes and after power-on for each variant.
+use crate::transport::Transport;
+use crate::{Bias, InitSequence, Pin, PixelBuffer, ST7567};
+
+/// A controller of the 128x64 LCD family, see the [module](self) docs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ControllerVariant {
+    /// Sitronix ST7567, as on the Pimoroni GFX HAT
+    #[default]
+    St7567,
+    /// Sitronix ST7565R
+    St7565,
+    /// UltraChip UC1701, as on "mini 12864" modules
+    Uc1701,
+    /// Novatek NT7538
+    Nt7538,
+}
+
+/// Settings sent by `init()` for a variant.
+pub(crate) struct Profile {
+    pub(crate) bias: Bias,
+    pub(crate) regulation_ratio: u8,
+    pub(crate) contrast: u8,
+    pub(crate) column_offset: u8,
+    pub(crate) init_sequence: InitSequence,
+}
+
+/// Booster at 4x, the level these modules are designed for.
+const BOOSTER_4X: &[u8] = &[0xf8, 0x00];
+
+impl ControllerVariant {
+    pub(crate) fn profile(self) -> Profile {
+        let (bias, regulation_ratio, contrast, init_sequence) = match self {
+            ControllerVariant::St7567 => (Bias::OneSeventh, 3, 40, InitSequence::BuiltIn),
+            ControllerVariant::St7565 => (Bias::OneSeventh, 6, 24, InitSequence::BuiltIn),
+            ControllerVariant::Uc1701 => (Bias::OneNinth, 3, 39, InitSequence::Extend(BOOSTER_4X)),
+            ControllerVariant::Nt7538 => (Bias::OneNinth, 4, 32, InitSequence::Extend(BOOSTER_4X)),
+        };
+        Profile {
+            bias,
+            regulation_ratio,
+            contrast,
+            column_offset: 0,
+            init_sequence,
+        }
+    }
+}
+
+impl<DC: Pin, RST: Pin, S: Transport, B: PixelBuffer, const W: usize, const H: usize>
+    ST7567<DC, RST, S, B, W, H>
+{
+    /// Use the bias, regulation ratio, contrast and init sequence of
+    /// `variant`, from the next `init()` on. The column offset is reset to 0,
+    /// see the [module](self) docs.
+    ///
+    /// Replaces any custom init sequence set before. The contrast is capped by
+    /// [`set_max_contrast`](Self::set_max_contrast).
+    pub fn set_controller_variant(&mut self, variant: ControllerVariant) {
+        let profile = variant.profile();
+        self.bias = profile.bias;
+        self.regulation_ratio = profile.regulation_ratio;
+        self.contrast = profile.contrast.min(self.max_contrast);
+        self.column_offset = profile.column_offset;
+        self.set_init_sequence(profile.init_sequence);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::*;
+    use crate::tests::create_test_st7567;
+
+    #[test]
+    fn test_controller_variant() {
+        let mut st7567 = create_test_st7567();
+        st7567.set_controller_variant(ControllerVariant::Uc1701);
+        assert!(st7567.init().is_ok());
+        let written_data = st7567.spi.get_written_data();
+        assert_eq!(written_data[0], ST7567_BIAS_1_9);
+        assert_eq!(
+            written_data[written_data.len() - 4..],
+            [0x81, 39, 0xf8, 0x00]
+        );
+
+        st7567.spi.clear_written_data();
+        st7567.set_controller_variant(ControllerVariant::St7567);
+        assert!(st7567.init().is_ok());
+        assert_eq!(st7567.spi.get_written_data(), crate::sequence::GFX_HAT);
+
+        st7567.set_max_contrast(20);
+        st7567.set_controller_variant(ControllerVariant::Uc1701);
+        assert_eq!(st7567.contrast(), 20);
+    }
+}